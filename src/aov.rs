@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{color::Color, framebuffer::Framebuffer};
+
+// Passe auxiliaire (AOV) rendue à côté de l'image, pour la composition et la mise au point
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Aov {
+    Depth,    // Distance du point touché au plan de la caméra, le long de l'axe de visée
+    Normal,   // Normale d'ombrage, dans le repère de la scène
+    Albedo,   // Couleur de la surface, sans éclairage
+    ObjectId, // Indice de l'objet touché plus un, 0 pour le fond
+    Uv,       // Coordonnées de texture
+    Direct,   // Lumière reçue de la lumière ponctuelle
+    Indirect, // Lumière ambiante ou reçue du fond
+    Shadow,   // 1 dans l'ombre de la lumière ponctuelle
+    Samples,  // Nombre d'échantillons du pixel, utile avec l'échantillonnage adaptatif
+}
+
+impl Aov {
+    pub const ALL: [Aov; 9] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::Uv,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Shadow,
+        Aov::Samples,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::Uv => "uv",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Shadow => "shadow",
+            Aov::Samples => "samples",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Aov> {
+        Aov::ALL.into_iter().find(|aov| aov.name() == name)
+    }
+
+    // Noms des canaux dans un EXR, pris dans l'ordre R, G, B du framebuffer de la passe
+    pub fn channels(self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal => &["X", "Y", "Z"],
+            Aov::Albedo | Aov::Direct | Aov::Indirect => &["R", "G", "B"],
+            Aov::ObjectId => &["id"],
+            Aov::Uv => &["U", "V"],
+            Aov::Shadow => &["Y"],
+            Aov::Samples => &["count"],
+        }
+    }
+
+    // La profondeur et l'identifiant n'ont pas de sens une fois moyennés : ils viennent
+    // du premier échantillon du pixel. Le nombre d'échantillons est compté, pas moyenné.
+    // Les autres passes sont moyennées comme l'image.
+    pub fn filtered(self) -> bool {
+        !matches!(self, Aov::Depth | Aov::ObjectId | Aov::Samples)
+    }
+
+    // Version affichable de la passe pour les formats 8 bits (PPM, PNG) : profondeur
+    // ramenée du blanc (proche) au noir (loin), normales de [-1, 1] vers [0, 1],
+    // une couleur par objet, carte de chaleur du bleu (le moins d'échantillons) au rouge
+    pub fn preview(self, pass: &Framebuffer) -> Framebuffer {
+        let (width, height) = (pass.width(), pass.height());
+        let depths = (0..height)
+            .flat_map(|y| (0..width).map(move |x| pass.pixel(x, y)[0]))
+            .filter(|d| d.is_finite());
+        let (near, far) = depths.fold((f32::INFINITY, 0.0f32), |(near, far), d| {
+            (near.min(d), far.max(d))
+        });
+        let (least, most) = pass
+            .pixels()
+            .fold((f32::INFINITY, 0.0f32), |(least, most), p| {
+                (least.min(p[0]), most.max(p[0]))
+            });
+
+        let mut preview = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let [r, g, b, a] = pass.pixel(x, y);
+                let color = match self {
+                    Aov::Depth if r.is_finite() => {
+                        let d = 1.0 - (r - near) / (far - near).max(f32::EPSILON);
+                        [d, d, d]
+                    }
+                    Aov::Depth => [0.0; 3],
+                    Aov::Normal if a > 0.0 => [r, g, b].map(|c| 0.5 * c / a + 0.5),
+                    Aov::Normal => [0.0; 3],
+                    Aov::ObjectId => id_color(r as usize),
+                    Aov::Shadow => [r; 3],
+                    Aov::Samples => heat_color((r - least) / (most - least).max(1.0)),
+                    _ => [r, g, b],
+                };
+                let [r, g, b] = color;
+                preview.set(x, y, [r, g, b, a]);
+            }
+        }
+        preview
+    }
+}
+
+// Couleur vive et stable pour chaque identifiant, noir pour le fond
+fn id_color(id: usize) -> [f32; 3] {
+    if id == 0 {
+        return [0.0; 3];
+    }
+    // Teintes réparties par le nombre d'or
+    let hue = (id as f64 * 0.618_033_988_75).fract() * 6.0;
+    let c = Color::new(
+        (hue - 3.0).abs() - 1.0,
+        2.0 - (hue - 2.0).abs(),
+        2.0 - (hue - 4.0).abs(),
+    );
+    [c.x(), c.y(), c.z()].map(|v| (0.25 + 0.75 * v.clamp(0.0, 1.0)) as f32)
+}
+
+// Dégradé bleu, cyan, vert, jaune, rouge pour t de 0 à 1
+fn heat_color(t: f32) -> [f32; 3] {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    [0, 1, 2].map(|c| STOPS[i][c] + f * (STOPS[i + 1][c] - STOPS[i][c]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for aov in Aov::ALL {
+            assert_eq!(Aov::from_name(aov.name()), Some(aov));
+            assert_eq!(
+                serde_json::to_value(aov).unwrap(),
+                serde_json::json!(aov.name())
+            );
+        }
+        assert_eq!(Aov::from_name("beauty"), None);
+    }
+
+    #[test]
+    fn depth_preview_goes_from_white_to_black() {
+        let mut pass = Framebuffer::new(3, 1);
+        pass.set(0, 0, [2.0, 2.0, 2.0, 1.0]);
+        pass.set(1, 0, [6.0, 6.0, 6.0, 1.0]);
+        pass.set(2, 0, [f32::INFINITY, f32::INFINITY, f32::INFINITY, 0.0]);
+
+        let preview = Aov::Depth.preview(&pass);
+        assert_eq!(preview.pixel(0, 0), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(preview.pixel(1, 0), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(preview.pixel(2, 0), [0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn sample_counts_are_a_heatmap() {
+        let mut pass = Framebuffer::new(3, 1);
+        for (x, count) in [4.0, 10.0, 16.0].into_iter().enumerate() {
+            pass.set(x, 0, [count, count, count, 1.0]);
+        }
+        let preview = Aov::Samples.preview(&pass);
+        assert_eq!(preview.pixel(0, 0), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(preview.pixel(2, 0), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(heat_color(0.5), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn ids_get_distinct_colors() {
+        assert_eq!(id_color(0), [0.0; 3]);
+        let colors: Vec<_> = (1..10).map(id_color).collect();
+        for (i, a) in colors.iter().enumerate() {
+            for b in &colors[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
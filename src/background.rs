@@ -0,0 +1,522 @@
+use std::{path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::{self, Color},
+    common::{self, Cache, PI},
+    error::Error,
+    image::Image,
+    vec3::{self, Vec3},
+};
+
+// Ce que voient les rayons qui ne touchent aucun objet. Le fond éclaire aussi la scène.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Background {
+    Solid {
+        #[serde(deserialize_with = "color::deserialize")]
+        color: Color,
+    },
+    // Dégradé vertical, de `bottom` (vers le bas) à `top` (vers le haut)
+    Gradient {
+        #[serde(deserialize_with = "color::deserialize")]
+        bottom: Color,
+        #[serde(deserialize_with = "color::deserialize")]
+        top: Color,
+    },
+    Hdr(HdrEnvironment),
+    Sky(PreethamSky),
+}
+
+impl Background {
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        if let Background::Hdr(hdr) = self {
+            hdr.load(base_dir)?;
+        }
+        Ok(())
+    }
+
+    // Radiance reçue depuis la direction donnée
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let d = vec3::unit_vector(direction);
+        match self {
+            Background::Solid { color } => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (d.y() + 1.0);
+                (1.0 - t) * *bottom + t * *top
+            }
+            Background::Hdr(hdr) => hdr.radiance(d),
+            Background::Sky(sky) => sky.radiance(d),
+        }
+    }
+
+    // Tire une direction d'éclairage pour un point de normale `normal`, à partir de deux
+    // nombres `u` dans [0, 1) donnés par l'échantillonneur.
+    // Renvoie la direction et sa densité de probabilité (par angle solide).
+    pub fn sample(&self, normal: Vec3, u: (f64, f64)) -> (Vec3, f64) {
+        self.sample_map(u).unwrap_or_else(|| {
+            let direction = cosine_direction(normal, u);
+            let pdf = vec3::dot(direction, normal).max(0.0) / PI;
+            (direction, pdf)
+        })
+    }
+
+    // Tire une direction pour un point de volume (fonction de phase isotrope)
+    pub fn sample_sphere(&self, u: (f64, f64)) -> (Vec3, f64) {
+        self.sample_map(u)
+            .unwrap_or_else(|| (sphere_direction(u), 1.0 / (4.0 * PI)))
+    }
+
+    // Échantillonnage préférentiel d'une carte d'environnement chargée, None sinon
+    fn sample_map(&self, u: (f64, f64)) -> Option<(Vec3, f64)> {
+        match self {
+            Background::Hdr(hdr) => hdr.sample(u),
+            _ => None,
+        }
+    }
+}
+
+// Direction uniforme sur la sphère unité
+fn sphere_direction((u1, u2): (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let r = f64::sqrt((1.0 - z * z).max(0.0));
+    let phi = 2.0 * PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Direction tirée proportionnellement au cosinus autour de la normale
+fn cosine_direction(normal: Vec3, (r1, r2): (f64, f64)) -> Vec3 {
+    let phi = 2.0 * PI * r1;
+    let x = f64::cos(phi) * r2.sqrt();
+    let y = f64::sin(phi) * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+
+    // Repère orthonormé autour de la normale
+    let w = vec3::unit_vector(normal);
+    let a = if w.x().abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let v = vec3::unit_vector(vec3::cross(w, a));
+    let u = vec3::cross(w, v);
+    x * u + y * v + z * w
+}
+
+// Carte d'environnement équirectangulaire lue depuis un fichier Radiance .hdr
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct HdrEnvironment {
+    pub file: String,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+    #[serde(default)]
+    pub rotation: f64, // Rotation autour de l'axe Y, en degrés
+    #[serde(skip)]
+    pub map: Cache<Option<Arc<EnvironmentMap>>>,
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+impl HdrEnvironment {
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        let image = Image::load(&base_dir.join(&self.file))?;
+        *self.map = Some(Arc::new(EnvironmentMap::new(image)));
+        Ok(())
+    }
+
+    fn radiance(&self, d: Vec3) -> Color {
+        match &*self.map {
+            Some(map) => self.intensity * map.lookup(rotate_y(d, -self.rotation)),
+            None => Color::new(1.0, 0.0, 1.0),
+        }
+    }
+
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, f64)> {
+        let (direction, pdf) = self.map.as_ref()?.sample(u);
+        Some((rotate_y(direction, self.rotation), pdf))
+    }
+}
+
+fn rotate_y(d: Vec3, degrees: f64) -> Vec3 {
+    if degrees == 0.0 {
+        return d;
+    }
+    let (sin, cos) = common::degrees_to_radians(degrees).sin_cos();
+    Vec3::new(cos * d.x() + sin * d.z(), d.y(), -sin * d.x() + cos * d.z())
+}
+
+// Image équirectangulaire et distributions pour l'échantillonnage préférentiel.
+// Chaque pixel est tiré proportionnellement à sa luminance pondérée par sin(theta).
+pub struct EnvironmentMap {
+    image: Image,
+    marginal_cdf: Vec<f64>,         // Une entrée par ligne (+1)
+    conditional_cdf: Vec<Vec<f64>>, // Pour chaque ligne, une entrée par colonne (+1)
+    total: f64,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image) -> EnvironmentMap {
+        let (width, height) = (image.width, image.height);
+        let mut conditional_cdf = Vec::with_capacity(height);
+        let mut row_sums = Vec::with_capacity(height);
+
+        for j in 0..height {
+            let sin_theta = f64::sin(PI * (j as f64 + 0.5) / height as f64);
+            let mut cdf = Vec::with_capacity(width + 1);
+            let mut sum = 0.0;
+            cdf.push(0.0);
+            for i in 0..width {
+                sum += luminance(image.pixel(i, j)) * sin_theta;
+                cdf.push(sum);
+            }
+            conditional_cdf.push(cdf);
+            row_sums.push(sum);
+        }
+
+        let mut marginal_cdf = Vec::with_capacity(height + 1);
+        let mut total = 0.0;
+        marginal_cdf.push(0.0);
+        for sum in &row_sums {
+            total += sum;
+            marginal_cdf.push(total);
+        }
+
+        EnvironmentMap {
+            image,
+            marginal_cdf,
+            conditional_cdf,
+            total,
+        }
+    }
+
+    fn lookup(&self, d: Vec3) -> Color {
+        if self.image.width == 0 || self.image.height == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let (u, v) = direction_to_uv(d);
+        let i = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let j = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        self.image.pixel(i, j)
+    }
+
+    // Densité (par angle solide) du tirage de `sample` dans la direction d
+    pub fn pdf(&self, d: Vec3) -> f64 {
+        if self.total <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = direction_to_uv(d);
+        let (width, height) = (self.image.width, self.image.height);
+        let i = ((u * width as f64) as usize).min(width - 1);
+        let j = ((v * height as f64) as usize).min(height - 1);
+        let weight = self.conditional_cdf[j][i + 1] - self.conditional_cdf[j][i];
+        let sin_theta = f64::sin(PI * v);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let pdf_uv = weight / self.total * (width * height) as f64;
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+
+    // Une ligne puis une colonne tirées selon leur poids ; la place de chaque nombre
+    // dans l'intervalle tiré donne la position dans le pixel
+    fn sample(&self, (u1, u2): (f64, f64)) -> (Vec3, f64) {
+        if self.total <= 0.0 {
+            // Carte noire : aucune direction n'apporte de lumière
+            return (Vec3::new(0.0, 1.0, 0.0), 0.0);
+        }
+
+        let (j, dv) = sample_cdf(&self.marginal_cdf, u1 * self.total);
+        let row = &self.conditional_cdf[j];
+        let (i, du) = sample_cdf(row, u2 * row[row.len() - 1]);
+
+        let u = (i as f64 + du) / self.image.width as f64;
+        let v = (j as f64 + dv) / self.image.height as f64;
+        let direction = uv_to_direction(u, v);
+        (direction, self.pdf(direction))
+    }
+}
+
+// Indice de l'intervalle [cdf[k], cdf[k + 1]) contenant la valeur, et position de la
+// valeur dans cet intervalle, dans [0, 1)
+fn sample_cdf(cdf: &[f64], value: f64) -> (usize, f64) {
+    let k = cdf.partition_point(|c| *c <= value);
+    let k = k.saturating_sub(1).min(cdf.len() - 2);
+    let width = cdf[k + 1] - cdf[k];
+    let offset = if width > 0.0 {
+        ((value - cdf[k]) / width).clamp(0.0, 1.0 - f64::EPSILON)
+    } else {
+        0.5
+    };
+    (k, offset)
+}
+
+// u : azimut dans [0, 1], v : angle depuis le zénith dans [0, 1]
+fn direction_to_uv(d: Vec3) -> (f64, f64) {
+    let phi = f64::atan2(d.x(), -d.z());
+    let theta = f64::acos(common::clamp(d.y(), -1.0, 1.0));
+    ((phi + PI) / (2.0 * PI), theta / PI)
+}
+
+fn uv_to_direction(u: f64, v: f64) -> Vec3 {
+    let phi = u * 2.0 * PI - PI;
+    let theta = v * PI;
+    Vec3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+// Ciel analytique de Preetham, Shirley et Smits (1999).
+// La luminance est normalisée pour valoir 1 au zénith, puis multipliée par `intensity`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct PreethamSky {
+    pub sun_direction: Vec3,
+    #[serde(default = "default_turbidity")]
+    pub turbidity: f64, // Trouble atmosphérique, de 2 (très clair) à 10 (brumeux)
+    #[serde(default = "default_sky_intensity")]
+    pub intensity: f64,
+    #[serde(default = "default_ground", deserialize_with = "color::deserialize")]
+    pub ground: Color, // Radiance sous l'horizon
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+// Garde l'horizon et les abords du soleil sous la saturation
+fn default_sky_intensity() -> f64 {
+    0.35
+}
+
+fn default_ground() -> Color {
+    Color::new(0.2, 0.2, 0.2)
+}
+
+// Coefficients A à E de la fonction de distribution de Perez
+type Perez = [f64; 5];
+
+impl PreethamSky {
+    fn radiance(&self, d: Vec3) -> Color {
+        if d.y() < 0.0 {
+            return self.intensity * self.ground;
+        }
+
+        let sun = vec3::unit_vector(self.sun_direction);
+        let t = self.turbidity;
+        // Le soleil reste juste au-dessus de l'horizon pour que le modèle reste défini
+        let theta_s = f64::acos(common::clamp(sun.y(), 0.0, 1.0)).min(PI / 2.0 - 1e-3);
+        let theta = f64::acos(common::clamp(d.y(), 1e-3, 1.0));
+        let gamma = f64::acos(common::clamp(vec3::dot(d, sun), -1.0, 1.0));
+
+        let perez_y = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let perez_x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let perez_yc = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        // Chromaticité au zénith
+        let (t2, s) = (t * t, [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0]);
+        let poly = |m: [[f64; 4]; 3]| -> f64 {
+            let row = |r: [f64; 4]| r[0] * s[0] + r[1] * s[1] + r[2] * s[2] + r[3] * s[3];
+            t2 * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let x_zenith = poly([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y_zenith = poly([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let relative = |coeffs: Perez| perez(coeffs, theta, gamma) / perez(coeffs, 0.0, theta_s);
+        let luminance = relative(perez_y);
+        let x = x_zenith * relative(perez_x);
+        let y = y_zenith * relative(perez_yc);
+
+        self.intensity * xyy_to_rgb(x, y, luminance)
+    }
+}
+
+fn perez(c: Perez, theta: f64, gamma: f64) -> f64 {
+    (1.0 + c[0] * f64::exp(c[1] / theta.cos()))
+        * (1.0 + c[2] * f64::exp(c[3] * gamma) + c[4] * gamma.cos() * gamma.cos())
+}
+
+// Chromaticité xyY vers RGB linéaire (primaires sRGB)
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    fn environment(image: Image, rotation: f64) -> HdrEnvironment {
+        HdrEnvironment {
+            file: String::new(),
+            intensity: 1.0,
+            rotation,
+            map: Cache(Some(Arc::new(EnvironmentMap::new(image)))),
+        }
+    }
+
+    fn image(width: usize, height: usize, pixel: impl Fn(usize, usize) -> f64) -> Image {
+        let pixels = (0..width * height)
+            .map(|k| {
+                let value = pixel(k % width, k / width);
+                Color::new(value, value, value)
+            })
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn rgbe_scanlines_are_decoded() {
+        // 8 x 2 : une ligne non compressée puis une ligne RLE
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        for i in 0..8u8 {
+            data.extend([10 * i + 1, 20, 30, 128]);
+        }
+        data.extend([2, 2, 0, 8]);
+        data.extend([128 + 8, 100]); // R : une répétition
+        data.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]); // G : valeurs littérales
+        data.extend([128 + 4, 50, 128 + 4, 60]); // B : deux répétitions
+        data.extend([128 + 8, 130]); // E
+
+        let dir = std::env::temp_dir().join(format!("rt-rgbe-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("fixture.hdr"), &data).unwrap();
+        let mut hdr = HdrEnvironment {
+            file: "fixture.hdr".to_string(),
+            intensity: 1.0,
+            rotation: 0.0,
+            map: Cache::default(),
+        };
+        let loaded = hdr.load(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        loaded.unwrap();
+
+        let image = &hdr.map.as_ref().unwrap().image;
+        assert_eq!((image.width, image.height), (8, 2));
+        let rgbe = |rgb: [f64; 3], e: i32| rgb.map(|c| (c + 0.5) * 2f64.powi(e - 136));
+        let pixel = |i: usize, j: usize| {
+            let c = image.pixel(i, j);
+            [c.x(), c.y(), c.z()]
+        };
+        for i in 0..8 {
+            assert_eq!(pixel(i, 0), rgbe([10.0 * i as f64 + 1.0, 20.0, 30.0], 128));
+            let blue = if i < 4 { 50.0 } else { 60.0 };
+            assert_eq!(pixel(i, 1), rgbe([100.0, i as f64, blue], 130));
+        }
+
+        // Fichier tronqué au milieu de la ligne RLE
+        let truncated = &data[..data.len() - 3];
+        assert!(Image::from_data_uri(&format!(
+            "data:image/vnd.radiance;base64,{}",
+            STANDARD.encode(truncated)
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let map = EnvironmentMap::new(image(16, 8, |_, _| 1.0));
+        // Intégrale sur (u, v) de pdf * |dω / du dv| = 2 pi^2 sin(theta)
+        let (nu, nv) = (300, 150);
+        let mut integral = 0.0;
+        for j in 0..nv {
+            for i in 0..nu {
+                let (u, v) = ((i as f64 + 0.5) / nu as f64, (j as f64 + 0.5) / nv as f64);
+                let jacobian = 2.0 * PI * PI * f64::sin(PI * v);
+                integral += map.pdf(uv_to_direction(u, v)) * jacobian / (nu * nv) as f64;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-2, "{}", integral);
+
+        // Carte constante : tirage presque uniforme sur la sphère
+        let uniform = 1.0 / (4.0 * PI);
+        let pdf = map.pdf(Vec3::new(1.0, 0.0, 0.0));
+        assert!((pdf / uniform - 1.0).abs() < 0.05, "{}", pdf);
+    }
+
+    #[test]
+    fn sampled_pdf_matches_pdf() {
+        // Dégradé avec un point très lumineux, carte tournée de 30°
+        let hdr = environment(
+            image(32, 16, |i, j| {
+                if (i, j) == (5, 4) {
+                    500.0
+                } else {
+                    0.1 + j as f64
+                }
+            }),
+            30.0,
+        );
+        let map = hdr.map.0.clone().unwrap();
+        let background = Background::Hdr(hdr);
+        let n = 64;
+        let mut bright = 0;
+        for a in 0..n {
+            for b in 0..n {
+                let u = ((a as f64 + 0.5) / n as f64, (b as f64 + 0.5) / n as f64);
+                let (direction, pdf) = background.sample(Vec3::new(0.0, 1.0, 0.0), u);
+                assert!(pdf > 0.0);
+                let expected = map.pdf(rotate_y(direction, -30.0));
+                assert!(
+                    (pdf - expected).abs() <= 1e-9 * expected,
+                    "{} != {}",
+                    pdf,
+                    expected
+                );
+                if background.radiance(direction).x() == 500.0 {
+                    bright += 1;
+                }
+            }
+        }
+        // Le point lumineux est tiré en proportion de sa part de l'énergie de la carte
+        let share = (map.conditional_cdf[4][6] - map.conditional_cdf[4][5]) / map.total;
+        let fraction = bright as f64 / (n * n) as f64;
+        assert!((fraction - share).abs() < 0.01, "{} != {}", fraction, share);
+    }
+}
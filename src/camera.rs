@@ -3,10 +3,14 @@ use crate::vec3::{Point3, Vec3};
 use crate::{common, vec3};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct Camera {
     pub origin: Point3,
     pub look_at: Point3,
+    #[serde(default = "default_fov")]
+    pub fov: f64, // Champ de vision vertical, en degrés
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stereo: Option<Stereo>,
     #[serde(skip)]
     pub lower_left_corner: Point3,
     #[serde(skip)]
@@ -15,6 +19,34 @@ pub struct Camera {
     pub vertical: Vec3,
 }
 
+fn default_fov() -> f64 {
+    90.0
+}
+
+// Disposition des deux images d'une paire stéréo
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum StereoLayout {
+    #[default]
+    SideBySide, // Gauche et droite dans une seule image deux fois plus large
+    Separate,   // Un fichier par œil
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub interocular_distance: f64, // Distance entre les deux yeux
+    #[serde(default)]
+    pub layout: StereoLayout,
+}
+
+// Caméra nommée de la liste `cameras` de la scène
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NamedCamera {
+    pub name: String,
+    #[serde(flatten)]
+    pub camera: Camera,
+}
+
 impl Camera {
     pub fn new(
         lookfrom: Point3,
@@ -40,12 +72,28 @@ impl Camera {
         Camera {
             origin,
             look_at: lookat,
+            fov: vfov,
+            stereo: None,
             lower_left_corner,
             horizontal,
             vertical,
         }
     }
 
+    // Positions (origine, cible) des yeux gauche et droit.
+    // Les deux yeux sont décalés parallèlement le long de l'axe horizontal
+    // de la caméra, ce qui évite la distorsion verticale d'un rig convergent.
+    pub fn eye_positions(&self, vup: Vec3, interocular_distance: f64) -> [(Point3, Point3); 2] {
+        let w = vec3::unit_vector(self.origin - self.look_at);
+        let u = vec3::unit_vector(vec3::cross(vup, w));
+        let offset = u * (interocular_distance / 2.0);
+
+        [
+            (self.origin - offset, self.look_at - offset),
+            (self.origin + offset, self.look_at + offset),
+        ]
+    }
+
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
         Ray::new(
             self.origin,
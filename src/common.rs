@@ -1,9 +1,37 @@
 use rand::Rng;
+use std::ops::{Deref, DerefMut};
 
 // Constants
 
 pub use std::f64::consts::PI;
-pub use std::f64::INFINITY;
+// Constante plutôt que `pub use std::f64::INFINITY`, que clippy refuse (legacy_numeric_constants)
+pub const INFINITY: f64 = f64::INFINITY;
+
+// Donnée calculée à partir de la description d'un objet (image chargée, BVH, ...). Elle
+// n'entre pas dans les comparaisons : deux scènes sont égales si leurs descriptions le sont,
+// que leurs images aient été chargées ou non.
+#[derive(Clone, Debug, Default)]
+pub struct Cache<T>(pub T);
+
+impl<T> PartialEq for Cache<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Deref for Cache<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Cache<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
 
 // Utility functions
 
@@ -0,0 +1,467 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{Error, Problem},
+    format::SceneFormat,
+    gltf_import,
+};
+
+// Listes d'objets dont les éléments peuvent utiliser `material` et `prototype`
+const OBJECT_LISTS: [&str; 8] = [
+    "spheres",
+    "planes",
+    "cubes",
+    "cylinders",
+    "sdfs",
+    "heightfields",
+    "metaballs",
+    "meshes",
+];
+
+// Vrai si le document utilise des inclusions, des définitions nommées ou des références :
+// sinon ses erreurs peuvent être situées dans le texte par leur ligne et leur colonne
+pub fn uses_composition(document: &Value) -> bool {
+    let Some(root) = document.as_object() else {
+        return false;
+    };
+    ["include", "materials", "prototypes"]
+        .iter()
+        .any(|key| root.contains_key(*key))
+        || OBJECT_LISTS.iter().any(|list| {
+            objects(root, list)
+                .any(|object| object.contains_key("material") || object.contains_key("prototype"))
+        })
+}
+
+// Fusionne les fichiers inclus puis remplace les références par les définitions nommées.
+// Le résultat est une scène autonome, prête à être désérialisée, avec les avertissements
+// de l'import des modèles glTF inclus.
+pub fn compose(path: &Path, document: Value) -> Result<(Value, Vec<String>), Error> {
+    let canonical = fs::canonicalize(path).map_err(|e| Error::io(path, e))?;
+    let mut includes = Includes {
+        stack: vec![(path.to_path_buf(), canonical.clone())],
+        seen: HashSet::from([canonical]),
+        warnings: Vec::new(),
+    };
+    let mut document = includes.expand(path, Path::new(""), document)?;
+
+    let problems = resolve(&mut document);
+    if !problems.is_empty() {
+        return Err(Error::Invalid {
+            path: path.to_path_buf(),
+            problems,
+        });
+    }
+    Ok((document, includes.warnings))
+}
+
+fn objects<'a>(
+    root: &'a Map<String, Value>,
+    list: &str,
+) -> impl Iterator<Item = &'a Map<String, Value>> {
+    root.get(list)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+}
+
+struct Includes {
+    // Fichiers en cours d'inclusion (chemin affiché, chemin canonique), pour détecter les cycles
+    stack: Vec<(PathBuf, PathBuf)>,
+    // Fichiers déjà fusionnés : un fichier inclus par plusieurs autres ne l'est qu'une fois
+    seen: HashSet<PathBuf>,
+    warnings: Vec<String>,
+}
+
+impl Includes {
+    // `prefix` est le dossier du fichier relativement à la scène principale :
+    // les chemins d'images de ses objets y sont rattachés
+    fn expand(&mut self, path: &Path, prefix: &Path, document: Value) -> Result<Value, Error> {
+        let Value::Object(mut root) = document else {
+            return Ok(document);
+        };
+
+        let includes = match root.remove("include") {
+            None => Vec::new(),
+            Some(Value::String(file)) => vec![file],
+            Some(Value::Array(files)) => files
+                .into_iter()
+                .map(|file| match file {
+                    Value::String(file) => Ok(file),
+                    _ => Err(invalid(path, "include", "must be a file name")),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(invalid(
+                    path,
+                    "include",
+                    "must be a file name or a list of file names",
+                ))
+            }
+        };
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut merged = Map::new();
+        for file in includes {
+            let included = dir.join(&file);
+            let canonical = fs::canonicalize(&included).map_err(|e| {
+                invalid(
+                    path,
+                    "include",
+                    &format!("cannot include \"{}\": {}", file, e),
+                )
+            })?;
+            if self.stack.iter().any(|(_, open)| *open == canonical) {
+                let mut chain: Vec<PathBuf> = self.stack.iter().map(|(p, _)| p.clone()).collect();
+                chain.push(included);
+                return Err(Error::IncludeCycle { chain });
+            }
+            if !self.seen.insert(canonical.clone()) {
+                continue;
+            }
+
+            let mut document: Value = if gltf_import::is_gltf(&included) {
+                let import = gltf_import::import(&included)?;
+                self.warnings.extend(import.warnings);
+                import.document
+            } else {
+                let format = SceneFormat::from_path(&included)?;
+                let text = fs::read_to_string(&included).map_err(|e| Error::io(&included, e))?;
+                format.parse(&included, &text)?
+            };
+            let prefix = prefix.join(Path::new(&file).parent().unwrap_or(Path::new("")));
+            rebase_files(&mut document, &prefix);
+
+            self.stack.push((included.clone(), canonical));
+            let document = self.expand(&included, &prefix, document)?;
+            self.stack.pop();
+
+            if let Value::Object(document) = document {
+                merge(&mut merged, document);
+            }
+        }
+
+        merge(&mut merged, root);
+        Ok(Value::Object(merged))
+    }
+}
+
+// Les listes sont concaténées, les définitions nommées fusionnées par nom,
+// les autres valeurs remplacées par celles du fichier qui inclut
+fn merge(base: &mut Map<String, Value>, overlay: Map<String, Value>) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Array(list)), Value::Array(more)) => list.extend(more),
+            (Some(Value::Object(names)), Value::Object(more))
+                if key == "materials" || key == "prototypes" =>
+            {
+                names.extend(more)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// Les champs `file` (images de textures, carte d'environnement, terrains) d'un fichier
+// inclus sont relatifs à ce fichier : ils sont réécrits relativement à la scène principale
+fn rebase_files(value: &mut Value, prefix: &Path) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(file)
+                        if key == "file"
+                            && !file.starts_with("data:")
+                            && Path::new(file).is_relative() =>
+                    {
+                        *file = prefix.join(&*file).to_string_lossy().into_owned();
+                    }
+                    _ => rebase_files(value, prefix),
+                }
+            }
+        }
+        Value::Array(list) => list
+            .iter_mut()
+            .for_each(|value| rebase_files(value, prefix)),
+        _ => {}
+    }
+}
+
+fn invalid(path: &Path, location: &str, message: &str) -> Error {
+    Error::Invalid {
+        path: path.to_path_buf(),
+        problems: vec![problem(location, message)],
+    }
+}
+
+// Définitions nommées (`materials` ou `prototypes`) ; une définition peut en étendre
+// une autre avec la même clé que les objets (`material` ou `prototype`)
+struct Definitions {
+    section: &'static str,
+    key: &'static str,
+    entries: Map<String, Value>,
+}
+
+impl Definitions {
+    fn take(
+        root: &mut Map<String, Value>,
+        section: &'static str,
+        key: &'static str,
+        problems: &mut Vec<Problem>,
+    ) -> Self {
+        let entries = match root.remove(section) {
+            None => Map::new(),
+            Some(Value::Object(entries)) => entries,
+            Some(_) => {
+                problems.push(problem(section, "must be a table of named definitions"));
+                Map::new()
+            }
+        };
+        Definitions {
+            section,
+            key,
+            entries,
+        }
+    }
+
+    // Champs de la définition `name`, ceux de ses parents compris
+    fn fields(&self, name: &str, location: &str) -> Result<Map<String, Value>, Problem> {
+        let mut chain = vec![name.to_string()];
+        let mut current = name.to_string();
+        let mut location = location.to_string();
+        let mut layers = Vec::new();
+
+        loop {
+            let Some(definition) = self.entries.get(&current) else {
+                return Err(problem(
+                    &location,
+                    &format!("unknown {} \"{}\"", self.key, current),
+                ));
+            };
+            let Value::Object(fields) = definition else {
+                return Err(problem(
+                    &format!("{}.{}", self.section, current),
+                    "must be a table of fields",
+                ));
+            };
+            layers.push(fields);
+
+            location = format!("{}.{}.{}", self.section, current, self.key);
+            match fields.get(self.key) {
+                None => break,
+                Some(Value::String(parent)) => {
+                    if chain.contains(parent) {
+                        chain.push(parent.clone());
+                        return Err(problem(
+                            &format!("{}.{}", self.section, name),
+                            &format!("{} cycle: {}", self.key, chain.join(" -> ")),
+                        ));
+                    }
+                    chain.push(parent.clone());
+                    current = parent.clone();
+                }
+                Some(_) => return Err(problem(&location, "must be a name")),
+            }
+        }
+
+        // Du parent le plus lointain à la définition elle-même
+        let mut fields = Map::new();
+        for layer in layers.into_iter().rev() {
+            fields.extend(layer.clone());
+        }
+        fields.remove(self.key);
+        Ok(fields)
+    }
+
+    // Remplace la référence de l'objet par les champs de la définition ;
+    // les champs de l'objet l'emportent
+    fn apply(&self, object: &mut Map<String, Value>, location: &str) -> Result<(), Problem> {
+        let name = match object.remove(self.key) {
+            None => return Ok(()),
+            Some(Value::String(name)) => name,
+            Some(_) => {
+                return Err(problem(
+                    &format!("{}.{}", location, self.key),
+                    "must be a name",
+                ))
+            }
+        };
+        let mut fields = self.fields(&name, &format!("{}.{}", location, self.key))?;
+        fields.extend(std::mem::take(object));
+        *object = fields;
+        Ok(())
+    }
+}
+
+fn problem(location: &str, message: &str) -> Problem {
+    Problem {
+        location: location.to_string(),
+        message: message.to_string(),
+    }
+}
+
+// Remplace les références `prototype` puis `material` de chaque objet
+fn resolve(document: &mut Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let Value::Object(root) = document else {
+        return problems;
+    };
+
+    let materials = Definitions::take(root, "materials", "material", &mut problems);
+    let prototypes = Definitions::take(root, "prototypes", "prototype", &mut problems);
+
+    for list in OBJECT_LISTS {
+        let Some(Value::Array(objects)) = root.get_mut(list) else {
+            continue;
+        };
+        for (i, object) in objects.iter_mut().enumerate() {
+            let Value::Object(object) = object else {
+                continue;
+            };
+            let location = format!("{}[{}]", list, i);
+            let result = prototypes.apply(object, &location).and_then(|()| {
+                // Le nom du matériau reste connu pour les mattes
+                let name = object.get("material").cloned();
+                materials.apply(object, &location)?;
+                if let Some(name) = name {
+                    object.entry("material_name").or_insert(name);
+                }
+                Ok(())
+            });
+            if let Err(problem) = result {
+                problems.push(problem);
+            }
+        }
+    }
+
+    // Les définitions inutilisées sont aussi vérifiées
+    for definitions in [&materials, &prototypes] {
+        for name in definitions.entries.keys() {
+            let location = format!("{}.{}", definitions.section, name);
+            if let Err(problem) = definitions.fields(name, &location) {
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn objects_take_fields_from_prototypes_then_materials() {
+        let mut document = json!({
+            "materials": {
+                "red": { "color": [1.0, 0.0, 0.0], "bump_scale": 2.0 },
+                "dark_red": { "material": "red", "color": [0.5, 0.0, 0.0] }
+            },
+            "prototypes": {
+                "ball": { "radius": 0.5, "material": "dark_red" },
+                "big_ball": { "prototype": "ball", "radius": 2.0 }
+            },
+            "spheres": [
+                { "prototype": "big_ball", "center": [0.0, 0.0, 0.0] },
+                { "prototype": "ball", "center": [1.0, 0.0, 0.0], "color": 0.5 }
+            ]
+        });
+
+        assert_eq!(resolve(&mut document), Vec::new());
+        assert_eq!(
+            document,
+            json!({
+                "spheres": [
+                    { "radius": 2.0, "color": [0.5, 0.0, 0.0], "bump_scale": 2.0, "center": [0.0, 0.0, 0.0],
+                      "material_name": "dark_red" },
+                    { "radius": 0.5, "color": 0.5, "bump_scale": 2.0, "center": [1.0, 0.0, 0.0],
+                      "material_name": "dark_red" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn missing_references_and_cycles_are_reported() {
+        let mut document = json!({
+            "materials": {
+                "a": { "material": "b" },
+                "b": { "material": "a" },
+                "c": { "material": "nothing" }
+            },
+            "cubes": [
+                { "min": 0.0, "max": 1.0, "material": "gold" },
+                { "min": 0.0, "max": 1.0, "prototype": "box" }
+            ]
+        });
+
+        let locations: Vec<String> = resolve(&mut document)
+            .into_iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                "cubes[0].material: unknown material \"gold\"",
+                "cubes[1].prototype: unknown prototype \"box\"",
+                "materials.a: material cycle: a -> b -> a",
+                "materials.b: material cycle: b -> a -> b",
+                "materials.c.material: unknown material \"nothing\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn includes_are_merged_once_and_cycles_detected() {
+        let dir = std::env::temp_dir().join(format!("rt-compose-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        let write = |name: &str, text: &str| fs::write(dir.join(name), text).unwrap();
+
+        write(
+            "lib/studio.json",
+            r#"{ "include": "common.json", "light": 1, "planes": [{ "file": "floor.png" }] }"#,
+        );
+        write(
+            "lib/common.json",
+            r#"{ "light": 0, "materials": { "m": { "color": 1 } } }"#,
+        );
+        write(
+            "scene.json",
+            r#"{ "include": ["lib/studio.json", "lib/common.json"], "planes": [{ "material": "m" }] }"#,
+        );
+
+        let path = dir.join("scene.json");
+        let document: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let (document, _) = compose(&path, document).unwrap();
+        assert_eq!(
+            document,
+            json!({
+                "light": 1,
+                "planes": [{ "file": "lib/floor.png" }, { "color": 1, "material_name": "m" }]
+            })
+        );
+
+        write("lib/common.json", r#"{ "include": "../scene.json" }"#);
+        let document: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let error = compose(&path, document).err().unwrap();
+        assert!(
+            matches!(&error, Error::IncludeCycle { chain } if chain.len() == 4),
+            "{}",
+            error
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
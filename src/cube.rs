@@ -1,71 +1,363 @@
-use crate::color::Color;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
-use crate::vec3::{unit_vector, Point3, Vec3};
+use crate::material::Material;
+use crate::vec3::{Point3, Vec3};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Cube {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub min: Point3, // Coin inférieur (minimum) du cube
     pub max: Point3, // Coin supérieur (maximum) du cube
-    pub color: Color,
+    #[serde(flatten)]
+    pub material: Material,
 }
 
 impl Cube {
-    pub fn new(min: Point3, max: Point3, color: Color) -> Cube {
-        Cube { min, max, color }
+    pub fn new(min: Point3, max: Point3, material: Material) -> Cube {
+        Cube {
+            name: None,
+            min,
+            max,
+            material,
+        }
+    }
+
+    // Axes portant u et v sur la face perpendiculaire à l'axe donné
+    fn face_axes(axis: usize) -> (usize, usize) {
+        match axis {
+            0 => (2, 1), // Faces gauche / droite : (z, y)
+            1 => (0, 2), // Faces inférieure / supérieure : (x, z)
+            _ => (0, 1), // Faces avant / arrière : (x, y)
+        }
+    }
+
+    // Coordonnées (u, v) dans [0, 1] sur la face perpendiculaire à l'axe donné
+    fn face_uv(&self, p: Point3, axis: usize) -> (f64, f64) {
+        let (a, b) = Self::face_axes(axis);
+        let local = |i: usize| (p[i] - self.min[i]) / (self.max[i] - self.min[i]);
+        (local(a), local(b))
+    }
+
+    // Dérivées dp/du et dp/dv : chaque coordonnée parcourt une arête de la face
+    fn face_derivatives(&self, axis: usize) -> (Vec3, Vec3) {
+        let (a, b) = Self::face_axes(axis);
+        let edge = |i: usize| {
+            let mut e = [0.0; 3];
+            e[i] = self.max[i] - self.min[i];
+            Vec3::new(e[0], e[1], e[2])
+        };
+        (edge(a), edge(b))
     }
 }
 
 impl Hittable for Cube {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        let mut t_min = t_min;
-        let mut t_max = t_max;
+        // Méthode des « slabs » : intervalle [t_near, t_far] du rayon dans la boîte,
+        // avec l'axe de la face d'entrée et celui de la face de sortie
+        let mut t_near = -f64::INFINITY;
+        let mut t_far = f64::INFINITY;
+        let mut near_axis = 0;
+        let mut far_axis = 0;
 
-        // Vérification pour chaque dimension
         for i in 0..3 {
-            let inv_d = 1.0 / r.direction()[i]; // Inverse de la direction
-            let mut t0 = (self.min[i] - r.origin()[i]) * inv_d;
-            let mut t1 = (self.max[i] - r.origin()[i]) * inv_d;
+            let origin = r.origin()[i];
+            let direction = r.direction()[i];
 
-            if inv_d < 0.0 {
-                std::mem::swap(&mut t0, &mut t1); // Inverse si nécessaire
+            if direction == 0.0 {
+                // Rayon parallèle aux faces : dans la tranche ou jamais
+                if origin < self.min[i] || origin > self.max[i] {
+                    return false;
+                }
+                continue;
             }
 
-            t_min = t_min.max(t0); // Garder la borne la plus grande
-            t_max = t_max.min(t1); // Garder la borne la plus petite
+            let inv_d = 1.0 / direction;
+            let mut t0 = (self.min[i] - origin) * inv_d;
+            let mut t1 = (self.max[i] - origin) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
 
-            if t_max <= t_min {
+            if t0 > t_near {
+                t_near = t0;
+                near_axis = i;
+            }
+            if t1 < t_far {
+                t_far = t1;
+                far_axis = i;
+            }
+            if t_far < t_near {
                 return false; // Pas d'intersection
             }
         }
 
-        rec.t = t_min;
-        rec.p = r.at(rec.t);
-
-        let outward_normal = if (rec.p.x() - self.min.x()).abs() < 1e-4 {
-            // println!("Face gauche");
-            Vec3::new(-1.0, 0.0, 0.0) // Face gauche
-        } else if (rec.p.x() - self.max.x()).abs() < 1e-4 {
-            // println!("Face droite");
-            Vec3::new(1.0, 0.0, 0.0) // Face droite
-        } else if (rec.p.y() - self.min.y()).abs() < 1e-4 {
-            // println!("Face inferieure");
-            Vec3::new(0.0, -1.0, 0.0) // Face inférieure
-        } else if (rec.p.y() - self.max.y()).abs() < 1e-4 {
-            // println!("Face superieure");
-            Vec3::new(0.0, 1.0, 0.0) // Face supérieure
-        } else if (rec.p.z() - self.min.z()).abs() < 1e-4 {
-            // println!("Face avant");
-            Vec3::new(0.0, 0.0, -1.0) // Face avant
+        // Entrée si elle est dans l'intervalle, sinon sortie (rayon partant de l'intérieur)
+        let (t, axis, entering) = if t_near > t_min && t_near < t_max {
+            (t_near, near_axis, true)
+        } else if t_far > t_min && t_far < t_max {
+            (t_far, far_axis, false)
+        } else {
+            return false;
+        };
+
+        rec.t = t;
+        rec.p = r.at(t);
+
+        // Normale extérieure : opposée au rayon à l'entrée, dans son sens à la sortie
+        let toward = if r.direction()[axis] > 0.0 { 1.0 } else { -1.0 };
+        let sign = if entering { -toward } else { toward };
+        let mut n = [0.0; 3];
+        n[axis] = sign;
+        let outward_normal = Vec3::new(n[0], n[1], n[2]);
+
+        // Le point est ramené exactement sur la face (pas d'erreur d'arrondi en u, v)
+        rec.p[axis] = if sign > 0.0 {
+            self.max[axis]
         } else {
-            // println!("Face arriere");
-            Vec3::new(0.0, 0.0, 1.0) // Face arrière
+            self.min[axis]
         };
 
-        rec.set_face_normal(r, unit_vector(outward_normal));
-        rec.color = self.color;
+        rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = self.face_uv(rec.p, axis);
+        (rec.dpdu, rec.dpdv) = self.face_derivatives(axis);
 
         true
     }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_cube() -> Cube {
+        Cube::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Material::default(),
+        )
+    }
+
+    fn hit(cube: &Cube, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        cube.hit(&Ray::new(origin, direction), 0.001, f64::INFINITY, &mut rec)
+            .then_some(rec)
+    }
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-9, "{} != {}", a, b);
+        }
+    }
+
+    fn assert_finite(rec: &HitRecord) {
+        for v in [rec.p, rec.normal, rec.dpdu, rec.dpdv] {
+            assert!(v.x().is_finite() && v.y().is_finite() && v.z().is_finite());
+        }
+        assert!(rec.t.is_finite() && rec.u.is_finite() && rec.v.is_finite());
+    }
+
+    #[test]
+    fn hits_entry_face_from_outside() {
+        let rec = hit(
+            &unit_cube(),
+            Point3::new(0.5, 0.5, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        )
+        .unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert_vec_eq(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert_vec_eq(rec.p, Point3::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn every_face_has_its_outward_normal() {
+        let cube = unit_cube();
+        let axes = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        for axis in axes {
+            for sign in [-1.0, 1.0] {
+                let outward = sign * axis;
+                let center = Point3::new(0.5, 0.5, 0.5);
+                let rec = hit(&cube, center + 3.0 * outward, -outward).unwrap();
+                assert!(rec.front_face);
+                assert_vec_eq(rec.normal, outward);
+                assert!((0.0..=1.0).contains(&rec.u) && (0.0..=1.0).contains(&rec.v));
+            }
+        }
+    }
+
+    #[test]
+    fn ray_from_inside_hits_exit_face() {
+        let rec = hit(
+            &unit_cube(),
+            Point3::new(0.5, 0.5, 0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+        )
+        .unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-12);
+        assert!(!rec.front_face);
+        // Normale d'ombrage tournée vers le rayon, normale extérieure +X
+        assert_vec_eq(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert_vec_eq(rec.p, Point3::new(1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn ray_leaving_from_surface_misses() {
+        // Rayon d'ombre partant de la face +X vers l'extérieur
+        let rec = hit(
+            &unit_cube(),
+            Point3::new(1.0, 0.5, 0.5),
+            Vec3::new(1.0, 0.2, 0.0),
+        );
+        assert!(rec.is_none());
+    }
+
+    #[test]
+    fn ray_starting_on_face_going_inside_hits_opposite_face() {
+        let rec = hit(
+            &unit_cube(),
+            Point3::new(0.0, 0.5, 0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+        )
+        .unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn zero_direction_component_on_slab_boundary_is_finite() {
+        // x = 0 et y = 0 sont sur les bords des tranches, direction nulle sur ces axes
+        let rec = hit(
+            &unit_cube(),
+            Point3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        )
+        .unwrap();
+        assert_finite(&rec);
+        assert!((rec.t - 2.0).abs() < 1e-12);
+        assert_vec_eq(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+
+        let rec = hit(
+            &unit_cube(),
+            Point3::new(1.0, -2.0, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        )
+        .unwrap();
+        assert_finite(&rec);
+        assert_vec_eq(rec.normal, Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn zero_direction_component_outside_slab_misses() {
+        let cube = unit_cube();
+        assert!(hit(&cube, Point3::new(1.5, 0.5, -2.0), Vec3::new(0.0, 0.0, 1.0)).is_none());
+        assert!(hit(
+            &cube,
+            Point3::new(-1e-9, 0.5, -2.0),
+            Vec3::new(0.0, 0.0, 1.0)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn edge_and_corner_hits_pick_one_adjacent_face() {
+        let cube = unit_cube();
+        // Arête x = 0, z = 0
+        let rec = hit(
+            &cube,
+            Point3::new(-1.0, 0.5, -1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+        )
+        .unwrap();
+        assert_finite(&rec);
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        let n = rec.normal;
+        assert!(n.length() > 0.999 && n.length() < 1.001);
+        assert!(n.x() == -1.0 || n.z() == -1.0);
+
+        // Coin (0, 0, 0)
+        let rec = hit(
+            &cube,
+            Point3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        )
+        .unwrap();
+        assert_finite(&rec);
+        assert_vec_eq(rec.p, Point3::new(0.0, 0.0, 0.0));
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn normal_does_not_depend_on_scale() {
+        for scale in [1e-6, 1e-3, 1.0, 1e3, 1e6] {
+            let cube = Cube::new(
+                Point3::new(-scale, -scale, -scale),
+                Point3::new(scale, scale, scale),
+                Material::default(),
+            );
+            // Rayon oblique qui touche la face +Y près d'un bord (t = 2 à toutes les échelles)
+            let origin = Point3::new(0.9 * scale, 3.0 * scale, 0.2 * scale);
+            let rec = hit(&cube, origin, scale * Vec3::new(0.01, -1.0, 0.0)).unwrap_or_else(|| {
+                panic!("missed at scale {}", scale);
+            });
+            assert_vec_eq(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+            assert!((rec.p.y() - scale).abs() <= 1e-12 * scale.max(1.0));
+        }
+    }
+
+    #[test]
+    fn misses_when_pointing_away_or_out_of_range() {
+        let cube = unit_cube();
+        assert!(hit(
+            &cube,
+            Point3::new(0.5, 0.5, -2.0),
+            Vec3::new(0.0, 0.0, -1.0)
+        )
+        .is_none());
+        assert!(hit(&cube, Point3::new(2.0, 0.5, -2.0), Vec3::new(0.0, 0.0, 1.0)).is_none());
+
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(0.5, 0.5, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!cube.hit(&ray, 0.001, 1.5, &mut rec));
+        // Entrée hors de [t_min, t_max] mais sortie dedans
+        assert!(cube.hit(&ray, 2.5, 10.0, &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-12);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn no_nan_for_axis_aligned_rays_from_every_boundary() {
+        let cube = unit_cube();
+        let coords = [-1.0, 0.0, 0.5, 1.0, 2.0];
+        let directions = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for &x in &coords {
+            for &y in &coords {
+                for &z in &coords {
+                    for d in directions {
+                        if let Some(rec) = hit(&cube, Point3::new(x, y, z), d) {
+                            assert_finite(&rec);
+                            assert!((rec.normal.length() - 1.0).abs() < 1e-12);
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -1,107 +1,338 @@
-use crate::color::Color;
+use crate::common::PI;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
-use crate::vec3::{dot, unit_vector, Point3, Vec3};
+use crate::material::Material;
+use crate::vec3::{unit_vector, Point3, Vec3};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Cylinder {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub base: Point3, // Centre de la base du cylindre
     pub height: f64,  // Hauteur du cylindre
     pub radius: f64,  // Rayon du cylindre
-    pub color: Color, // Couleur du cylindre
+    #[serde(flatten)]
+    pub material: Material, // Couleur du cylindre
 }
 
 impl Cylinder {
-    pub fn new(base: Point3, height: f64, radius: f64, color: Color) -> Cylinder {
+    pub fn new(base: Point3, height: f64, radius: f64, material: Material) -> Cylinder {
         Cylinder {
+            name: None,
             base,
             height,
             radius,
-            color,
+            material,
         }
     }
+
+    // u : angle autour de l'axe, v : hauteur relative sur la surface latérale
+    fn side_uv(&self, p: Point3) -> (f64, f64) {
+        let phi = f64::atan2(-(p.z() - self.base.z()), p.x() - self.base.x()) + PI;
+        (phi / (2.0 * PI), (p.y() - self.base.y()) / self.height)
+    }
+
+    // Dérivées dp/du (tour complet autour de l'axe) et dp/dv (hauteur)
+    fn side_derivatives(&self, p: Point3) -> (Vec3, Vec3) {
+        let local = p - self.base;
+        (
+            2.0 * PI * Vec3::new(local.z(), 0.0, -local.x()),
+            Vec3::new(0.0, self.height, 0.0),
+        )
+    }
+
+    // Projection du disque sur le carré [0, 1] x [0, 1]
+    fn cap_uv(&self, p: Point3) -> (f64, f64) {
+        (
+            (p.x() - self.base.x()) / (2.0 * self.radius) + 0.5,
+            (p.z() - self.base.z()) / (2.0 * self.radius) + 0.5,
+        )
+    }
+
+    fn cap_derivatives(&self) -> (Vec3, Vec3) {
+        (
+            Vec3::new(2.0 * self.radius, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0 * self.radius),
+        )
+    }
+}
+
+// Partie du cylindre touchée par le rayon
+#[derive(Clone, Copy)]
+enum Part {
+    Side,
+    Bottom,
+    Top,
 }
 
 impl Hittable for Cylinder {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        let axis = Vec3::new(0.0, 1.0, 0.0); // Direction verticale du cylindre
-        let oc = r.origin() - self.base; // Vecteur entre l'origine du rayon et la base
-
-        // Projections pour la surface latérale
-        let a = dot(
-            r.direction() - axis * dot(r.direction(), axis),
-            r.direction() - axis * dot(r.direction(), axis),
-        );
-        let b = 2.0
-            * dot(
-                oc - axis * dot(oc, axis),
-                r.direction() - axis * dot(r.direction(), axis),
-            );
-        let c =
-            dot(oc - axis * dot(oc, axis), oc - axis * dot(oc, axis)) - self.radius * self.radius;
-
-        let mut t_closest = t_max; // Pour suivre le point d'intersection le plus proche
-        let mut hit_anything = false;
-
-        // Calcul des intersections pour la surface latérale
-        let discriminant = b * b - 4.0 * a * c;
-        if discriminant >= 0.0 {
-            let sqrtd = discriminant.sqrt();
-            let mut t = (-b - sqrtd) / (2.0 * a);
-            if t < t_min || t > t_max {
-                t = (-b + sqrtd) / (2.0 * a);
+        let oc = r.origin() - self.base; // Origine du rayon dans le repère de la base
+        let d = r.direction();
+
+        // Intersection la plus proche parmi tous les candidats dans ]t_min, t_max[
+        let mut closest: Option<(f64, Part)> = None;
+        let mut consider = |t: f64, part: Part| {
+            if t > t_min && t < t_max && closest.is_none_or(|(best, _)| t < best) {
+                closest = Some((t, part));
             }
+        };
 
-            if t >= t_min && t <= t_max {
-                let point = r.at(t);
-                let y = point.y() - self.base.y();
-                if y >= 0.0 && y <= self.height {
-                    t_closest = t;
-                    rec.t = t;
-                    rec.p = point;
-
-                    // Normale pour la surface latérale
-                    let outward_normal =
-                        unit_vector(rec.p - self.base - axis * dot(rec.p - self.base, axis));
-                    rec.set_face_normal(r, outward_normal);
-                    rec.color = self.color;
-
-                    hit_anything = true;
+        // Surface latérale : (ox + t dx)² + (oz + t dz)² = r², les deux racines sont testées
+        let a = d.x() * d.x() + d.z() * d.z();
+        if a > 0.0 {
+            let half_b = oc.x() * d.x() + oc.z() * d.z();
+            let c = oc.x() * oc.x() + oc.z() * oc.z() - self.radius * self.radius;
+            let discriminant = half_b * half_b - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                for t in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
+                    let y = oc.y() + t * d.y();
+                    if (0.0..=self.height).contains(&y) {
+                        consider(t, Part::Side);
+                    }
                 }
             }
         }
 
-        // Intersection avec le disque inférieur
-        let t_disk_base = (self.base.y() - r.origin().y()) / r.direction().y();
-        if t_disk_base >= t_min && t_disk_base <= t_closest {
-            let p = r.at(t_disk_base);
-            if (p - self.base).length_squared() <= self.radius * self.radius {
-                t_closest = t_disk_base;
-                rec.t = t_disk_base;
-                rec.p = p;
-                rec.set_face_normal(r, Vec3::new(0.0, -1.0, 0.0)); // Normale vers le bas
-                rec.color = self.color;
-
-                hit_anything = true;
+        // Disques inférieur et supérieur (aucun si le rayon est parallèle)
+        if d.y() != 0.0 {
+            for (y, part) in [(0.0, Part::Bottom), (self.height, Part::Top)] {
+                let t = (y - oc.y()) / d.y();
+                let x = oc.x() + t * d.x();
+                let z = oc.z() + t * d.z();
+                if x * x + z * z <= self.radius * self.radius {
+                    consider(t, part);
+                }
             }
         }
 
-        // Intersection avec le disque supérieur
-        let top_center = self.base + Vec3::new(0.0, self.height, 0.0);
-        let t_disk_top = (top_center.y() - r.origin().y()) / r.direction().y();
-        if t_disk_top >= t_min && t_disk_top <= t_closest {
-            let p = r.at(t_disk_top);
-            if (p - top_center).length_squared() <= self.radius * self.radius {
-                rec.t = t_disk_top;
-                rec.p = p;
-                rec.set_face_normal(r, Vec3::new(0.0, 1.0, 0.0)); // Normale vers le haut
-                rec.color = self.color;
-
-                hit_anything = true;
+        let Some((t, part)) = closest else {
+            return false;
+        };
+
+        rec.t = t;
+        rec.p = r.at(t);
+        match part {
+            Part::Side => {
+                let local = rec.p - self.base;
+                let outward_normal = unit_vector(Vec3::new(local.x(), 0.0, local.z()));
+                rec.set_face_normal(r, outward_normal);
+                (rec.u, rec.v) = self.side_uv(rec.p);
+                (rec.dpdu, rec.dpdv) = self.side_derivatives(rec.p);
+            }
+            Part::Bottom | Part::Top => {
+                let outward_normal = match part {
+                    Part::Bottom => Vec3::new(0.0, -1.0, 0.0), // Normale vers le bas
+                    _ => Vec3::new(0.0, 1.0, 0.0),             // Normale vers le haut
+                };
+                rec.set_face_normal(r, outward_normal);
+                (rec.u, rec.v) = self.cap_uv(rec.p);
+                (rec.dpdu, rec.dpdv) = self.cap_derivatives();
             }
         }
 
-        hit_anything
+        true
+    }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+    use proptest::prelude::*;
+
+    const T_MIN: f64 = 0.001;
+    const T_MAX: f64 = 100.0;
+    // Pas d'échantillonnage de la référence par force brute
+    const STEPS: usize = 20_000;
+
+    fn cylinder(base: Point3, height: f64, radius: f64) -> Cylinder {
+        Cylinder::new(base, height, radius, Material::default())
+    }
+
+    fn hit(cyl: &Cylinder, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        cyl.hit(&Ray::new(origin, direction), T_MIN, T_MAX, &mut rec)
+            .then_some(rec)
+    }
+
+    fn inside(cyl: &Cylinder, p: Point3) -> bool {
+        let local = p - cyl.base;
+        local.x() * local.x() + local.z() * local.z() <= cyl.radius * cyl.radius
+            && (0.0..=cyl.height).contains(&local.y())
+    }
+
+    // Distance du point à la surface du cylindre plein
+    fn surface_distance(cyl: &Cylinder, p: Point3) -> f64 {
+        let local = p - cyl.base;
+        let radial = f64::sqrt(local.x() * local.x() + local.z() * local.z()) - cyl.radius;
+        let axial = (local.y() - cyl.height / 2.0).abs() - cyl.height / 2.0;
+        if radial > 0.0 || axial > 0.0 {
+            f64::sqrt(radial.max(0.0).powi(2) + axial.max(0.0).powi(2))
+        } else {
+            radial.max(axial).abs()
+        }
+    }
+
+    // Référence : premier changement intérieur / extérieur le long du rayon,
+    // détecté par échantillonnage régulier puis affiné par dichotomie. Le cylindre étant
+    // convexe, le premier échantillon de l'autre côté encadre la première traversée.
+    // Sans traversée, la réponse n'est sûre que si aucun échantillon n'est à moins d'un
+    // demi-pas de la surface : sinon une traversée plus courte que le pas a pu passer
+    // entre deux échantillons, et le résultat est `None` (rayon rasant, indécidable).
+    fn brute_force(cyl: &Cylinder, r: &Ray) -> Option<Option<f64>> {
+        let step = (T_MAX - T_MIN) / STEPS as f64;
+        let start = inside(cyl, r.at(T_MIN));
+        let mut closest = surface_distance(cyl, r.at(T_MIN));
+        for k in 1..=STEPS {
+            let t = T_MIN + k as f64 * step;
+            if inside(cyl, r.at(t)) != start {
+                let (mut a, mut b) = (t - step, t);
+                for _ in 0..60 {
+                    let mid = 0.5 * (a + b);
+                    if inside(cyl, r.at(mid)) == start {
+                        a = mid;
+                    } else {
+                        b = mid;
+                    }
+                }
+                return Some(Some(0.5 * (a + b)));
+            }
+            closest = closest.min(surface_distance(cyl, r.at(t)));
+        }
+        // La distance varie au plus de la longueur parcourue
+        (closest > 0.5 * step * r.direction().length()).then_some(None)
+    }
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-9, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn ray_along_axis_hits_top_cap() {
+        // Direction sans composante horizontale : pas de surface latérale
+        let cyl = cylinder(Point3::new(0.0, 0.0, 0.0), 2.0, 1.0);
+        let rec = hit(&cyl, Point3::new(0.3, 5.0, 0.2), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-12);
+        assert_vec_eq(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn horizontal_ray_in_cap_plane_is_finite() {
+        // Origine dans le plan du disque supérieur, direction.y nulle
+        let cyl = cylinder(Point3::new(0.0, 0.0, 0.0), 2.0, 1.0);
+        let rec = hit(&cyl, Point3::new(-3.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-12);
+        assert_vec_eq(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert!(rec.p.x().is_finite() && rec.u.is_finite() && rec.v.is_finite());
+    }
+
+    #[test]
+    fn side_hit_in_front_of_cap() {
+        let cyl = cylinder(Point3::new(0.0, 0.0, 0.0), 1.0, 1.0);
+        let rec = hit(&cyl, Point3::new(-2.0, 0.5, 0.0), Vec3::new(1.0, 0.2, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert_vec_eq(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn cap_hit_in_front_of_side() {
+        // Entrée par le disque supérieur : la racine latérale proche est au-dessus du cylindre
+        let cyl = cylinder(Point3::new(0.0, 0.0, 0.0), 1.0, 1.0);
+        let rec = hit(&cyl, Point3::new(-1.5, 2.0, 0.0), Vec3::new(1.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert_vec_eq(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn far_lateral_root_used_when_near_one_is_out_of_height() {
+        // Départ à l'intérieur : la racine proche est derrière, la sortie est latérale
+        let cyl = cylinder(Point3::new(0.0, 0.0, 0.0), 1.0, 1.0);
+        let rec = hit(&cyl, Point3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.1, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!(!rec.front_face);
+        assert_vec_eq(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+
+        // t_min au-delà de l'entrée : seule la sortie compte
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(-3.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cyl.hit(&ray, 2.5, T_MAX, &mut rec));
+        assert!((rec.t - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn misses_when_out_of_range() {
+        let cyl = cylinder(Point3::new(0.0, 0.0, 0.0), 1.0, 1.0);
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(Point3::new(-3.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!cyl.hit(&ray, T_MIN, 1.5, &mut rec));
+        assert!(hit(&cyl, Point3::new(-3.0, 0.5, 0.0), Vec3::new(-1.0, 0.0, 0.0)).is_none());
+        assert!(hit(&cyl, Point3::new(-3.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    fn coordinate() -> impl Strategy<Value = f64> {
+        -6.0..6.0f64
+    }
+
+    // Directions quelconques, plus des directions alignées sur l'axe ou horizontales
+    fn direction() -> impl Strategy<Value = Vec3> {
+        let component = || -1.0..1.0f64;
+        prop_oneof![
+            (component(), component(), component()).prop_map(|(x, y, z)| Vec3::new(x, y, z)),
+            (component(), component()).prop_map(|(x, z)| Vec3::new(x, 0.0, z)),
+            prop_oneof![Just(1.0), Just(-1.0)].prop_map(|y| Vec3::new(0.0, y, 0.0)),
+        ]
+        .prop_filter("direction non nulle", |d| d.length() > 1e-3)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn nearest_hit_matches_brute_force(
+            base in (coordinate(), coordinate(), coordinate()),
+            height in 0.1..4.0f64,
+            radius in 0.1..3.0f64,
+            origin in (coordinate(), coordinate(), coordinate()),
+            direction in direction(),
+        ) {
+            let cyl = cylinder(Point3::new(base.0, base.1, base.2), height, radius);
+            let origin = Point3::new(origin.0, origin.1, origin.2);
+            prop_assume!(surface_distance(&cyl, origin) > 1e-3);
+
+            let ray = Ray::new(origin, direction);
+            let reference = brute_force(&cyl, &ray);
+            prop_assume!(reference.is_some());
+            let result = hit(&cyl, origin, direction);
+
+            match (result, reference.unwrap()) {
+                (Some(rec), Some(t_ref)) => {
+                    prop_assert!(
+                        (rec.t - t_ref).abs() <= 1e-7,
+                        "hit at {} instead of the crossing at {}", rec.t, t_ref
+                    );
+                    prop_assert!(surface_distance(&cyl, rec.p) < 1e-6);
+                    prop_assert!((rec.normal.length() - 1.0).abs() < 1e-9);
+                    prop_assert!(vec3::dot(rec.normal, direction) <= 1e-12);
+                    prop_assert_eq!(rec.front_face, !inside(&cyl, origin));
+                }
+                (Some(rec), None) => {
+                    prop_assert!(false, "hit at t = {} without any crossing", rec.t);
+                }
+                (None, Some(t_ref)) => {
+                    prop_assert!(false, "missed the crossing at t = {}", t_ref);
+                }
+                (None, None) => {}
+            }
+        }
     }
 }
@@ -0,0 +1,201 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+// Erreurs du chargement de la scène et du rendu, affichées par `main` à la place d'une panique
+#[derive(Debug)]
+pub enum Error {
+    // Lecture ou écriture d'un fichier
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    // Syntaxe ou structure invalide, avec la position dans le fichier
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    // Extension de fichier inconnue, fichier glTF invalide ou échec de la sérialisation
+    Format {
+        path: PathBuf,
+        message: String,
+    },
+    // Fichiers qui s'incluent mutuellement, dans l'ordre des inclusions
+    IncludeCycle {
+        chain: Vec<PathBuf>,
+    },
+    // Image illisible ou dans un format non pris en charge
+    Image {
+        path: PathBuf,
+        message: String,
+    },
+    // Valeurs incohérentes trouvées par la validation de la scène
+    Invalid {
+        path: PathBuf,
+        problems: Vec<Problem>,
+    },
+    // Moteur de rendu incomplet ou mal réglé
+    Renderer {
+        message: String,
+    },
+}
+
+// Problème relevé par la validation : où (ex. `spheres[2].radius`) et pourquoi
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub location: String,
+    pub message: String,
+}
+
+impl Error {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn image(path: &Path, message: impl Into<String>) -> Self {
+        Error::Image {
+            path: path.to_path_buf(),
+            message: message.into(),
+        }
+    }
+
+    pub fn renderer(message: impl Into<String>) -> Self {
+        Error::Renderer {
+            message: message.into(),
+        }
+    }
+
+    pub fn format(path: &Path, message: impl Into<String>) -> Self {
+        Error::Format {
+            path: path.to_path_buf(),
+            message: message.into(),
+        }
+    }
+
+    // Les erreurs JSON et de lecture du fichier glTF gardent leur forme habituelle
+    pub fn gltf(path: &Path, error: gltf::Error) -> Self {
+        match error {
+            gltf::Error::Deserialize(error) => Error::json(path, error),
+            gltf::Error::Io(error) => Error::io(path, error),
+            error => Error::format(path, error.to_string()),
+        }
+    }
+
+    // serde_json ajoute « at line L column C » au message : la position est gardée à part
+    pub fn json(path: &Path, error: serde_json::Error) -> Self {
+        let (line, column) = (error.line(), error.column());
+        let message = error.to_string();
+        let suffix = format!(" at line {} column {}", line, column);
+        Error::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message: message
+                .strip_suffix(&suffix)
+                .unwrap_or(&message)
+                .to_string(),
+        }
+    }
+
+    // Même traitement pour serde_yaml_ng, dont certaines erreurs n'ont pas de position (ligne 0)
+    pub fn yaml(path: &Path, error: serde_yaml_ng::Error) -> Self {
+        let message = error.to_string();
+        let (line, column) = error
+            .location()
+            .map_or((0, 0), |location| (location.line(), location.column()));
+        let suffix = format!(" at line {} column {}", line, column);
+        Error::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message: message
+                .strip_suffix(&suffix)
+                .unwrap_or(&message)
+                .to_string(),
+        }
+    }
+
+    // toml ne donne qu'un intervalle d'octets : la ligne et la colonne sont recalculées sur le texte
+    pub fn toml(path: &Path, text: &str, error: toml::de::Error) -> Self {
+        let (line, column) = match error.span() {
+            Some(span) => {
+                let before = &text[..span.start.min(text.len())];
+                let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+                (
+                    before.matches('\n').count() + 1,
+                    before[line_start..].chars().count() + 1,
+                )
+            }
+            None => (0, 0),
+        };
+        Error::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message: error.message().trim_end().to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => {
+                if *line == 0 {
+                    write!(f, "{}: {}", path.display(), message)
+                } else {
+                    write!(f, "{}:{}:{}: {}", path.display(), line, column, message)
+                }
+            }
+            Error::Format { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::IncludeCycle { chain } => {
+                let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "include cycle: {}", chain.join(" -> "))
+            }
+            Error::Image { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::Invalid { path, problems } => {
+                let plural = if problems.len() > 1 { "s" } else { "" };
+                write!(
+                    f,
+                    "{}: {} problem{} found",
+                    path.display(),
+                    problems.len(),
+                    plural
+                )?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            Error::Renderer { message } => write!(f, "renderer: {}", message),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
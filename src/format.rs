@@ -0,0 +1,100 @@
+use std::{fs, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::Error;
+
+// Format d'un fichier de scène, choisi selon son extension
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SceneFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl SceneFormat {
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("json") => Ok(SceneFormat::Json),
+            Some("yaml") | Some("yml") => Ok(SceneFormat::Yaml),
+            Some("toml") => Ok(SceneFormat::Toml),
+            _ => Err(Error::format(
+                path,
+                "unsupported scene format, expected .json, .yaml, .yml or .toml",
+            )),
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, path: &Path, text: &str) -> Result<T, Error> {
+        match self {
+            SceneFormat::Json => serde_json::from_str(text).map_err(|e| Error::json(path, e)),
+            SceneFormat::Yaml => serde_yaml_ng::from_str(text).map_err(|e| Error::yaml(path, e)),
+            SceneFormat::Toml => toml::from_str(text).map_err(|e| Error::toml(path, text, e)),
+        }
+    }
+
+    pub fn to_string<T: Serialize>(self, path: &Path, value: &T) -> Result<String, Error> {
+        let text = match self {
+            SceneFormat::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            SceneFormat::Yaml => serde_yaml_ng::to_string(value).map_err(|e| e.to_string()),
+            SceneFormat::Toml => toml::to_string(value).map_err(|e| e.to_string()),
+        };
+        text.map_err(|message| Error::format(path, message))
+    }
+}
+
+// Sérialise dans le format donné par l'extension du fichier
+pub fn write<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let mut text = SceneFormat::from_path(path)?.to_string(path, value)?;
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    fs::write(path, text).map_err(|e| Error::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::Scene;
+
+    #[test]
+    fn format_is_chosen_from_extension() {
+        let format = |name: &str| SceneFormat::from_path(Path::new(name)).ok();
+        assert_eq!(format("scene.json"), Some(SceneFormat::Json));
+        assert_eq!(format("dir/scene.YAML"), Some(SceneFormat::Yaml));
+        assert_eq!(format("scene.yml"), Some(SceneFormat::Yaml));
+        assert_eq!(format("scene.toml"), Some(SceneFormat::Toml));
+        assert_eq!(format("scene.txt"), None);
+        assert_eq!(format("scene"), None);
+    }
+
+    #[test]
+    fn toml_errors_have_a_position() {
+        let text = "[camera]\norigin = [1.0, 2.0]\n";
+        let error = SceneFormat::Toml
+            .parse::<Scene>(Path::new("s.toml"), text)
+            .err()
+            .unwrap();
+        match error {
+            Error::Parse { line, column, .. } => assert_eq!((line, column), (2, 10)),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn scene_round_trips_through_every_format() {
+        let path = Path::new("scene.json");
+        let scene = Scene::read(path).unwrap();
+
+        for format in [SceneFormat::Json, SceneFormat::Yaml, SceneFormat::Toml] {
+            let text = format.to_string(path, &scene).unwrap();
+            let parsed: Scene = format.parse(path, &text).unwrap();
+            assert!(parsed == scene, "{:?}", format);
+        }
+    }
+}
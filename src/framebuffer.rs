@@ -0,0 +1,369 @@
+use crate::{color::Color, material};
+
+// Couleur RVB et couverture alpha d'un pixel
+pub type Rgba = [f32; 4];
+
+// Image rendue en mémoire, ligne du haut en premier. Chaque pixel garde la somme de
+// ses échantillons et leur nombre, ce qui permet d'ajouter des passes successives
+// (rendu progressif) et de relire à tout moment la moyenne obtenue.
+// Alpha est la part des échantillons qui ont touché un objet. Avec un fond transparent,
+// les échantillons du fond sont noirs et la couleur est donc prémultipliée par alpha.
+// La somme des luminances et de leurs carrés donne en plus la variance du pixel, qui
+// guide l'échantillonnage adaptatif.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    sums: Vec<Rgba>,
+    samples: Vec<u32>,
+    moments: Vec<[f64; 2]>, // Somme des luminances et de leurs carrés
+}
+
+impl Framebuffer {
+    // Image noire et transparente, sans aucun échantillon
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            sums: vec![[0.0; 4]; width * height],
+            samples: vec![0; width * height],
+            moments: vec![[0.0; 2]; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) outside of a {}x{} framebuffer",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        y * self.width + x
+    }
+
+    // Ajoute un échantillon de couleur `color`, de couverture `alpha` (1 sur un objet, 0 sur le fond)
+    pub fn add_sample(&mut self, x: usize, y: usize, color: Color, alpha: f64) {
+        let i = self.index(x, y);
+        let sample = [color.x(), color.y(), color.z(), alpha].map(|c| c as f32);
+        for (sum, value) in self.sums[i].iter_mut().zip(sample) {
+            *sum += value;
+        }
+        self.samples[i] += 1;
+        let y = material::luminance(color);
+        self.moments[i][0] += y;
+        self.moments[i][1] += y * y;
+    }
+
+    // Remplace le pixel par une seule valeur
+    pub fn set(&mut self, x: usize, y: usize, rgba: Rgba) {
+        let i = self.index(x, y);
+        self.sums[i] = rgba;
+        self.samples[i] = 1;
+        let [r, g, b, _] = rgba.map(|c| c as f64);
+        let y = material::luminance(Color::new(r, g, b));
+        self.moments[i] = [y, y * y];
+    }
+
+    // Moyenne des échantillons du pixel ; noir transparent sans échantillon
+    pub fn pixel(&self, x: usize, y: usize) -> Rgba {
+        let i = self.index(x, y);
+        match self.samples[i] {
+            0 => [0.0; 4],
+            n => self.sums[i].map(|sum| sum / n as f32),
+        }
+    }
+
+    // Couleur moyenne du pixel, sans alpha
+    pub fn color(&self, x: usize, y: usize) -> Color {
+        let [r, g, b, _] = self.pixel(x, y);
+        Color::new(r as f64, g as f64, b as f64)
+    }
+
+    pub fn alpha(&self, x: usize, y: usize) -> f64 {
+        self.pixel(x, y)[3] as f64
+    }
+
+    pub fn samples(&self, x: usize, y: usize) -> u32 {
+        self.samples[self.index(x, y)]
+    }
+
+    // Luminance moyenne des échantillons du pixel
+    pub fn luminance(&self, x: usize, y: usize) -> f64 {
+        let i = self.index(x, y);
+        match self.samples[i] {
+            0 => 0.0,
+            n => self.moments[i][0] / n as f64,
+        }
+    }
+
+    // Variance (non biaisée) de la luminance des échantillons ; 0 avant le deuxième
+    pub fn variance(&self, x: usize, y: usize) -> f64 {
+        let i = self.index(x, y);
+        let n = self.samples[i] as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let [sum, squares] = self.moments[i];
+        ((squares - sum * sum / n) / (n - 1.0)).max(0.0)
+    }
+
+    // Erreur type de la luminance moyenne : l'écart attendu entre la moyenne obtenue et
+    // la vraie valeur du pixel, qui décroît comme 1 / sqrt(échantillons)
+    pub fn error(&self, x: usize, y: usize) -> f64 {
+        match self.samples(x, y) {
+            0 => 0.0,
+            n => (self.variance(x, y) / n as f64).sqrt(),
+        }
+    }
+
+    // Ajoute les échantillons d'une autre passe de même taille
+    pub fn accumulate(&mut self, other: &Framebuffer) {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "framebuffers of different sizes"
+        );
+        for (sum, value) in self.sums.iter_mut().zip(&other.sums) {
+            for c in 0..4 {
+                sum[c] += value[c];
+            }
+        }
+        for (count, n) in self.samples.iter_mut().zip(&other.samples) {
+            *count += n;
+        }
+        for (moments, value) in self.moments.iter_mut().zip(&other.moments) {
+            moments[0] += value[0];
+            moments[1] += value[1];
+        }
+    }
+
+    // Pixels moyens de l'image, ligne par ligne
+    pub fn pixels(&self) -> impl Iterator<Item = Rgba> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| self.pixel(x, y)))
+    }
+
+    // Rectangle de l'image commençant en (x, y), limité aux bords de l'image
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Framebuffer {
+        let width = width.min(self.width.saturating_sub(x));
+        let height = height.min(self.height.saturating_sub(y));
+        let mut cropped = Framebuffer::new(width, height);
+        if width == 0 {
+            return cropped;
+        }
+        for row in 0..height {
+            let from = self.index(x, y + row);
+            let to = row * width;
+            cropped.sums[to..to + width].copy_from_slice(&self.sums[from..from + width]);
+            cropped.samples[to..to + width].copy_from_slice(&self.samples[from..from + width]);
+            cropped.moments[to..to + width].copy_from_slice(&self.moments[from..from + width]);
+        }
+        cropped
+    }
+
+    // Rééchantillonne l'image : chaque nouveau pixel est la moyenne des pixels qu'il
+    // recouvre, pondérés par la surface recouverte. Le résultat a un échantillon par pixel.
+    pub fn resize(&self, width: usize, height: usize) -> Framebuffer {
+        let mut resized = Framebuffer::new(width, height);
+        if self.width == 0 || self.height == 0 {
+            return resized;
+        }
+        let columns: Vec<_> = (0..width)
+            .map(|x| footprint(x, width, self.width))
+            .collect();
+        for y in 0..height {
+            let rows = footprint(y, height, self.height);
+            for (x, columns) in columns.iter().enumerate() {
+                let mut total = [0.0f64; 4];
+                let mut weight = 0.0;
+                for &(sy, wy) in &rows {
+                    for &(sx, wx) in columns {
+                        let pixel = self.pixel(sx, sy);
+                        for c in 0..4 {
+                            total[c] += wx * wy * pixel[c] as f64;
+                        }
+                        weight += wx * wy;
+                    }
+                }
+                resized.set(x, y, total.map(|t| (t / weight) as f32));
+            }
+        }
+        resized
+    }
+
+    // Rééchantillonne l'image sans mélanger les pixels : chaque nouveau pixel reprend le
+    // pixel source sous son centre. Pour les passes dont une moyenne n'aurait pas de sens,
+    // comme la profondeur ou l'identifiant d'objet.
+    pub fn resize_nearest(&self, width: usize, height: usize) -> Framebuffer {
+        let mut resized = Framebuffer::new(width, height);
+        if self.width == 0 || self.height == 0 {
+            return resized;
+        }
+        let nearest = |i: usize, size: usize, source: usize| {
+            (((i as f64 + 0.5) * source as f64 / size as f64) as usize).min(source - 1)
+        };
+        for y in 0..height {
+            let sy = nearest(y, height, self.height);
+            for x in 0..width {
+                let from = self.index(nearest(x, width, self.width), sy);
+                let to = resized.index(x, y);
+                resized.sums[to] = self.sums[from];
+                resized.samples[to] = self.samples[from];
+                resized.moments[to] = self.moments[from];
+            }
+        }
+        resized
+    }
+
+    // Deux images de même hauteur côte à côte, la gauche puis la droite
+    pub fn side_by_side(left: &Framebuffer, right: &Framebuffer) -> Framebuffer {
+        assert_eq!(
+            left.height, right.height,
+            "framebuffers of different heights"
+        );
+        let mut combined = Framebuffer::new(left.width + right.width, left.height);
+        for y in 0..left.height {
+            for (image, offset) in [(left, 0), (right, left.width)] {
+                let from = y * image.width;
+                let to = y * combined.width + offset;
+                combined.sums[to..to + image.width]
+                    .copy_from_slice(&image.sums[from..from + image.width]);
+                combined.samples[to..to + image.width]
+                    .copy_from_slice(&image.samples[from..from + image.width]);
+                combined.moments[to..to + image.width]
+                    .copy_from_slice(&image.moments[from..from + image.width]);
+            }
+        }
+        combined
+    }
+}
+
+// Pixels sources (indice, part recouverte) sous le pixel `i` d'une image de taille `size`
+// tirée d'une image de taille `source`
+pub(crate) fn footprint(i: usize, size: usize, source: usize) -> Vec<(usize, f64)> {
+    let scale = source as f64 / size as f64;
+    let (start, end) = (i as f64 * scale, (i + 1) as f64 * scale);
+    let last = (end.ceil() as usize).min(source);
+    (start.floor() as usize..last)
+        .map(|s| (s, end.min(s as f64 + 1.0) - start.max(s as f64)))
+        .filter(|&(_, w)| w > 0.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                framebuffer.set(x, y, [x as f32, y as f32, 0.0, 1.0]);
+            }
+        }
+        framebuffer
+    }
+
+    #[test]
+    fn samples_are_averaged() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        assert_eq!(framebuffer.pixel(2, 1), [0.0; 4]);
+
+        framebuffer.add_sample(2, 1, Color::new(1.0, 0.5, 0.0), 1.0);
+        framebuffer.add_sample(2, 1, Color::new(0.0, 0.5, 1.0), 1.0);
+        framebuffer.add_sample(2, 1, Color::new(0.5, 0.5, 0.5), 0.0);
+        assert_eq!(framebuffer.samples(2, 1), 3);
+        assert_eq!(framebuffer.pixel(2, 1), [0.5, 0.5, 0.5, 2.0 / 3.0]);
+        assert_eq!(framebuffer.samples(0, 0), 0);
+    }
+
+    #[test]
+    fn passes_accumulate() {
+        let mut first = Framebuffer::new(2, 2);
+        let mut second = Framebuffer::new(2, 2);
+        first.add_sample(1, 0, Color::new(1.0, 1.0, 1.0), 1.0);
+        second.add_sample(1, 0, Color::new(0.0, 0.0, 0.0), 1.0);
+        second.add_sample(1, 0, Color::new(0.5, 0.5, 0.5), 1.0);
+
+        first.accumulate(&second);
+        assert_eq!(first.samples(1, 0), 3);
+        assert_eq!(first.pixel(1, 0), [0.5, 0.5, 0.5, 1.0]);
+        // Variance des luminances 1, 0 et 0,5
+        assert!((first.variance(1, 0) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn error_shrinks_with_the_samples() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        for gray in [0.2, 0.4, 0.6, 0.8] {
+            framebuffer.add_sample(0, 0, Color::new(gray, gray, gray), 1.0);
+            framebuffer.add_sample(1, 0, Color::new(0.5, 0.5, 0.5), 1.0);
+        }
+        assert!((framebuffer.luminance(0, 0) - 0.5).abs() < 1e-12);
+        let variance = 0.2 / 3.0;
+        assert!((framebuffer.variance(0, 0) - variance).abs() < 1e-12);
+        assert!((framebuffer.error(0, 0) - (variance / 4.0).sqrt()).abs() < 1e-12);
+
+        // Des échantillons identiques ne laissent aucune erreur
+        assert!(framebuffer.error(1, 0) < 1e-12);
+        assert_eq!(Framebuffer::new(1, 1).error(0, 0), 0.0);
+    }
+
+    #[test]
+    fn crop_is_clamped_to_the_image() {
+        let cropped = gradient(4, 3).crop(2, 1, 10, 10);
+        assert_eq!((cropped.width(), cropped.height()), (2, 2));
+        assert_eq!(cropped.pixel(0, 0), [2.0, 1.0, 0.0, 1.0]);
+        assert_eq!(cropped.pixel(1, 1), [3.0, 2.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn resize_averages_the_covered_pixels() {
+        // Réduction de moitié : moyenne de blocs 2x2
+        let half = gradient(4, 2).resize(2, 1);
+        assert_eq!(half.pixel(0, 0), [0.5, 0.5, 0.0, 1.0]);
+        assert_eq!(half.pixel(1, 0), [2.5, 0.5, 0.0, 1.0]);
+
+        // Agrandissement : chaque pixel reprend le pixel source qui le recouvre
+        let double = gradient(2, 1).resize(4, 2);
+        assert_eq!(double.pixel(1, 1), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(double.pixel(2, 0), [1.0, 0.0, 0.0, 1.0]);
+
+        // Facteur non entier : 3 pixels vers 2, le pixel du milieu est partagé
+        let mut row = Framebuffer::new(3, 1);
+        for (x, value) in [0.0, 3.0, 6.0].into_iter().enumerate() {
+            row.set(x, 0, [value, 0.0, 0.0, 1.0]);
+        }
+        let resized = row.resize(2, 1);
+        assert_eq!(resized.pixel(0, 0)[0], 1.0);
+        assert_eq!(resized.pixel(1, 0)[0], 5.0);
+    }
+
+    #[test]
+    fn resize_nearest_copies_the_pixel_under_the_center() {
+        let half = gradient(4, 2).resize_nearest(2, 1);
+        assert_eq!(half.pixel(0, 0), [1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(half.pixel(1, 0), [3.0, 1.0, 0.0, 1.0]);
+
+        let triple = gradient(2, 1).resize_nearest(3, 1);
+        assert_eq!(triple.pixel(1, 0), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(triple.pixel(2, 0), [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn side_by_side_keeps_both_images() {
+        let combined = Framebuffer::side_by_side(&gradient(2, 2), &gradient(3, 2));
+        assert_eq!((combined.width(), combined.height()), (5, 2));
+        assert_eq!(combined.pixel(1, 1), [1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(combined.pixel(4, 1), [2.0, 1.0, 0.0, 1.0]);
+    }
+}
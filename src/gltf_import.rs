@@ -0,0 +1,637 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use gltf::{camera::Projection, image, khr_lights_punctual::Kind, mesh::Mode, Gltf};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    color::Color,
+    common::Cache,
+    error::Error,
+    material::Material,
+    mesh::Mesh,
+    texture::{ImageTexture, Pattern, TextureConfig},
+    vec3::{self, Point3, Vec3},
+};
+
+// Matrice 4x4 en colonnes, comme dans glTF : m[colonne][ligne]
+type Matrix = [[f64; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// Document de scène tiré d'un fichier glTF, avec ce qui n'a pas pu être importé tel quel
+pub struct Import {
+    pub document: Value,
+    pub warnings: Vec<String>,
+}
+
+pub fn is_gltf(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str());
+    extension.is_some_and(|e| e.eq_ignore_ascii_case("gltf") || e.eq_ignore_ascii_case("glb"))
+}
+
+// Scène complète tirée d'un fichier glTF : une caméra et une lumière sont ajoutées
+// autour des objets si le fichier n'en a pas
+pub fn import_scene(path: &Path) -> Result<Import, Error> {
+    let mut import = import(path)?;
+    let Value::Object(root) = &mut import.document else {
+        return Ok(import);
+    };
+
+    let (center, radius) = bounds(root);
+    if !root.contains_key("camera") {
+        let origin = center + Vec3::new(0.0, 0.5 * radius, 2.0 * radius);
+        root.insert(
+            "camera".into(),
+            json!({ "origin": origin, "look_at": center }),
+        );
+    }
+    if !root.contains_key("light") {
+        let position = center + radius * Vec3::new(1.0, 3.0, 2.0);
+        root.insert(
+            "light".into(),
+            json!({ "position": position, "color": 1.0, "intensity": 1.0 }),
+        );
+    }
+    Ok(import)
+}
+
+// Document de scène avec les maillages, la première caméra et la première lumière du fichier.
+// Les transformations des nœuds sont appliquées aux sommets. Les lumières en trop ou
+// simplifiées et les images illisibles sont signalées dans les avertissements.
+pub fn import(path: &Path) -> Result<Import, Error> {
+    let gltf = Gltf::open(path).map_err(|e| Error::gltf(path, e))?;
+    let buffers = load_buffers(path, &gltf)?;
+
+    let mut importer = Importer {
+        path,
+        buffers,
+        meshes: Vec::new(),
+        camera: None,
+        light: None,
+        warnings: Vec::new(),
+    };
+
+    let document = &gltf.document;
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => scene
+            .nodes()
+            .for_each(|node| importer.visit(&node, &IDENTITY)),
+        // Sans scène, tous les nœuds racines
+        None => {
+            let children: Vec<usize> = document
+                .nodes()
+                .flat_map(|node| node.children().map(|child| child.index()))
+                .collect();
+            document
+                .nodes()
+                .filter(|node| !children.contains(&node.index()))
+                .for_each(|node| importer.visit(&node, &IDENTITY));
+        }
+    }
+
+    let mut root = Map::new();
+    let meshes =
+        serde_json::to_value(&importer.meshes).map_err(|e| Error::format(path, e.to_string()))?;
+    root.insert("meshes".into(), meshes);
+    if let Some(camera) = importer.camera {
+        root.insert("camera".into(), camera);
+    }
+    if let Some(light) = importer.light {
+        let position = match light.kind {
+            // Lumière directionnelle : placée loin en amont de sa direction
+            Kind::Directional => {
+                let (center, radius) = bounds(&root);
+                center - 10.0 * radius * light.direction
+            }
+            _ => light.position,
+        };
+        root.insert(
+            "light".into(),
+            json!({ "position": position, "color": light.color, "intensity": light.intensity }),
+        );
+    }
+    Ok(Import {
+        document: Value::Object(root),
+        warnings: importer.warnings,
+    })
+}
+
+struct Importer<'a> {
+    path: &'a Path,
+    buffers: Vec<Vec<u8>>,
+    meshes: Vec<Mesh>,
+    camera: Option<Value>,
+    light: Option<Light>,
+    warnings: Vec<String>,
+}
+
+struct Light {
+    kind: Kind,
+    position: Point3,
+    direction: Vec3,
+    color: Color,
+    intensity: f64,
+}
+
+impl Importer<'_> {
+    fn visit(&mut self, node: &gltf::Node, parent: &Matrix) {
+        let world = multiply(parent, &node.transform().matrix().map(|c| c.map(f64::from)));
+
+        if let Some(mesh) = node.mesh() {
+            // Les primitives d'un même nœud partagent son nom
+            let name = node.name().or(mesh.name());
+            for primitive in mesh.primitives() {
+                if let Some(mesh) = self.primitive(&primitive, &world, name) {
+                    self.meshes.push(mesh);
+                }
+            }
+        }
+
+        // La caméra regarde vers -Z dans son repère local
+        if let (Some(camera), None) = (node.camera(), &self.camera) {
+            let origin = transform_point(&world, Point3::new(0.0, 0.0, 0.0));
+            let forward = vec3::unit_vector(transform_vector(&world, Vec3::new(0.0, 0.0, -1.0)));
+            let fov = match camera.projection() {
+                Projection::Perspective(perspective) => widen(perspective.yfov()).to_degrees(),
+                Projection::Orthographic(_) => 90.0,
+            };
+            self.camera =
+                Some(json!({ "origin": origin, "look_at": origin + forward, "fov": fov }));
+        }
+
+        // La scène n'a qu'une lumière ponctuelle, sans cône ni atténuation
+        if let Some(light) = node.light() {
+            let label = match light.name() {
+                Some(name) => format!("light \"{}\"", name),
+                None => format!("light {}", light.index()),
+            };
+            if self.light.is_some() {
+                self.warn(format!(
+                    "{} is ignored, only the first light is imported",
+                    label
+                ));
+            } else {
+                if let Kind::Spot { .. } = light.kind() {
+                    self.warn(format!(
+                        "{} is a spot light, it is imported as a point light",
+                        label
+                    ));
+                }
+                let [r, g, b] = light.color().map(widen);
+                self.light = Some(Light {
+                    kind: light.kind(),
+                    position: transform_point(&world, Point3::new(0.0, 0.0, 0.0)),
+                    direction: vec3::unit_vector(transform_vector(
+                        &world,
+                        Vec3::new(0.0, 0.0, -1.0),
+                    )),
+                    color: Color::new(r, g, b),
+                    intensity: widen(light.intensity()),
+                });
+            }
+        }
+
+        for child in node.children() {
+            self.visit(&child, &world);
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings
+            .push(format!("{}: {}", self.path.display(), message));
+    }
+
+    // Maillage d'une primitive ; les points et les lignes sont ignorés
+    fn primitive(
+        &mut self,
+        primitive: &gltf::Primitive,
+        world: &Matrix,
+        name: Option<&str>,
+    ) -> Option<Mesh> {
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(Vec::as_slice));
+        let positions: Vec<Point3> = reader
+            .read_positions()?
+            .map(|[x, y, z]| transform_point(world, Vec3::new(x as f64, y as f64, z as f64)))
+            .collect();
+
+        // Les normales suivent la transposée de l'inverse (cofacteurs, au signe du déterminant près)
+        let axes = [0, 1, 2].map(|c| Vec3::new(world[c][0], world[c][1], world[c][2]));
+        let det = vec3::dot(axes[0], vec3::cross(axes[1], axes[2]));
+        let cofactors = [
+            vec3::cross(axes[1], axes[2]),
+            vec3::cross(axes[2], axes[0]),
+            vec3::cross(axes[0], axes[1]),
+        ];
+        let normals = reader
+            .read_normals()
+            .map(|normals| {
+                normals
+                    .map(|[x, y, z]| {
+                        let n = x as f64 * cofactors[0]
+                            + y as f64 * cofactors[1]
+                            + z as f64 * cofactors[2];
+                        vec3::unit_vector(det.signum() * n)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let material = primitive.material();
+        let pbr = material.pbr_metallic_roughness();
+        let set = pbr
+            .base_color_texture()
+            .map(|info| info.tex_coord())
+            .or_else(|| material.normal_texture().map(|info| info.tex_coord()))
+            .unwrap_or(0);
+        // glTF place v = 0 en haut de l'image, le moteur en bas
+        let uvs = reader
+            .read_tex_coords(set)
+            .map(|uvs| {
+                uvs.into_f32()
+                    .map(|[u, v]| [u as f64, 1.0 - v as f64])
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let mut triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            Mode::TriangleStrip => (2..indices.len())
+                .map(|i| match i % 2 {
+                    0 => [indices[i - 2], indices[i - 1], indices[i]],
+                    _ => [indices[i - 1], indices[i - 2], indices[i]],
+                })
+                .collect(),
+            Mode::TriangleFan => (2..indices.len())
+                .map(|i| [indices[0], indices[i - 1], indices[i]])
+                .collect(),
+            _ => return None,
+        };
+        // Une symétrie inverse l'ordre des sommets
+        if det < 0.0 {
+            triangles.iter_mut().for_each(|t| t.swap(1, 2));
+        }
+        if triangles.is_empty() {
+            return None;
+        }
+
+        Some(Mesh {
+            name: name.map(str::to_string),
+            positions,
+            normals,
+            uvs,
+            triangles,
+            material: self.material(&material),
+            bvh: Cache::default(),
+        })
+    }
+
+    // PBR métal-rugosité → couleur de base (texture ou facteur) et normal map.
+    // La métallicité, la rugosité, l'émission et la transparence n'ont pas d'équivalent.
+    fn material(&mut self, material: &gltf::Material) -> Material {
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor().map(widen);
+        let color = pbr
+            .base_color_texture()
+            .and_then(|info| self.texture(&info.texture()))
+            .unwrap_or(TextureConfig::Solid(Color::new(r, g, b)));
+
+        let mut imported = Material::from(color);
+        imported.material_name = material.name().map(str::to_string);
+        imported.normal_map = material
+            .normal_texture()
+            .and_then(|info| self.texture(&info.texture()));
+        imported
+    }
+
+    // Image d'une texture : fichier voisin, ou URI `data:` pour une image intégrée.
+    // Seules les images PNG, PPM et HDR sont lues par le moteur.
+    fn texture(&mut self, texture: &gltf::Texture) -> Option<TextureConfig> {
+        let file = match texture.source().source() {
+            image::Source::Uri { uri, .. } if uri.starts_with("data:") => [
+                "data:image/png;",
+                "data:image/x-portable-pixmap;",
+                "data:image/vnd.radiance;",
+            ]
+            .iter()
+            .any(|prefix| uri.starts_with(prefix))
+            .then(|| uri.to_string()),
+            image::Source::Uri { uri, .. } => {
+                let file = percent_decode(uri);
+                let extension = Path::new(&file).extension().and_then(|e| e.to_str());
+                extension
+                    .is_some_and(|e| {
+                        ["png", "ppm", "hdr"].contains(&e.to_ascii_lowercase().as_str())
+                    })
+                    .then_some(file)
+            }
+            image::Source::View { view, mime_type } => {
+                let data = self.buffers.get(view.buffer().index())?;
+                let bytes = data.get(view.offset()..view.offset() + view.length())?;
+                (mime_type == "image/png")
+                    .then(|| format!("data:image/png;base64,{}", STANDARD.encode(bytes)))
+            }
+        };
+
+        if file.is_none() {
+            self.warn(format!(
+                "image {} is not a PNG, PPM or HDR image, the base color is used instead",
+                texture.source().index()
+            ));
+        }
+        file.map(|file| {
+            TextureConfig::Pattern(Pattern::Image(ImageTexture {
+                file,
+                image: Cache::default(),
+            }))
+        })
+    }
+}
+
+fn load_buffers(path: &Path, gltf: &Gltf) -> Result<Vec<Vec<u8>>, Error> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    gltf.document
+        .buffers()
+        .map(|buffer| {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => gltf
+                    .blob
+                    .clone()
+                    .ok_or_else(|| Error::format(path, "missing binary chunk"))?,
+                gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                    let payload = uri
+                        .split_once(";base64,")
+                        .map_or("", |(_, payload)| payload);
+                    STANDARD.decode(payload).map_err(|e| {
+                        Error::format(path, format!("buffer {}: {}", buffer.index(), e))
+                    })?
+                }
+                gltf::buffer::Source::Uri(uri) => {
+                    let file = dir.join(percent_decode(uri));
+                    fs::read(&file).map_err(|e| Error::io(&file, e))?
+                }
+            };
+            if data.len() < buffer.length() {
+                let message = format!(
+                    "buffer {} has {} bytes, expected {}",
+                    buffer.index(),
+                    data.len(),
+                    buffer.length()
+                );
+                return Err(Error::format(path, message));
+            }
+            Ok(data)
+        })
+        .collect()
+}
+
+// 0.95f32 donne 0.949999988079071 en f64 : les couleurs et les angles gardent
+// l'écriture décimale la plus courte du f32, plus lisible une fois convertie
+fn widen(x: f32) -> f64 {
+    x.to_string().parse().unwrap_or(x as f64)
+}
+
+// Les URI relatives de glTF peuvent contenir des caractères encodés (%20 pour une espace)
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Centre et rayon de la sphère englobant les maillages du document
+fn bounds(root: &Map<String, Value>) -> (Point3, f64) {
+    let positions: Vec<Point3> = root
+        .get("meshes")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|mesh| mesh.get("positions"))
+        .filter_map(|positions| serde_json::from_value::<Vec<Point3>>(positions.clone()).ok())
+        .flatten()
+        .collect();
+    let Some(first) = positions.first() else {
+        return (Point3::new(0.0, 0.0, 0.0), 1.0);
+    };
+
+    let (min, max) = positions.iter().fold((*first, *first), |(min, max), p| {
+        (
+            Vec3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z())),
+            Vec3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z())),
+        )
+    });
+    let radius = (max - min).length() / 2.0;
+    ((min + max) / 2.0, if radius > 0.0 { radius } else { 1.0 })
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (col, column) in m.iter_mut().enumerate() {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    m
+}
+
+fn transform_point(m: &Matrix, p: Point3) -> Point3 {
+    transform_vector(m, p) + Vec3::new(m[3][0], m[3][1], m[3][2])
+}
+
+fn transform_vector(m: &Matrix, v: Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * v.x() + m[1][0] * v.y() + m[2][0] * v.z(),
+        m[0][1] * v.x() + m[1][1] * v.y() + m[2][1] * v.z(),
+        m[0][2] * v.x() + m[1][2] * v.y() + m[2][2] * v.z(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Un triangle avec coordonnées de texture, une caméra et une lumière ponctuelle,
+    // le tout dans un tampon intégré en data URI
+    fn write_model(path: &Path) {
+        let mut data = Vec::new();
+        for x in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        for x in [0.0f32, 0.0, 1.0, 0.0, 0.0, 1.0] {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        for i in [0u16, 1, 2] {
+            data.extend_from_slice(&i.to_le_bytes());
+        }
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            STANDARD.encode(&data)
+        );
+
+        let model = json!({
+            "asset": { "version": "2.0" },
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": { "KHR_lights_punctual": { "lights": [
+                { "type": "point", "color": [1.0, 0.5, 0.25], "intensity": 2.5 }
+            ] } },
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 1, 2] }],
+            "nodes": [
+                { "mesh": 0, "translation": [1.0, 0.0, 0.0], "scale": [2.0, 2.0, 2.0] },
+                { "camera": 0, "translation": [0.0, 0.0, 5.0] },
+                { "extensions": { "KHR_lights_punctual": { "light": 0 } },
+                  "translation": [0.0, 4.0, 0.0] }
+            ],
+            "cameras": [{ "type": "perspective", "perspective": {
+                "yfov": std::f32::consts::FRAC_PI_3, "znear": 0.1
+            } }],
+            "meshes": [{ "name": "triangle", "primitives": [{
+                "attributes": { "POSITION": 0, "TEXCOORD_0": 1 },
+                "indices": 2,
+                "material": 0
+            }] }],
+            "materials": [{ "name": "lilac", "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.25, 1.0, 1.0] } }],
+            "buffers": [{ "byteLength": data.len(), "uri": uri }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 24 },
+                { "buffer": 0, "byteOffset": 60, "byteLength": 6 }
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+                { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2" },
+                { "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }
+            ]
+        });
+        fs::write(path, model.to_string()).unwrap();
+    }
+
+    #[test]
+    fn imports_meshes_camera_and_light() {
+        let dir = std::env::temp_dir().join(format!("rt-gltf-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gltf");
+        write_model(&path);
+
+        let import = import(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        let Import { document, warnings } = import.unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        // Sommets passés dans le repère du monde, v retourné
+        let mesh = &document["meshes"][0];
+        assert_eq!(
+            mesh["positions"],
+            json!([[1.0, 0.0, 0.0], [3.0, 0.0, 0.0], [1.0, 2.0, 0.0]])
+        );
+        assert_eq!(mesh["uvs"], json!([[0.0, 1.0], [1.0, 1.0], [0.0, 0.0]]));
+        assert_eq!(mesh["triangles"], json!([[0, 1, 2]]));
+        assert_eq!(mesh["color"], json!([0.5, 0.25, 1.0]));
+        assert_eq!(mesh["name"], "triangle");
+        assert_eq!(mesh["material_name"], "lilac");
+
+        let camera = &document["camera"];
+        assert_eq!(camera["origin"], json!([0.0, 0.0, 5.0]));
+        assert_eq!(camera["look_at"], json!([0.0, 0.0, 4.0]));
+        assert!((camera["fov"].as_f64().unwrap() - 60.0).abs() < 1e-4);
+
+        let light = &document["light"];
+        assert_eq!(light["position"], json!([0.0, 4.0, 0.0]));
+        assert_eq!(light["color"], json!([1.0, 0.5, 0.25]));
+        assert_eq!(light["intensity"], json!(2.5));
+    }
+
+    #[test]
+    fn dropped_lights_and_images_are_reported() {
+        let dir = std::env::temp_dir().join(format!("rt-gltf-warnings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gltf");
+        write_model(&path);
+        let mut model: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        model["extensions"]["KHR_lights_punctual"]["lights"] = json!([
+            { "type": "spot", "name": "key", "spot": {} },
+            { "type": "directional" }
+        ]);
+        model["scenes"][0]["nodes"] = json!([0, 2, 3]);
+        model["nodes"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "extensions": { "KHR_lights_punctual": { "light": 1 } } }));
+        model["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"] = json!({ "index": 0 });
+        model["textures"] = json!([{ "source": 0 }]);
+        model["images"] = json!([{ "uri": "wood.jpg" }]);
+        fs::write(&path, model.to_string()).unwrap();
+
+        let import = import(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        let Import { document, warnings } = import.unwrap();
+
+        let messages: Vec<&str> = warnings
+            .iter()
+            .map(|w| w.split_once(": ").unwrap().1)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "image 0 is not a PNG, PPM or HDR image, the base color is used instead",
+                "light \"key\" is a spot light, it is imported as a point light",
+                "light 1 is ignored, only the first light is imported",
+            ]
+        );
+        // Le projecteur est gardé comme lumière ponctuelle, avec l'intensité par défaut de glTF
+        assert_eq!(document["light"]["position"], json!([0.0, 4.0, 0.0]));
+        assert_eq!(document["light"]["intensity"], json!(1.0));
+        assert_eq!(document["meshes"][0]["color"], json!([0.5, 0.25, 1.0]));
+    }
+
+    #[test]
+    fn missing_camera_and_light_get_defaults() {
+        let dir = std::env::temp_dir().join(format!("rt-gltf-scene-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gltf");
+        write_model(&path);
+        let mut model: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        model["scenes"][0]["nodes"] = json!([0]);
+        fs::write(&path, model.to_string()).unwrap();
+
+        let import = import_scene(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        let document = import.unwrap().document;
+
+        assert!(document["camera"]["origin"].is_array());
+        assert!(document["light"]["position"].is_array());
+        assert_eq!(document["meshes"].as_array().map(Vec::len), Some(1));
+    }
+}
@@ -0,0 +1,476 @@
+use std::{path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{self, Cache},
+    error::Error,
+    hittable::{HitRecord, Hittable},
+    image::Image,
+    material::{self, Material},
+    mesh::{intersect_triangle, slab_interval},
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+// Marge du rejet rapide des cellules : un rayon qui frôle un sommet reste testé
+// malgré les arrondis sur les altitudes
+const CELL_MARGIN: f64 = 1e-9;
+
+// Terrain défini par une grille d'altitudes régulière dans le plan XZ.
+// Les altitudes viennent d'une image en niveaux de gris (MNT) ou d'un tableau de valeurs.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Heightfield {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub origin: Point3, // Coin (x min, z min) à l'altitude 0
+    pub width: f64,     // Étendue selon X
+    pub depth: f64,     // Étendue selon Z
+    #[serde(default = "default_height_scale")]
+    pub height_scale: f64, // Altitude d'une valeur 1.0 de la grille
+    // Image en niveaux de gris, relative au fichier de la scène
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    // Grille brute : heights[ligne selon Z][colonne selon X]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heights: Option<Vec<Vec<f64>>>,
+    #[serde(flatten)]
+    pub material: Material,
+    #[serde(skip)]
+    grid: Cache<Option<Arc<HeightGrid>>>,
+}
+
+fn default_height_scale() -> f64 {
+    1.0
+}
+
+// Altitudes (en unités de la scène) et normales lissées aux sommets de la grille
+struct HeightGrid {
+    nx: usize,
+    nz: usize,
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    min_height: f64,
+    max_height: f64,
+}
+
+impl HeightGrid {
+    fn new(nx: usize, nz: usize, heights: Vec<f64>, dx: f64, dz: f64) -> Self {
+        let height = |i: usize, j: usize| heights[j * nx + i];
+
+        // Normales par différences centrées (décentrées sur les bords)
+        let mut normals = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+                let dhdx = (height(i1, j) - height(i0, j)) / ((i1 - i0) as f64 * dx);
+                let dhdz = (height(i, j1) - height(i, j0)) / ((j1 - j0) as f64 * dz);
+                normals.push(vec3::unit_vector(Vec3::new(-dhdx, 1.0, -dhdz)));
+            }
+        }
+
+        let min_height = heights.iter().copied().fold(common::INFINITY, f64::min);
+        let max_height = heights.iter().copied().fold(-common::INFINITY, f64::max);
+        HeightGrid {
+            nx,
+            nz,
+            heights,
+            normals,
+            min_height,
+            max_height,
+        }
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.nx + i]
+    }
+
+    fn normal(&self, i: usize, j: usize) -> Vec3 {
+        self.normals[j * self.nx + i]
+    }
+}
+
+impl Heightfield {
+    // Construit la grille depuis l'image ou le tableau, puis charge le matériau.
+    // Les grilles mal formées sont signalées par la validation de la scène.
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        self.material.load(base_dir)?;
+
+        let (nx, nz, values) = if let Some(file) = &self.file {
+            let path = base_dir.join(file);
+            let image = Image::load(&path)?;
+            if image.width < 2 || image.height < 2 {
+                return Err(Error::image(
+                    &path,
+                    "heightfield images need at least 2x2 pixels",
+                ));
+            }
+            // La ligne du haut de l'image correspond à z max (v = 1), comme pour les textures
+            let mut values = Vec::with_capacity(image.width * image.height);
+            for j in 0..image.height {
+                for i in 0..image.width {
+                    values.push(material::luminance(image.pixel(i, image.height - 1 - j)));
+                }
+            }
+            (image.width, image.height, values)
+        } else if let Some(heights) = &self.heights {
+            let nx = heights.first().map_or(0, |row| row.len());
+            if nx < 2 || heights.len() < 2 || heights.iter().any(|row| row.len() != nx) {
+                return Ok(());
+            }
+            (nx, heights.len(), heights.concat())
+        } else {
+            return Ok(());
+        };
+
+        let dx = self.width / (nx - 1) as f64;
+        let dz = self.depth / (nz - 1) as f64;
+        let heights = values
+            .iter()
+            .map(|h| self.origin.y() + h * self.height_scale)
+            .collect();
+        *self.grid = Some(Arc::new(HeightGrid::new(nx, nz, heights, dx, dz)));
+        Ok(())
+    }
+
+    // Intersection avec les deux triangles de la cellule (i, j)
+    fn hit_cell(
+        &self,
+        grid: &HeightGrid,
+        r: &Ray,
+        (i, j): (usize, usize),
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let dx = self.width / (grid.nx - 1) as f64;
+        let dz = self.depth / (grid.nz - 1) as f64;
+        let corner = |ci: usize, cj: usize| {
+            Point3::new(
+                self.origin.x() + ci as f64 * dx,
+                grid.height(ci, cj),
+                self.origin.z() + cj as f64 * dz,
+            )
+        };
+
+        // Sommets : 00, 10, 11, 01 ; triangles (00, 10, 11) et (00, 11, 01)
+        let vertices = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let mut closest = t_max;
+        let mut found = None;
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [a, b, c] = triangle.map(|k| vertices[k]);
+            if let Some((t, beta, gamma)) =
+                intersect_triangle(r, corner(a.0, a.1), corner(b.0, b.1), corner(c.0, c.1))
+            {
+                if t >= t_min && t <= closest {
+                    closest = t;
+                    found = Some((a, b, c, beta, gamma));
+                }
+            }
+        }
+
+        let Some((a, b, c, beta, gamma)) = found else {
+            return false;
+        };
+
+        // Normale lissée : interpolation barycentrique des normales aux sommets
+        let alpha = 1.0 - beta - gamma;
+        let n = vec3::unit_vector(
+            alpha * grid.normal(a.0, a.1)
+                + beta * grid.normal(b.0, b.1)
+                + gamma * grid.normal(c.0, c.1),
+        );
+
+        rec.t = closest;
+        rec.p = r.at(closest);
+        rec.set_face_normal(r, n);
+        rec.u = common::clamp((rec.p.x() - self.origin.x()) / self.width, 0.0, 1.0);
+        rec.v = common::clamp((rec.p.z() - self.origin.z()) / self.depth, 0.0, 1.0);
+        // Tangentes dans le plan de la normale lissée (n.y > 0 pour un terrain)
+        rec.dpdu = Vec3::new(self.width, -n.x() / n.y() * self.width, 0.0);
+        rec.dpdv = Vec3::new(0.0, -n.z() / n.y() * self.depth, self.depth);
+        true
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let Some(grid) = &*self.grid else {
+            return false;
+        };
+
+        // Boîte englobante du terrain
+        let box_min = Point3::new(self.origin.x(), grid.min_height, self.origin.z());
+        let box_max = Point3::new(
+            self.origin.x() + self.width,
+            grid.max_height,
+            self.origin.z() + self.depth,
+        );
+        let Some((t_enter, t_exit)) = slab_interval(r, box_min, box_max, t_min, t_max) else {
+            return false;
+        };
+
+        // Parcours 2D (DDA) des cellules traversées dans le plan XZ
+        let cells_x = grid.nx - 1;
+        let cells_z = grid.nz - 1;
+        let dx = self.width / cells_x as f64;
+        let dz = self.depth / cells_z as f64;
+        let start = r.at(t_enter);
+        let cell = |offset: f64, size: f64, count: usize| {
+            ((offset / size).floor().max(0.0) as usize).min(count - 1)
+        };
+        let mut i = cell(start.x() - self.origin.x(), dx, cells_x);
+        let mut j = cell(start.z() - self.origin.z(), dz, cells_z);
+
+        let dir = r.direction();
+        // Distance (en t) jusqu'à la prochaine frontière de cellule, et entre deux frontières
+        let axis_setup = |index: usize, size: f64, origin: f64, o: f64, d: f64| {
+            if d > 0.0 {
+                let boundary = origin + (index + 1) as f64 * size;
+                ((boundary - o) / d, size / d)
+            } else if d < 0.0 {
+                let boundary = origin + index as f64 * size;
+                ((boundary - o) / d, -size / d)
+            } else {
+                (common::INFINITY, common::INFINITY)
+            }
+        };
+        let (mut t_next_x, t_delta_x) = axis_setup(i, dx, self.origin.x(), r.origin().x(), dir.x());
+        let (mut t_next_z, t_delta_z) = axis_setup(j, dz, self.origin.z(), r.origin().z(), dir.z());
+
+        let mut t_cell_enter = t_enter;
+        loop {
+            let t_cell_exit = t_next_x.min(t_next_z).min(t_exit);
+
+            // Rejet rapide : le rayon reste au-dessus ou au-dessous de la cellule
+            let y0 = r.at(t_cell_enter).y();
+            let y1 = r.at(t_cell_exit).y();
+            let cell_min = grid
+                .height(i, j)
+                .min(grid.height(i + 1, j))
+                .min(grid.height(i, j + 1))
+                .min(grid.height(i + 1, j + 1));
+            let cell_max = grid
+                .height(i, j)
+                .max(grid.height(i + 1, j))
+                .max(grid.height(i, j + 1))
+                .max(grid.height(i + 1, j + 1));
+            let overlaps =
+                y0.min(y1) <= cell_max + CELL_MARGIN && y0.max(y1) >= cell_min - CELL_MARGIN;
+
+            if overlaps && self.hit_cell(grid, r, (i, j), t_min, t_max, rec) {
+                return true;
+            }
+
+            if t_cell_exit >= t_exit {
+                return false;
+            }
+
+            if t_next_x < t_next_z {
+                if dir.x() > 0.0 {
+                    if i + 1 >= cells_x {
+                        return false;
+                    }
+                    i += 1;
+                } else {
+                    if i == 0 {
+                        return false;
+                    }
+                    i -= 1;
+                }
+                t_next_x += t_delta_x;
+            } else {
+                if dir.z() > 0.0 {
+                    if j + 1 >= cells_z {
+                        return false;
+                    }
+                    j += 1;
+                } else {
+                    if j == 0 {
+                        return false;
+                    }
+                    j -= 1;
+                }
+                t_next_z += t_delta_z;
+            }
+            t_cell_enter = t_cell_exit;
+        }
+    }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Grille 5 x 4 de 4 x 3 unités, altitudes entre 0 et 2 au-dessus de y = -1
+    fn terrain() -> Heightfield {
+        let mut heightfield: Heightfield = serde_json::from_value(json!({
+            "origin": [-2, -1, -1],
+            "width": 4,
+            "depth": 3,
+            "height_scale": 2,
+            "heights": [
+                [0.0, 0.2, 0.5, 0.3, 0.1],
+                [0.1, 0.6, 1.0, 0.4, 0.2],
+                [0.3, 0.8, 0.7, 0.2, 0.0],
+                [0.2, 0.4, 0.3, 0.1, 0.5]
+            ],
+            "color": 1.0
+        }))
+        .unwrap();
+        heightfield.load(Path::new(".")).unwrap();
+        heightfield
+    }
+
+    fn hit(heightfield: &Heightfield, origin: Point3, direction: Vec3) -> Option<f64> {
+        let mut rec = HitRecord::new();
+        heightfield
+            .hit(
+                &Ray::new(origin, direction),
+                0.001,
+                common::INFINITY,
+                &mut rec,
+            )
+            .then_some(rec.t)
+    }
+
+    // Référence sans DDA : toutes les cellules sont testées
+    fn brute_force(heightfield: &Heightfield, origin: Point3, direction: Vec3) -> Option<f64> {
+        let grid = heightfield.grid.as_ref().unwrap();
+        let r = Ray::new(origin, direction);
+        let mut closest = None;
+        for j in 0..grid.nz - 1 {
+            for i in 0..grid.nx - 1 {
+                let mut rec = HitRecord::new();
+                let t_max = closest.unwrap_or(common::INFINITY);
+                if heightfield.hit_cell(grid, &r, (i, j), 0.001, t_max, &mut rec) {
+                    closest = Some(rec.t);
+                }
+            }
+        }
+        closest
+    }
+
+    fn assert_matches(heightfield: &Heightfield, origin: Point3, direction: Vec3) -> Option<f64> {
+        let found = hit(heightfield, origin, direction);
+        let expected = brute_force(heightfield, origin, direction);
+        match (found, expected) {
+            (Some(found), Some(expected)) => {
+                assert!(
+                    (found - expected).abs() < 1e-9,
+                    "{} {}: {} != {}",
+                    origin,
+                    direction,
+                    found,
+                    expected
+                )
+            }
+            _ => assert_eq!(found, expected, "{} {}", origin, direction),
+        }
+        found
+    }
+
+    #[test]
+    fn axis_aligned_rays() {
+        let heightfield = terrain();
+        for k in 0..=30 {
+            let s = k as f64 / 30.0;
+            let (x, z) = (-2.0 + 4.0 * s, -1.0 + 3.0 * s);
+            // Rayons verticaux : une seule cellule, toujours touchée
+            assert!(assert_matches(
+                &heightfield,
+                Point3::new(x, 5.0, z - 0.3 * s),
+                Vec3::new(0.0, -1.0, 0.0)
+            )
+            .is_some());
+            // Rayons horizontaux selon ±X et ±Z, à plusieurs altitudes
+            for y in [-0.9, -0.5, 0.0, 0.5] {
+                assert_matches(
+                    &heightfield,
+                    Point3::new(-5.0, y, z),
+                    Vec3::new(1.0, 0.0, 0.0),
+                );
+                assert_matches(
+                    &heightfield,
+                    Point3::new(5.0, y, z),
+                    Vec3::new(-1.0, 0.0, 0.0),
+                );
+                assert_matches(
+                    &heightfield,
+                    Point3::new(x, y, -5.0),
+                    Vec3::new(0.0, 0.0, 1.0),
+                );
+                assert_matches(
+                    &heightfield,
+                    Point3::new(x, y, 5.0),
+                    Vec3::new(0.0, 0.0, -1.0),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rays_entering_through_a_side_face() {
+        let heightfield = terrain();
+        // Sous le sommet de la ligne z = 0 (altitude 1), le rayon entre par la face x min
+        let t = assert_matches(
+            &heightfield,
+            Point3::new(-4.0, 0.5, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        )
+        .unwrap();
+        let x = -4.0 + t;
+        assert!(x > -2.0 && x < 0.0, "{}", x);
+        // Même chose en descendant légèrement, depuis la face z max
+        let direction = Vec3::new(0.2, -0.1, -1.0);
+        assert!(assert_matches(&heightfield, Point3::new(-1.1, 0.3, 3.0), direction).is_some());
+    }
+
+    #[test]
+    fn rays_crossing_cell_diagonals() {
+        let heightfield = terrain();
+        // Les rayons passent exactement par les coins des cellules (pas de 1 dans les deux axes)
+        for y in [-0.8, -0.4, 0.0, 0.4] {
+            for start in [-3.0, -2.0, -1.0] {
+                let origin = Point3::new(start, y, start + 1.0);
+                assert_matches(&heightfield, origin, Vec3::new(1.0, 0.0, 1.0));
+                assert_matches(
+                    &heightfield,
+                    origin + Vec3::new(6.0, 0.0, 6.0),
+                    Vec3::new(-1.0, 0.0, -1.0),
+                );
+                assert_matches(
+                    &heightfield,
+                    origin + Vec3::new(0.0, 0.5, 0.0),
+                    Vec3::new(1.0, -0.1, 1.0),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rays_above_the_highest_point_miss() {
+        let heightfield = terrain();
+        // Altitude max : -1 + 2 * 1.0 = 1
+        for direction in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.3, 0.01, -1.0),
+        ] {
+            assert_eq!(
+                hit(
+                    &heightfield,
+                    Point3::new(-1.0, 1.001, 0.5) - 3.0 * direction,
+                    direction
+                ),
+                None
+            );
+        }
+    }
+}
@@ -7,8 +7,20 @@ pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    // Dérivées partielles de la position par rapport à u et v (repère tangent)
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub front_face: bool,
     pub color: Color,
+    // Point de diffusion dans un volume : éclairage sans terme en cosinus
+    pub isotropic: bool,
+    // Indice de l'objet touché dans la liste de la scène
+    pub object: usize,
+    // Indice de l'objet touché dans chaque liste traversée, de la plus imbriquée à celle
+    // de la scène : `HittableList::shade` les dépile pour retrouver l'objet
+    pub path: Vec<usize>,
 }
 
 impl HitRecord {
@@ -24,8 +36,26 @@ impl HitRecord {
             -outward_normal
         };
     }
+
+    // Surfaces sans paramétrisation naturelle (SDF, métaballes) : repère tangent
+    // arbitraire autour de la normale et (u, v) = (0, 0)
+    pub fn set_arbitrary_tangent_frame(&mut self, outward_normal: Vec3) {
+        let helper = if outward_normal.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        self.dpdu = vec3::unit_vector(vec3::cross(helper, outward_normal));
+        self.dpdv = vec3::cross(outward_normal, self.dpdu);
+        self.u = 0.0;
+        self.v = 0.0;
+    }
 }
 
 pub trait Hittable {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+
+    // Couleur et normale d'ombrage du point trouvé par `hit`. Appelé une seule fois, sur
+    // le point le plus proche : les rayons d'ombre n'en ont pas besoin.
+    fn shade(&self, _rec: &mut HitRecord) {}
 }
@@ -1,11 +1,19 @@
 use crate::cube::Cube;
 use crate::cylinder::Cylinder;
 use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
 use crate::param::Scene;
 use crate::plane::Plane;
 use crate::ray::Ray;
 use crate::sphere::Sphere;
 
+// Nom d'un objet du monde et de son matériau, pour les mattes
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLabel {
+    pub name: String,
+    pub material: Option<String>,
+}
+
 #[derive(Default)]
 pub struct HittableList {
     objects: Vec<Box<dyn Hittable>>,
@@ -20,40 +28,106 @@ impl HittableList {
         self.objects.push(object);
     }
 
-    pub fn set_scene(&mut self, scene: Scene) {
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    // Ajoute les objets de la scène au monde. Tous les objets ont un champ `name` facultatif,
+    // repris dans les mattes : l'étiquette de chaque objet, renvoyée dans l'ordre d'ajout,
+    // est ce nom ou sinon sa place dans la scène (`spheres[0]`), avec son matériau nommé.
+    pub fn set_scene(&mut self, scene: Scene) -> Vec<ObjectLabel> {
+        let mut labels = Vec::new();
+        let mut label =
+            |list: &str, i: usize, name: Option<String>, material: Option<&Material>| {
+                labels.push(ObjectLabel {
+                    name: name.unwrap_or_else(|| format!("{}[{}]", list, i)),
+                    material: material.and_then(|m| m.material_name.clone()),
+                });
+            };
+
         // Ajouter les sphères
-        for sphere in scene.spheres {
+        for (i, sphere) in scene.spheres.into_iter().enumerate() {
+            label("spheres", i, sphere.name, Some(&sphere.material));
             self.add(Box::new(Sphere::new(
                 sphere.center,
                 sphere.radius,
-                sphere.color,
+                sphere.material,
             )));
         }
 
         // Ajouter les plans
-        for plane in scene.planes {
+        for (i, plane) in scene.planes.into_iter().enumerate() {
+            label("planes", i, plane.name, Some(&plane.material));
             self.add(Box::new(Plane::new(
                 plane.origine,
                 plane.width,
                 plane.height,
-                plane.color,
+                plane.material,
             )));
         }
 
         // Ajouter les cubes
-        for cube in scene.cubes {
-            self.add(Box::new(Cube::new(cube.min, cube.max, cube.color)));
+        for (i, cube) in scene.cubes.into_iter().enumerate() {
+            label("cubes", i, cube.name, Some(&cube.material));
+            self.add(Box::new(Cube::new(cube.min, cube.max, cube.material)));
         }
 
         // Ajouter les cylindres
-        for cylinder in scene.cylinders {
+        for (i, cylinder) in scene.cylinders.into_iter().enumerate() {
+            label("cylinders", i, cylinder.name, Some(&cylinder.material));
             self.add(Box::new(Cylinder::new(
                 cylinder.base,
                 cylinder.height,
                 cylinder.radius,
-                cylinder.color,
+                cylinder.material,
             )));
         }
+
+        // Ajouter les objets SDF
+        for (i, sdf) in scene.sdfs.into_iter().enumerate() {
+            label("sdfs", i, sdf.name.clone(), Some(&sdf.material));
+            self.add(Box::new(sdf));
+        }
+
+        // Ajouter les métaballes
+        for (i, metaballs) in scene.metaballs.into_iter().enumerate() {
+            label(
+                "metaballs",
+                i,
+                metaballs.name.clone(),
+                Some(&metaballs.material),
+            );
+            self.add(Box::new(metaballs));
+        }
+
+        // Ajouter les terrains
+        for (i, heightfield) in scene.heightfields.into_iter().enumerate() {
+            label(
+                "heightfields",
+                i,
+                heightfield.name.clone(),
+                Some(&heightfield.material),
+            );
+            self.add(Box::new(heightfield));
+        }
+
+        // Ajouter les maillages
+        for (i, mesh) in scene.meshes.into_iter().enumerate() {
+            label("meshes", i, mesh.name.clone(), Some(&mesh.material));
+            self.add(Box::new(mesh));
+        }
+
+        // Ajouter les volumes, qui n'ont pas de matériau
+        for (i, volume) in scene.volumes.into_iter().enumerate() {
+            label("volumes", i, volume.name.clone(), None);
+            self.add(Box::new(volume.build()));
+        }
+
+        labels
     }
 }
 
@@ -63,14 +137,26 @@ impl Hittable for HittableList {
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
-        for object in &self.objects {
+        for (index, object) in self.objects.iter().enumerate() {
+            // Seule une liste imbriquée remplit le chemin
+            temp_rec.path.clear();
             if object.hit(ray, t_min, closest_so_far, &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
+                temp_rec.object = index;
+                temp_rec.path.push(index);
                 *rec = temp_rec.clone();
             }
         }
 
         hit_anything
     }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        let index = rec
+            .path
+            .pop()
+            .expect("shading a record this list did not hit");
+        self.objects[index].shade(rec);
+    }
 }
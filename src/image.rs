@@ -0,0 +1,433 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{color::Color, error::Error};
+
+// Image en mémoire, ligne du haut en premier.
+// Couleurs normalisées dans [0, 1], sauf pour les images HDR (radiance linéaire).
+#[derive(Clone, Default)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    // Charge une image PPM (P3 / P6), PNG ou Radiance HDR selon l'extension du fichier
+    pub fn load(file_path: &Path) -> Result<Image, Error> {
+        let extension = file_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        let decode = match extension.as_deref() {
+            Some("png") => Self::decode_png,
+            Some("ppm") => Self::decode_ppm,
+            Some("hdr") => Self::decode_hdr,
+            _ => return Err(Error::image(file_path, "unsupported image format")),
+        };
+        let data = fs::read(file_path).map_err(|e| Error::io(file_path, e))?;
+        decode(file_path, &data)
+    }
+
+    // Image intégrée à la scène : `data:<type MIME>;base64,<données>` (textures des fichiers glTF)
+    pub fn from_data_uri(uri: &str) -> Result<Image, Error> {
+        let (header, payload) = uri.split_once(',').unwrap_or((uri, ""));
+        let name = Path::new(header);
+        let Some(mime_type) = header
+            .strip_prefix("data:")
+            .and_then(|h| h.strip_suffix(";base64"))
+        else {
+            return Err(Error::image(name, "expected a base64 data URI"));
+        };
+
+        let decode = match mime_type {
+            "image/png" => Self::decode_png,
+            "image/x-portable-pixmap" => Self::decode_ppm,
+            "image/vnd.radiance" => Self::decode_hdr,
+            _ => return Err(Error::image(name, "unsupported image format")),
+        };
+        let data = STANDARD
+            .decode(payload)
+            .map_err(|e| Error::image(name, e.to_string()))?;
+        decode(name, &data)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    fn decode_png(file_path: &Path, data: &[u8]) -> Result<Image, Error> {
+        let invalid = |e: png::DecodingError| Error::image(file_path, e.to_string());
+        let mut decoder = png::Decoder::new(data);
+        // Les 16 bits sont conservés (cartes d'élévation)
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(invalid)?;
+
+        let channels = info.color_type.samples();
+        let samples: Vec<f64> = match info.bit_depth {
+            png::BitDepth::Sixteen => buffer[..info.buffer_size()]
+                .chunks(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as f64 / 65535.0)
+                .collect(),
+            _ => buffer[..info.buffer_size()]
+                .iter()
+                .map(|b| *b as f64 / 255.0)
+                .collect(),
+        };
+        let pixels = samples
+            .chunks(channels)
+            .map(|px| {
+                let c = |i: usize| px[i];
+                match channels {
+                    1 | 2 => Color::new(c(0), c(0), c(0)), // Niveaux de gris (+ alpha)
+                    _ => Color::new(c(0), c(1), c(2)),     // RGB (+ alpha)
+                }
+            })
+            .collect();
+
+        Ok(Image {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    fn decode_ppm(file_path: &Path, data: &[u8]) -> Result<Image, Error> {
+        let malformed = || Error::image(file_path, "malformed PPM file");
+        let mut pos = 0;
+
+        let magic = next_token(data, &mut pos);
+        let width: usize = parse_token(data, &mut pos).ok_or_else(malformed)?;
+        let height: usize = parse_token(data, &mut pos).ok_or_else(malformed)?;
+        let max_value: u32 = parse_token(data, &mut pos).ok_or_else(malformed)?;
+        // Le format limite les valeurs à 16 bits ; 0 diviserait par zéro
+        if !(1..=65535).contains(&max_value) {
+            return Err(malformed());
+        }
+        let max_value = max_value as f64;
+
+        // Taille annoncée par l'en-tête : refusée si elle déborde, et la réservation est
+        // bornée par la taille du fichier (au moins un octet par valeur)
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(malformed)?;
+        let mut samples = Vec::with_capacity(count.min(data.len()));
+        match magic.as_str() {
+            "P3" => {
+                for _ in 0..count {
+                    let value: f64 = parse_token(data, &mut pos).ok_or_else(malformed)?;
+                    samples.push(value / max_value);
+                }
+            }
+            "P6" => {
+                // Un seul caractère blanc sépare l'en-tête des données binaires
+                pos += 1;
+                let wide = max_value > 255.0;
+                let bytes = data.get(pos..).unwrap_or_default();
+                let expected = count.checked_mul(if wide { 2 } else { 1 });
+                if expected.is_none_or(|expected| bytes.len() < expected) {
+                    return Err(Error::image(file_path, "truncated PPM file"));
+                }
+                for i in 0..count {
+                    let value = if wide {
+                        u16::from_be_bytes([bytes[2 * i], bytes[2 * i + 1]]) as f64
+                    } else {
+                        bytes[i] as f64
+                    };
+                    samples.push(value / max_value);
+                }
+            }
+            _ => {
+                let message = format!("unsupported PPM format: {}", magic);
+                return Err(Error::image(file_path, message));
+            }
+        }
+
+        let pixels = samples
+            .chunks(3)
+            .map(|c| Color::new(c[0], c[1], c[2]))
+            .collect();
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    // Radiance RGBE (.hdr) : valeurs en radiance linéaire, non bornées à 1
+    fn decode_hdr(file_path: &Path, data: &[u8]) -> Result<Image, Error> {
+        let malformed = || Error::image(file_path, "malformed HDR file");
+        let mut pos = 0;
+
+        // En-tête : lignes de texte jusqu'à une ligne vide
+        let read_line = |pos: &mut usize| {
+            let start = *pos;
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+            let line = String::from_utf8_lossy(&data[start..*pos]).into_owned();
+            *pos += 1;
+            line
+        };
+
+        let magic = read_line(&mut pos);
+        if !magic.starts_with("#?") {
+            return Err(malformed());
+        }
+        loop {
+            let line = read_line(&mut pos);
+            if line.trim().is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line.trim() != "FORMAT=32-bit_rle_rgbe" {
+                let message = format!("unsupported HDR format: {}", line.trim());
+                return Err(Error::image(file_path, message));
+            }
+        }
+
+        // Résolution : « -Y hauteur +X largeur » (orientation standard)
+        let resolution = read_line(&mut pos);
+        let fields: Vec<&str> = resolution.split_whitespace().collect();
+        if fields.len() != 4 || fields[0] != "-Y" || fields[2] != "+X" {
+            let message = format!("unsupported HDR orientation: {}", resolution.trim());
+            return Err(Error::image(file_path, message));
+        }
+        let height: usize = fields[1].parse().map_err(|_| malformed())?;
+        let width: usize = fields[3].parse().map_err(|_| malformed())?;
+        // Une ligne vide ne consomme rien : sans ce refus, une hauteur immense ne finirait pas
+        if width == 0 || height == 0 {
+            return Err(malformed());
+        }
+
+        // Une ligne RLE compte moins de 0x8000 pixels, une ligne brute 4 octets par pixel
+        if width >= 0x8000 && width.checked_mul(4).is_none_or(|n| n > data.len()) {
+            return Err(Error::image(file_path, "truncated HDR file"));
+        }
+        let count = width.checked_mul(height).ok_or_else(malformed)?;
+        let mut pixels = Vec::with_capacity(count.min(data.len()));
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            read_hdr_scanline(data, &mut pos, &mut scanline)
+                .ok_or_else(|| Error::image(file_path, "truncated HDR file"))?;
+            pixels.extend(scanline.iter().map(|rgbe| rgbe_to_color(*rgbe)));
+        }
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+// None si le fichier est tronqué
+fn read_hdr_scanline(data: &[u8], pos: &mut usize, scanline: &mut [[u8; 4]]) -> Option<()> {
+    let width = scanline.len();
+    let byte = |i: usize| data.get(i).copied();
+
+    // Nouveau format RLE : 2 2 suivi de la largeur sur deux octets
+    let is_rle = (8..0x8000).contains(&width)
+        && byte(*pos) == Some(2)
+        && byte(*pos + 1) == Some(2)
+        && byte(*pos + 2)? & 0x80 == 0;
+
+    if !is_rle {
+        // Pixels non compressés
+        for pixel in scanline.iter_mut() {
+            pixel.copy_from_slice(data.get(*pos..*pos + 4)?);
+            *pos += 4;
+        }
+        return Some(());
+    }
+
+    *pos += 4;
+    // Chaque composante est compressée séparément
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = byte(*pos)? as usize;
+            *pos += 1;
+            if count == 0 || count == 128 {
+                return None; // Longueur nulle : fichier corrompu
+            }
+            if count > 128 {
+                let run = count - 128;
+                let value = byte(*pos)?;
+                *pos += 1;
+                for pixel in &mut scanline[x..(x + run).min(width)] {
+                    pixel[channel] = value;
+                }
+                x += run;
+            } else {
+                for pixel in &mut scanline[x..(x + count).min(width)] {
+                    pixel[channel] = byte(*pos)?;
+                    *pos += 1;
+                }
+                x += count;
+            }
+        }
+    }
+    Some(())
+}
+
+fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let f = 2f64.powi(rgbe[3] as i32 - 128 - 8);
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * f,
+        (rgbe[1] as f64 + 0.5) * f,
+        (rgbe[2] as f64 + 0.5) * f,
+    )
+}
+
+// Lit le prochain mot de l'en-tête PPM en ignorant les commentaires
+fn next_token(data: &[u8], pos: &mut usize) -> String {
+    loop {
+        while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < data.len() && data[*pos] == b'#' {
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+            continue;
+        }
+        break;
+    }
+
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    String::from_utf8_lossy(&data[start..*pos]).into_owned()
+}
+
+fn parse_token<T: std::str::FromStr>(data: &[u8], pos: &mut usize) -> Option<T> {
+    next_token(data, pos).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: [[u8; 3]; 6] = [
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [255, 255, 255],
+        [128, 64, 32],
+        [0, 0, 0],
+    ];
+
+    fn decode_ppm(data: &[u8]) -> Result<Image, String> {
+        Image::decode_ppm(Path::new("test.ppm"), data).map_err(|e| e.to_string())
+    }
+
+    fn decode_hdr(data: &[u8]) -> Result<Image, String> {
+        Image::decode_hdr(Path::new("test.hdr"), data).map_err(|e| e.to_string())
+    }
+
+    fn assert_pixels(image: &Image) {
+        assert_eq!((image.width, image.height), (3, 2));
+        for (pixel, expected) in image.pixels.iter().zip(PIXELS) {
+            for (value, expected) in [pixel.x(), pixel.y(), pixel.z()].into_iter().zip(expected) {
+                assert!((value - expected as f64 / 255.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn ppm_round_trips() {
+        // Texte, avec un commentaire dans l'en-tête
+        let mut text = String::from("P3\n# commentaire\n3 2\n255\n");
+        for pixel in PIXELS {
+            text.push_str(&format!("{} {} {}\n", pixel[0], pixel[1], pixel[2]));
+        }
+        assert_pixels(&decode_ppm(text.as_bytes()).unwrap());
+
+        // Binaire sur 8 bits
+        let mut binary = b"P6 3 2 255\n".to_vec();
+        binary.extend(PIXELS.iter().flatten());
+        assert_pixels(&decode_ppm(&binary).unwrap());
+
+        // Binaire sur 16 bits, valeurs en big-endian
+        let mut wide = b"P6 3 2 65535\n".to_vec();
+        wide.extend(PIXELS.iter().flatten().flat_map(|&c| (c as u16 * 257).to_be_bytes()));
+        let image = decode_ppm(&wide).unwrap();
+        assert_eq!(image.pixel(1, 1).x(), 128.0 * 257.0 / 65535.0);
+        assert_pixels(&image);
+    }
+
+    #[test]
+    fn ppm_header_is_not_trusted() {
+        let cases: [&[u8]; 5] = [
+            b"P3 100000 100000 255\n1 2 3",
+            b"P3 18446744073709551615 2 255\n1 2 3",
+            b"P6 100000 100000 255\n123",
+            b"P6 18446744073709551615 18446744073709551615 255\n123",
+            b"P3 2 1 255\n1 2 3 4 5",
+        ];
+        for data in cases {
+            assert!(decode_ppm(data).is_err(), "{}", String::from_utf8_lossy(data));
+        }
+        assert_eq!(
+            decode_ppm(b"P6 2 1 255\n12345").err().unwrap(),
+            "test.ppm: truncated PPM file"
+        );
+        assert_eq!(
+            decode_ppm(b"P5 2 1 255\n12").err().unwrap(),
+            "test.ppm: unsupported PPM format: P5"
+        );
+    }
+
+    #[test]
+    fn ppm_max_value_is_checked() {
+        for data in [
+            &b"P3 1 1 0\n0 0 0"[..],
+            b"P3 1 1 65536\n0 0 0",
+            b"P6 1 1 0\n123",
+            b"P3 1 1 -1\n0 0 0",
+        ] {
+            assert_eq!(
+                decode_ppm(data).err().as_deref(),
+                Some("test.ppm: malformed PPM file"),
+                "{}",
+                String::from_utf8_lossy(data)
+            );
+        }
+        assert_eq!(decode_ppm(b"P3 1 1 1\n1 0 1").unwrap().pixel(0, 0).x(), 1.0);
+    }
+
+    #[test]
+    fn hdr_size_is_checked() {
+        let header = "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n";
+        for resolution in ["-Y 18446744073709551615 +X 0", "-Y 0 +X 4", "-Y 0 +X 0"] {
+            let data = format!("{}{}\n", header, resolution);
+            assert_eq!(
+                decode_hdr(data.as_bytes()).err().as_deref(),
+                Some("test.hdr: malformed HDR file"),
+                "{}",
+                resolution
+            );
+        }
+        // Une hauteur immense s'arrête à la fin des données
+        let pixel = |resolution: &str| {
+            let mut data = format!("{}{}\n", header, resolution).into_bytes();
+            data.extend([128, 128, 128, 129]);
+            decode_hdr(&data)
+        };
+        assert_eq!(
+            pixel("-Y 18446744073709551615 +X 1").err().as_deref(),
+            Some("test.hdr: truncated HDR file")
+        );
+        assert_eq!(pixel("-Y 1 +X 1").unwrap().pixel(0, 0).x(), 1.00390625);
+    }
+}
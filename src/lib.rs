@@ -0,0 +1,42 @@
+// Bibliothèque du lancer de rayons : chargement des scènes, objets et moteur de rendu.
+// Le binaire `rt` n'est qu'une interface en ligne de commande au-dessus de `Renderer`.
+
+pub mod aov;
+pub mod background;
+pub mod camera;
+pub mod color;
+pub mod common;
+pub mod compose;
+pub mod cube;
+pub mod cylinder;
+pub mod error;
+pub mod format;
+pub mod framebuffer;
+pub mod gltf_import;
+pub mod heightfield;
+pub mod hittable;
+pub mod hittable_list;
+pub mod image;
+pub mod light;
+pub mod material;
+pub mod matte;
+pub mod medium;
+pub mod mesh;
+pub mod metaballs;
+pub mod output;
+pub mod param;
+pub mod perlin;
+pub mod plane;
+pub mod ray;
+pub mod renderer;
+pub mod sampler;
+pub mod sdf;
+pub mod sphere;
+pub mod texture;
+pub mod validate;
+pub mod vec3;
+
+pub use error::Error;
+pub use framebuffer::Framebuffer;
+pub use param::Scene;
+pub use renderer::{Frame, Progressive, Renderer, RendererBuilder};
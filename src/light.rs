@@ -1,9 +1,13 @@
-use crate::{color::Color, vec3::Point3};
+use crate::{
+    color::{self, Color},
+    vec3::Point3,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct Light {
     pub position: Point3,
+    #[serde(deserialize_with = "color::deserialize")]
     pub color: Color,
     pub intensity: f64,
 }
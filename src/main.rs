@@ -1,27 +1,294 @@
-use output::Output;
-
-pub mod output;
-pub mod vec3;
-pub mod color;
-pub mod ray;
-pub mod hittable;
-pub mod sphere;
-pub mod hittable_list;
-pub mod common;
-pub mod camera;
-pub mod plane;
-pub mod light;
-pub mod cube;
-pub mod cylinder;
-pub mod param;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
-fn main() {
-    println!("おはよう世界！");
+use rt::{
+    aov::Aov,
+    camera::{Camera, StereoLayout},
+    matte::MatteKind,
+    output,
+    renderer::{Adaptive, Update, VUP},
+    sampler::SamplerKind,
+    Error, Frame, Progressive, Renderer, Scene,
+};
+
+const USAGE: &str = "usage: rt [--transparent] [--spp N] [--adaptive THRESHOLD] [--min-spp N]
+          [--sampler independent|stratified|halton|sobol|blue_noise]
+          [--aov depth,normal,...] [--matte object,material] [--separate-aovs]
+          [--progressive] [--time SECONDS] [--save-every N|SECONDSs]
+          [--crop X,Y,WIDTH,HEIGHT] [--resize WIDTHxHEIGHT]
+          [-o output.ppm|output.png|output.exr] [scene]
+       rt convert <input> <output>
+passes: depth, normal, albedo, object_id, uv, direct, indirect, shadow, samples";
+
+// Passe à vrai sur Ctrl-C pendant un rendu progressif
+static STOP: AtomicBool = AtomicBool::new(false);
+
+// Options du rendu en ligne de commande
+struct Options<'a> {
+    scene: &'a str,
+    output: &'a str,
+    transparent: bool,
+    aovs: Vec<Aov>,
+    mattes: Vec<MatteKind>,
+    separate_aovs: bool,
+    samples_per_pixel: Option<u32>,
+    adaptive: Option<Adaptive>,
+    sampler: Option<SamplerKind>,
+    progressive: Option<Progressive>,
+    framing: output::Framing,
+}
+
+impl<'a> Options<'a> {
+    fn parse(args: &[&'a str]) -> Option<Self> {
+        let mut options = Options {
+            scene: "scene.json",
+            output: "output.ppm",
+            transparent: false,
+            aovs: Vec::new(),
+            mattes: Vec::new(),
+            separate_aovs: false,
+            samples_per_pixel: None,
+            adaptive: None,
+            sampler: None,
+            progressive: None,
+            framing: output::Framing::default(),
+        };
+        let mut scene = None;
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            match arg {
+                "--transparent" => options.transparent = true,
+                "-o" | "--output" => options.output = args.next()?,
+                "--aov" => {
+                    for name in args.next()?.split(',') {
+                        options.aovs.push(Aov::from_name(name.trim())?);
+                    }
+                }
+                "--matte" => {
+                    for name in args.next()?.split(',') {
+                        options.mattes.push(MatteKind::from_name(name.trim())?);
+                    }
+                }
+                "--separate-aovs" => options.separate_aovs = true,
+                "--spp" => options.samples_per_pixel = Some(args.next()?.parse().ok()?),
+                "--sampler" => options.sampler = Some(SamplerKind::from_name(args.next()?)?),
+                // Les réglages de l'échantillonnage adaptatif l'activent
+                "--adaptive" => {
+                    let threshold = args.next()?.parse().ok()?;
+                    options
+                        .adaptive
+                        .get_or_insert_with(Adaptive::default)
+                        .threshold = threshold;
+                }
+                "--min-spp" => {
+                    let min_samples = args.next()?.parse().ok()?;
+                    options
+                        .adaptive
+                        .get_or_insert_with(Adaptive::default)
+                        .min_samples = min_samples;
+                }
+                // Les réglages du rendu progressif l'activent
+                "--progressive" => {
+                    options.progressive.get_or_insert_with(Progressive::default);
+                }
+                "--time" => {
+                    let seconds = args.next()?.parse().ok()?;
+                    let progressive = options.progressive.get_or_insert_with(Progressive::default);
+                    progressive.time_limit = Some(Duration::try_from_secs_f64(seconds).ok()?);
+                }
+                "--save-every" => {
+                    let update = parse_update(args.next()?)?;
+                    options
+                        .progressive
+                        .get_or_insert_with(Progressive::default)
+                        .update = update;
+                }
+                "--crop" => options.framing.crop = Some(parse_crop(args.next()?)?),
+                "--resize" => options.framing.size = Some(parse_size(args.next()?)?),
+                _ if arg.starts_with('-') || scene.is_some() => return None,
+                _ => scene = Some(arg),
+            }
+        }
+        options.scene = scene.unwrap_or(options.scene);
+        Some(options)
+    }
+}
 
-    let mut out = Output::new();
-    out.init_pixel_color();
+// "10" : toutes les 10 passes, "2.5s" : toutes les 2,5 secondes
+fn parse_update(arg: &str) -> Option<Update> {
+    match arg.strip_suffix('s') {
+        Some(seconds) => Duration::try_from_secs_f64(seconds.parse().ok()?)
+            .ok()
+            .map(Update::Interval),
+        None => arg.parse().ok().filter(|&n| n > 0).map(Update::Passes),
+    }
+}
+
+// "10,20,320,240" : le rectangle de 320x240 pixels commençant en (10, 20)
+fn parse_crop(arg: &str) -> Option<(usize, usize, usize, usize)> {
+    let values: Vec<usize> = arg
+        .split(',')
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Some((x, y, width, height)),
+        _ => None,
+    }
+}
+
+// "640x480"
+fn parse_size(arg: &str) -> Option<(usize, usize)> {
+    let (width, height) = arg.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+// Traduit une scène d'un format à l'autre, sans charger les images ni valider.
+// Les inclusions et les définitions nommées sont développées.
+fn convert(input: &str, output: &str) -> Result<(), Error> {
+    let scene = Scene::read(Path::new(input))?;
+    warn(&scene);
+    scene.to_file(Path::new(output))
+}
+
+fn warn(scene: &Scene) {
+    for warning in &scene.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+// Rend chaque caméra de la scène dans output.ppm, output_<caméra>.ppm, ...
+// Les passes et les mattes de la ligne de commande s'ajoutent à celles de la scène.
+// Le recadrage et le redimensionnement s'appliquent à chaque fichier écrit, après la
+// mise côte à côte des yeux d'un rig stéréo.
+// En rendu progressif, chaque fichier est réécrit au fil des passes ; Ctrl-C arrête le
+// rendu en gardant la dernière image.
+fn render(options: &Options) -> Result<(), Error> {
+    let scene = Scene::from_file(options.scene)?;
+    warn(&scene);
+    let mut builder = Renderer::builder()
+        .scene(&scene)
+        .aovs(&options.aovs)
+        .mattes(&options.mattes)
+        .transparent_background(options.transparent);
+    if let Some(samples_per_pixel) = options.samples_per_pixel {
+        builder = builder.samples_per_pixel(samples_per_pixel);
+    }
+    if let Some(adaptive) = options.adaptive {
+        builder = builder.adaptive(adaptive);
+    }
+    if let Some(sampler) = options.sampler {
+        builder = builder.sampler(sampler);
+    }
+    let renderer = match options.progressive {
+        Some(_) => builder.progress(|done, total| eprint!("\rPass {}/{} ", done, total)),
+        None => builder.progress(|done, total| {
+            eprint!("\rScanlines remaining: {} ", total - done);
+            if done == total {
+                eprint!("\nDone.\n");
+            }
+        }),
+    }
+    .build()?;
+    if options.progressive.is_some() {
+        ctrlc::set_handler(|| STOP.store(true, Ordering::Relaxed))
+            .map_err(|e| Error::renderer(format!("cannot catch Ctrl-C: {}", e)))?;
+    }
+
+    // La validation garantit au moins une caméra
+    for (name, view) in scene.views() {
+        let name = name.as_deref();
+        if let Some(name) = name {
+            eprintln!("Camera \"{}\"", name);
+        }
+
+        for (eye, cameras) in images(&view) {
+            let path = output_path(options.output, name, eye);
+            // Les yeux d'un rig stéréo côte à côte dans un seul fichier
+            let write = |frames: &[Frame]| {
+                let combined = frames[1..].iter().fold(frames[0].clone(), |left, right| {
+                    Frame::side_by_side(&left, right)
+                });
+                output::write_frame(
+                    &combined,
+                    &path,
+                    options.transparent,
+                    options.separate_aovs,
+                    &options.framing,
+                )
+            };
+
+            let Some(progressive) = &options.progressive else {
+                let frames: Vec<_> = cameras.iter().map(|c| renderer.render_frame(c)).collect();
+                write(&frames)?;
+                continue;
+            };
+            renderer.render_progressive(&cameras, progressive, &STOP, write)?;
+            if STOP.load(Ordering::Relaxed) {
+                eprint!("\nStopped, {} saved.\n", path.display());
+                return Ok(());
+            }
+            eprint!("\nDone.\n");
+        }
+    }
+    Ok(())
+}
+
+// Images d'un point de vue et leur œil : une seule, une par œil d'un rig stéréo,
+// ou les deux yeux côte à côte
+fn images(view: &Camera) -> Vec<(Option<&'static str>, Vec<Camera>)> {
+    let Some(stereo) = view.stereo else {
+        return vec![(None, vec![*view])];
+    };
+    let [left, right] = view
+        .eye_positions(VUP, stereo.interocular_distance)
+        .map(|(origin, look_at)| Camera::new(origin, look_at, VUP, view.fov, 1.0));
+    match stereo.layout {
+        StereoLayout::Separate => vec![(Some("left"), vec![left]), (Some("right"), vec![right])],
+        StereoLayout::SideBySide => vec![(None, vec![left, right])],
+    }
+}
+
+// output.ppm, output_<caméra>.ppm, output_<caméra>_<œil>.ppm
+fn output_path(output: &str, camera: Option<&str>, eye: Option<&str>) -> PathBuf {
+    let output = Path::new(output);
+    let mut name = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for part in [camera, eye].into_iter().flatten() {
+        name.push('_');
+        name.push_str(part);
+    }
+    if let Some(extension) = output.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    output.with_file_name(name)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
 
-    out.edit_image();
+    let result = match args.as_slice() {
+        ["convert", input, output] => Some(convert(input, output)),
+        ["convert", ..] => None,
+        _ => Options::parse(&args).map(|options| {
+            println!("おはよう世界！");
+            render(&options)
+        }),
+    };
+    let Some(result) = result else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
 
-    out.generate_ppm_file();
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
 }
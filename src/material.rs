@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    hittable::HitRecord,
+    texture::{Texture, TextureConfig},
+    vec3::{self, Vec3},
+};
+
+// Pas (en u, v) des différences finies du bump mapping
+const BUMP_DELTA: f64 = 1e-3;
+
+// Aspect d'une surface : couleur et perturbation de la normale d'ombrage.
+// Les champs sont « aplatis » dans chaque objet de la scène.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Material {
+    pub color: TextureConfig,
+    // Normal map en espace tangent (R → dpdu, G → dpdv, B → normale)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal_map: Option<TextureConfig>,
+    // Carte de hauteur en niveaux de gris
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bump_map: Option<TextureConfig>,
+    #[serde(default = "default_bump_scale")]
+    pub bump_scale: f64, // Hauteur (en unités de la scène) d'une valeur 1.0 de la bump map
+    // Matériau nommé dont viennent ces champs, pour les mattes de matériaux. Rempli à
+    // partir de `material` au chargement de la scène.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material_name: Option<String>,
+}
+
+fn default_bump_scale() -> f64 {
+    1.0
+}
+
+impl From<TextureConfig> for Material {
+    fn from(color: TextureConfig) -> Self {
+        Material {
+            color,
+            normal_map: None,
+            bump_map: None,
+            bump_scale: default_bump_scale(),
+            material_name: None,
+        }
+    }
+}
+
+impl Material {
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        self.color.load(base_dir)?;
+        if let Some(normal_map) = &mut self.normal_map {
+            normal_map.load(base_dir)?;
+        }
+        if let Some(bump_map) = &mut self.bump_map {
+            bump_map.load(base_dir)?;
+        }
+        Ok(())
+    }
+
+    // Complète le HitRecord une fois la géométrie calculée (p, u, v, normale, dpdu, dpdv)
+    pub fn shade(&self, rec: &mut HitRecord) {
+        rec.color = self.color.value(rec.u, rec.v, &rec.p);
+        rec.isotropic = false;
+
+        if let Some(bump_map) = &self.bump_map {
+            self.apply_bump_map(bump_map, rec);
+        }
+        if let Some(normal_map) = &self.normal_map {
+            apply_normal_map(normal_map, rec);
+        }
+    }
+
+    // Déplace virtuellement la surface le long de la normale extérieure et
+    // recalcule la normale à partir des dérivées partielles déplacées
+    fn apply_bump_map(&self, bump_map: &TextureConfig, rec: &mut HitRecord) {
+        // Le point est décalé avec (u, v) pour que les textures 3D varient aussi
+        let height = |du: f64, dv: f64| {
+            let p = rec.p + du * rec.dpdu + dv * rec.dpdv;
+            luminance(bump_map.value(rec.u + du, rec.v + dv, &p)) * self.bump_scale
+        };
+
+        let h = height(0.0, 0.0);
+        let dhdu = (height(BUMP_DELTA, 0.0) - h) / BUMP_DELTA;
+        let dhdv = (height(0.0, BUMP_DELTA) - h) / BUMP_DELTA;
+
+        let outward = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        let dpdu = rec.dpdu + dhdu * outward;
+        let dpdv = rec.dpdv + dhdv * outward;
+        let bumped = vec3::cross(dpdu, dpdv);
+        if bumped.length_squared() == 0.0 {
+            return;
+        }
+
+        let bumped = vec3::unit_vector(bumped);
+        rec.normal = if vec3::dot(bumped, rec.normal) < 0.0 {
+            -bumped
+        } else {
+            bumped
+        };
+    }
+}
+
+fn apply_normal_map(normal_map: &TextureConfig, rec: &mut HitRecord) {
+    let Some((tangent, bitangent)) = tangent_frame(rec) else {
+        return;
+    };
+
+    // Couleur [0, 1] → composantes [-1, 1] en espace tangent
+    let c = normal_map.value(rec.u, rec.v, &rec.p);
+    let local = 2.0 * c - Vec3::new(1.0, 1.0, 1.0);
+    let perturbed = local.x() * tangent + local.y() * bitangent + local.z() * rec.normal;
+    if perturbed.length_squared() > 0.0 {
+        rec.normal = vec3::unit_vector(perturbed);
+    }
+}
+
+// Repère orthonormé (T, B) autour de la normale d'ombrage, T aligné sur dpdu
+// et B orienté comme dpdv. None si dpdu est dégénéré (pôles, dpdu parallèle à n).
+fn tangent_frame(rec: &HitRecord) -> Option<(Vec3, Vec3)> {
+    let n = rec.normal;
+    let t = rec.dpdu - vec3::dot(rec.dpdu, n) * n;
+    if t.length_squared() < 1e-16 {
+        return None;
+    }
+
+    let t = vec3::unit_vector(t);
+    let mut b = vec3::cross(n, t);
+    if vec3::dot(b, rec.dpdv) < 0.0 {
+        b = -b;
+    }
+    Some((t, b))
+}
+
+pub fn luminance(c: Vec3) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
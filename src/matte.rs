@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::framebuffer::{footprint, Framebuffer};
+
+// Sorte de matte : un identifiant par nom d'objet, ou par nom de matériau
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MatteKind {
+    Object,
+    Material,
+}
+
+impl MatteKind {
+    pub const ALL: [MatteKind; 2] = [MatteKind::Object, MatteKind::Material];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MatteKind::Object => "object",
+            MatteKind::Material => "material",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MatteKind> {
+        MatteKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    // Nom du calque Cryptomatte, préfixe de ses canaux
+    pub fn layer(self) -> &'static str {
+        match self {
+            MatteKind::Object => "CryptoObject",
+            MatteKind::Material => "CryptoMaterial",
+        }
+    }
+}
+
+// Couverture antialiasée de chaque identifiant dans chaque pixel : la part des
+// échantillons du pixel qui ont touché un objet portant ce nom. Les échantillons sont
+// comptés en flottants pour qu'un matte redimensionné garde des couvertures moyennes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matte {
+    kind: MatteKind,
+    names: Vec<String>,
+    width: usize,
+    height: usize,
+    samples: Vec<f32>,
+    hits: Vec<Vec<(usize, f32)>>, // (identifiant, poids de ses échantillons) de chaque pixel
+}
+
+impl Matte {
+    // Matte vide ; les identifiants sont les indices dans `names`
+    pub fn new(kind: MatteKind, names: Vec<String>, width: usize, height: usize) -> Self {
+        Matte {
+            kind,
+            names,
+            width,
+            height,
+            samples: vec![0.0; width * height],
+            hits: vec![Vec::new(); width * height],
+        }
+    }
+
+    pub fn kind(&self) -> MatteKind {
+        self.kind
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) outside of a {}x{} matte",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        y * self.width + x
+    }
+
+    // Ajoute un échantillon qui a touché l'identifiant `id`, ou rien de nommé
+    pub fn add_sample(&mut self, x: usize, y: usize, id: Option<usize>) {
+        let i = self.index(x, y);
+        self.samples[i] += 1.0;
+        if let Some(id) = id {
+            add_hit(&mut self.hits[i], id, 1.0);
+        }
+    }
+
+    // Ajoute les échantillons d'une autre passe de même taille et mêmes identifiants
+    pub fn accumulate(&mut self, other: &Matte) {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "mattes of different sizes"
+        );
+        assert!(
+            self.kind == other.kind && self.names == other.names,
+            "mattes of different objects"
+        );
+        for (samples, n) in self.samples.iter_mut().zip(&other.samples) {
+            *samples += n;
+        }
+        for (hits, other) in self.hits.iter_mut().zip(&other.hits) {
+            for &(id, weight) in other {
+                add_hit(hits, id, weight);
+            }
+        }
+    }
+
+    // Identifiants présents dans le pixel et leur couverture, la plus grande d'abord
+    pub fn coverage(&self, x: usize, y: usize) -> Vec<(usize, f32)> {
+        let i = self.index(x, y);
+        let mut coverage: Vec<_> = self.hits[i]
+            .iter()
+            .map(|&(id, weight)| (id, weight / self.samples[i]))
+            .collect();
+        coverage.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        coverage
+    }
+
+    // Plus grand nombre d'identifiants dans un même pixel
+    pub fn depth(&self) -> usize {
+        self.hits.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    // Masque d'un identifiant : sa couverture en niveaux de gris, opaque
+    pub fn mask(&self, id: usize) -> Framebuffer {
+        let mut mask = Framebuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index(x, y);
+                let weight = self.hits[i]
+                    .iter()
+                    .find(|(hit, _)| *hit == id)
+                    .map_or(0.0, |&(_, weight)| weight);
+                let c = match self.samples[i] {
+                    n if n > 0.0 => weight / n,
+                    _ => 0.0,
+                };
+                mask.set(x, y, [c, c, c, 1.0]);
+            }
+        }
+        mask
+    }
+
+    // Rectangle du matte commençant en (x, y), limité aux bords du matte
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Matte {
+        let width = width.min(self.width.saturating_sub(x));
+        let height = height.min(self.height.saturating_sub(y));
+        let mut cropped = Matte::new(self.kind, self.names.clone(), width, height);
+        if width == 0 {
+            return cropped;
+        }
+        for row in 0..height {
+            let from = self.index(x, y + row);
+            let to = row * width;
+            cropped.samples[to..to + width].copy_from_slice(&self.samples[from..from + width]);
+            cropped.hits[to..to + width].clone_from_slice(&self.hits[from..from + width]);
+        }
+        cropped
+    }
+
+    // Rééchantillonne le matte comme `Framebuffer::resize` : la couverture de chaque
+    // identifiant est la moyenne de ses couvertures dans les pixels recouverts, pondérées
+    // par la surface recouverte. Le résultat a un échantillon par pixel.
+    pub fn resize(&self, width: usize, height: usize) -> Matte {
+        let mut resized = Matte::new(self.kind, self.names.clone(), width, height);
+        if self.width == 0 || self.height == 0 {
+            return resized;
+        }
+        let columns: Vec<_> = (0..width)
+            .map(|x| footprint(x, width, self.width))
+            .collect();
+        for y in 0..height {
+            let rows = footprint(y, height, self.height);
+            for (x, columns) in columns.iter().enumerate() {
+                let mut hits = Vec::new();
+                let mut weight = 0.0;
+                for &(sy, wy) in &rows {
+                    for &(sx, wx) in columns {
+                        let w = (wx * wy) as f32;
+                        for (id, coverage) in self.coverage(sx, sy) {
+                            add_hit(&mut hits, id, w * coverage);
+                        }
+                        weight += w;
+                    }
+                }
+                for (_, coverage) in &mut hits {
+                    *coverage /= weight;
+                }
+                let i = resized.index(x, y);
+                resized.samples[i] = 1.0;
+                resized.hits[i] = hits;
+            }
+        }
+        resized
+    }
+
+    // Deux mattes de même hauteur et mêmes identifiants côte à côte
+    pub fn side_by_side(left: &Matte, right: &Matte) -> Matte {
+        assert_eq!(left.height, right.height, "mattes of different heights");
+        assert!(
+            left.kind == right.kind && left.names == right.names,
+            "mattes of different objects"
+        );
+        let mut combined = Matte::new(
+            left.kind,
+            left.names.clone(),
+            left.width + right.width,
+            left.height,
+        );
+        for y in 0..left.height {
+            for (matte, offset) in [(left, 0), (right, left.width)] {
+                let from = y * matte.width;
+                let to = y * combined.width + offset;
+                combined.samples[to..to + matte.width]
+                    .clone_from_slice(&matte.samples[from..from + matte.width]);
+                combined.hits[to..to + matte.width]
+                    .clone_from_slice(&matte.hits[from..from + matte.width]);
+            }
+        }
+        combined
+    }
+
+    // Manifeste Cryptomatte : nom -> hachage en hexadécimal
+    pub fn manifest(&self) -> String {
+        let manifest: Map<String, Value> = self
+            .names
+            .iter()
+            .map(|name| (name.clone(), format!("{:08x}", hash(name)).into()))
+            .collect();
+        Value::Object(manifest).to_string()
+    }
+}
+
+// Ajoute le poids `weight` à l'identifiant `id` parmi les touches d'un pixel
+fn add_hit(hits: &mut Vec<(usize, f32)>, id: usize, weight: f32) {
+    match hits.iter_mut().find(|(hit, _)| *hit == id) {
+        Some((_, total)) => *total += weight,
+        None => hits.push((id, weight)),
+    }
+}
+
+// Noms distincts d'une liste de libellés (un par objet du monde), dans l'ordre
+// d'apparition, et identifiant de chaque objet. Les objets de même nom partagent
+// un identifiant ; ceux sans libellé n'en ont pas.
+pub fn ids(labels: &[Option<String>]) -> (Vec<String>, Vec<Option<usize>>) {
+    let mut names: Vec<String> = Vec::new();
+    let ids = labels
+        .iter()
+        .map(|label| {
+            let label = label.as_ref()?;
+            Some(match names.iter().position(|name| name == label) {
+                Some(id) => id,
+                None => {
+                    names.push(label.clone());
+                    names.len() - 1
+                }
+            })
+        })
+        .collect();
+    (names, ids)
+}
+
+// Identifiant Cryptomatte d'un nom : son MurmurHash3 32 bits, dont les motifs qui
+// donneraient un flottant dénormalisé, infini ou NaN sont décalés
+pub fn hash(name: &str) -> u32 {
+    let hash = murmur3_32(name.as_bytes(), 0);
+    let exponent = (hash >> 23) & 0xff;
+    if exponent == 0 || exponent == 0xff {
+        hash ^ (1 << 23)
+    } else {
+        hash
+    }
+}
+
+// Clé des métadonnées d'un calque : les 7 premiers chiffres hexadécimaux du hachage de son nom
+pub fn layer_key(layer: &str) -> String {
+    format!("{:08x}", murmur3_32(layer.as_bytes(), 0))[..7].to_string()
+}
+
+// MurmurHash3 x86 32 bits
+pub fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        h = (h ^ mix(k))
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe654_6b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .rev()
+            .fold(0u32, |k, &byte| (k << 8) | byte as u32);
+        h ^= mix(k);
+    }
+
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3_matches_the_reference() {
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"", 1), 0x514e_28b7);
+        assert_eq!(murmur3_32(b"test", 0), 0xba6b_d213);
+        assert_eq!(murmur3_32(b"Hello, world!", 0x9747_b28c), 0x2488_4cba);
+        assert_eq!(
+            murmur3_32(b"The quick brown fox jumps over the lazy dog", 0x9747_b28c),
+            0x2fa8_26cd
+        );
+    }
+
+    #[test]
+    fn hashes_are_normal_floats() {
+        for name in ["ball", "floor", "spheres[0]", "cubes[12]", ""] {
+            assert!(f32::from_bits(hash(name)).is_normal());
+        }
+        // Exposant nul : le bit 23 est inversé
+        let raw = murmur3_32(b"", 0);
+        assert_eq!(hash(""), raw ^ (1 << 23));
+    }
+
+    #[test]
+    fn objects_with_the_same_name_share_an_id() {
+        let labels = [
+            Some("tree".to_string()),
+            None,
+            Some("house".to_string()),
+            Some("tree".to_string()),
+        ];
+        let (names, ids) = ids(&labels);
+        assert_eq!(names, ["tree", "house"]);
+        assert_eq!(ids, [Some(0), None, Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn coverage_counts_the_samples_of_each_id() {
+        let mut matte = Matte::new(MatteKind::Object, vec!["a".into(), "b".into()], 2, 1);
+        for id in [Some(1), Some(0), Some(1), None] {
+            matte.add_sample(0, 0, id);
+        }
+        assert_eq!(matte.coverage(0, 0), [(1, 0.5), (0, 0.25)]);
+        assert_eq!(matte.coverage(1, 0), []);
+        assert_eq!(matte.depth(), 2);
+        assert_eq!(matte.mask(1).pixel(0, 0), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(matte.mask(0).pixel(1, 0), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn passes_accumulate() {
+        let names = vec!["a".to_string(), "b".to_string()];
+        let mut first = Matte::new(MatteKind::Object, names.clone(), 1, 1);
+        let mut second = Matte::new(MatteKind::Object, names, 1, 1);
+        first.add_sample(0, 0, Some(0));
+        second.add_sample(0, 0, Some(1));
+        second.add_sample(0, 0, Some(0));
+        second.add_sample(0, 0, None);
+
+        first.accumulate(&second);
+        assert_eq!(first.coverage(0, 0), [(0, 0.5), (1, 0.25)]);
+    }
+
+    #[test]
+    fn crop_and_resize_keep_the_coverage() {
+        // Ligne de 4 pixels : a, a, b, rien
+        let mut matte = Matte::new(MatteKind::Object, vec!["a".into(), "b".into()], 4, 1);
+        for (x, id) in [Some(0), Some(0), Some(1), None].into_iter().enumerate() {
+            matte.add_sample(x, 0, id);
+        }
+
+        let cropped = matte.crop(1, 0, 10, 10);
+        assert_eq!((cropped.width(), cropped.height()), (3, 1));
+        assert_eq!(cropped.coverage(1, 0), [(1, 1.0)]);
+
+        let half = matte.resize(2, 1);
+        assert_eq!(half.coverage(0, 0), [(0, 1.0)]);
+        assert_eq!(half.coverage(1, 0), [(1, 0.5)]);
+        assert_eq!(half.mask(1).pixel(1, 0), [0.5, 0.5, 0.5, 1.0]);
+    }
+}
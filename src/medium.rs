@@ -0,0 +1,408 @@
+use std::{fmt, marker::PhantomData, path::Path};
+
+use serde::{
+    de::{value::MapAccessDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{
+    color::{self, Color},
+    common,
+    cube::Cube,
+    cylinder::Cylinder,
+    error::Error,
+    hittable::{HitRecord, Hittable},
+    mesh::Mesh,
+    metaballs::Metaballs,
+    ray::Ray,
+    sdf::Sdf,
+    sphere::Sphere,
+    vec3::Vec3,
+};
+
+// Écart entre une sortie de la frontière et la recherche de l'entrée suivante
+const BOUNDARY_EPSILON: f64 = 0.0001;
+
+// Milieu homogène (fumée, brume) délimité par un objet fermé, normales vers l'extérieur.
+// Les rayons qui le traversent sont diffusés à une distance tirée selon la densité.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: f64,
+    albedo: Color,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Hittable>, density: f64, albedo: Color) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            albedo,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let ray_length = r.direction().length();
+        if ray_length == 0.0 {
+            return false;
+        }
+
+        // Distance de vol libre : loi exponentielle de paramètre la densité, consommée
+        // par chaque portion du rayon à l'intérieur de la frontière (plusieurs si elle
+        // n'est pas convexe). L'orientation de la normale dit si l'on entre ou sort.
+        let mut remaining = self.neg_inv_density * f64::ln(common::random_double());
+        let mut t = t_min.max(0.0);
+        let mut boundary = HitRecord::new();
+        let (t_enter, distance) = loop {
+            if !self.boundary.hit(r, t, common::INFINITY, &mut boundary) {
+                return false;
+            }
+            let (t_enter, t_exit) = if boundary.front_face {
+                let t_enter = boundary.t;
+                if !self.boundary.hit(
+                    r,
+                    t_enter + BOUNDARY_EPSILON,
+                    common::INFINITY,
+                    &mut boundary,
+                ) {
+                    return false;
+                }
+                (t_enter, boundary.t)
+            } else {
+                // Départ à l'intérieur
+                (t, boundary.t)
+            };
+            if t_enter >= t_max {
+                return false;
+            }
+
+            let inside = (t_exit.min(t_max) - t_enter) * ray_length;
+            if remaining <= inside {
+                break (t_enter, remaining);
+            }
+            remaining -= inside;
+            if t_exit >= t_max {
+                return false;
+            }
+            t = t_exit + BOUNDARY_EPSILON;
+        };
+
+        rec.t = t_enter + distance / ray_length;
+        rec.p = r.at(rec.t);
+        rec.normal = Vec3::new(1.0, 0.0, 0.0); // Arbitraire
+        rec.front_face = true;
+        rec.u = 0.0;
+        rec.v = 0.0;
+        rec.dpdu = Vec3::default();
+        rec.dpdv = Vec3::default();
+        true
+    }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        rec.color = self.albedo;
+        rec.isotropic = true;
+    }
+}
+
+// Volume décrit dans la scène
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Volume {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub boundary: VolumeShape,
+    pub density: f64,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub albedo: Color,
+}
+
+// Objet de la scène qui délimite le volume, décrit comme dans sa liste avec un `type`
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VolumeShape {
+    Sphere(Boundary<Sphere>),
+    Cube(Boundary<Cube>),
+    Cylinder(Boundary<Cylinder>),
+    Sdf(Boundary<Sdf>),
+    Metaballs(Boundary<Metaballs>),
+    Mesh(Boundary<Mesh>),
+}
+
+impl Volume {
+    // Construit la BVH d'une frontière maillée
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        match &mut self.boundary {
+            VolumeShape::Mesh(Boundary(mesh)) => mesh.load(base_dir),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn build(self) -> ConstantMedium {
+        let boundary: Box<dyn Hittable> = match self.boundary {
+            VolumeShape::Sphere(Boundary(sphere)) => Box::new(sphere),
+            VolumeShape::Cube(Boundary(cube)) => Box::new(cube),
+            VolumeShape::Cylinder(Boundary(cylinder)) => Box::new(cylinder),
+            VolumeShape::Sdf(Boundary(sdf)) => Box::new(sdf),
+            VolumeShape::Metaballs(Boundary(metaballs)) => Box::new(metaballs),
+            VolumeShape::Mesh(Boundary(mesh)) => Box::new(mesh),
+        };
+        ConstantMedium::new(boundary, self.density, self.albedo)
+    }
+}
+
+// Objet utilisé comme frontière : son aspect ne sert pas, `color` peut donc être omis
+#[derive(Serialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Boundary<T>(pub T);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Boundary<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(BoundaryVisitor(PhantomData))
+    }
+}
+
+struct BoundaryVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for BoundaryVisitor<T> {
+    type Value = Boundary<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a scene object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Boundary<T>, A::Error> {
+        let map = WithColor {
+            map,
+            seen: false,
+            pending: false,
+            done: false,
+        };
+        T::deserialize(MapAccessDeserializer::new(map)).map(Boundary)
+    }
+}
+
+// Rend les entrées de la table, puis `"color": 1` si elle n'en avait pas
+struct WithColor<A> {
+    map: A,
+    seen: bool,    // La table a sa propre couleur
+    pending: bool, // La clé ajoutée vient d'être rendue, sa valeur pas encore
+    done: bool,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for WithColor<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        if self.done {
+            return Ok(None);
+        }
+        let key = match self.map.next_key::<String>()? {
+            Some(key) => {
+                self.seen |= key == "color";
+                key
+            }
+            None => {
+                self.done = true;
+                if self.seen {
+                    return Ok(None);
+                }
+                self.pending = true;
+                "color".to_string()
+            }
+        };
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        if self.pending {
+            self.pending = false;
+            return seed.deserialize(1.0.into_deserializer());
+        }
+        self.map.next_value_seed(seed)
+    }
+}
+
+// Brouillard global dont la densité décroît exponentiellement avec l'altitude :
+// density * exp(-falloff * (y - base_height))
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct HeightFog {
+    pub density: f64,
+    #[serde(default)]
+    pub falloff: f64, // 0 : brouillard uniforme
+    #[serde(default)]
+    pub base_height: f64,
+    #[serde(deserialize_with = "color::deserialize")]
+    pub color: Color,
+}
+
+impl HeightFog {
+    // Mélange la couleur vue à la distance `t` (infinie si rien n'est touché) avec le brouillard
+    pub fn apply(&self, r: &Ray, t: f64, color: Color) -> Color {
+        let transmittance = f64::exp(-self.optical_depth(r, t));
+        transmittance * color + (1.0 - transmittance) * self.color
+    }
+
+    // Intégrale de la densité le long du rayon entre 0 et t
+    fn optical_depth(&self, r: &Ray, t: f64) -> f64 {
+        let length = r.direction().length();
+        if length == 0.0 || self.density <= 0.0 {
+            return 0.0;
+        }
+
+        let distance = t * length;
+        let dy = r.direction().y() / length;
+        let start = self.density * f64::exp(-self.falloff * (r.origin().y() - self.base_height));
+        let k = self.falloff * dy;
+        if start == 0.0 {
+            return 0.0;
+        }
+
+        if k.abs() < 1e-9 {
+            // Densité constante le long du rayon
+            return start * distance;
+        }
+        if distance.is_infinite() {
+            // Rayon vers le haut : l'intégrale converge, sinon brouillard opaque
+            return if k > 0.0 { start / k } else { common::INFINITY };
+        }
+        start * (1.0 - f64::exp(-k * distance)) / k
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable_list::HittableList, material::Material, vec3::Point3};
+    use serde_json::{json, Value};
+
+    fn volume(boundary: Value, density: f64) -> ConstantMedium {
+        let volume = json!({ "boundary": boundary, "density": density, "albedo": 0.5 });
+        serde_json::from_value::<Volume>(volume).unwrap().build()
+    }
+
+    // Part des rayons qui traversent le volume sans être diffusés
+    fn transmittance(medium: &ConstantMedium, ray: &Ray) -> f64 {
+        let n = 20000;
+        let passed = (0..n)
+            .filter(|_| !medium.hit(ray, 0.001, common::INFINITY, &mut HitRecord::new()))
+            .count();
+        passed as f64 / n as f64
+    }
+
+    fn assert_close(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() < tolerance,
+            "{} != {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn transmittance_is_exp_minus_density_times_distance() {
+        // Direction non unitaire : les distances sont mesurées le long du rayon
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 2.0));
+
+        // Sphère traversée sur 2 unités
+        let sphere = json!({ "type": "sphere", "center": [0.0, 0.0, 0.0], "radius": 1.0 });
+        let medium = volume(sphere, 0.5);
+        assert_close(transmittance(&medium, &ray), f64::exp(-1.0), 0.015);
+
+        // Départ à l'intérieur : 1 unité
+        let inside = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_close(transmittance(&medium, &inside), f64::exp(-0.5), 0.015);
+
+        // Frontière non convexe : deux boules d'une SDF, 2 + 2 unités et rien entre elles
+        let sdf = json!({ "type": "sdf", "root": { "type": "union", "children": [
+            { "type": "sphere", "center": [0.0, 0.0, -2.0], "radius": 1.0 },
+            { "type": "sphere", "center": [0.0, 0.0, 2.0], "radius": 1.0 }
+        ] } });
+        let medium = volume(sdf, 0.25);
+        assert_close(transmittance(&medium, &ray), f64::exp(-1.0), 0.015);
+        for _ in 0..1000 {
+            let mut rec = HitRecord::new();
+            if medium.hit(&ray, 0.001, common::INFINITY, &mut rec) {
+                let z = rec.p.z();
+                assert!((-3.0..=-1.0).contains(&z) || (1.0..=3.0).contains(&z), "{}", z);
+            }
+        }
+    }
+
+    #[test]
+    fn height_fog_matches_its_integral() {
+        let fog = HeightFog {
+            density: 0.3,
+            falloff: 0.7,
+            base_height: 1.0,
+            color: Color::new(1.0, 1.0, 1.0),
+        };
+        let density = |p: Point3| fog.density * f64::exp(-fog.falloff * (p.y() - fog.base_height));
+
+        for direction in [
+            Vec3::new(1.0, 0.5, 0.0),
+            Vec3::new(0.2, -0.3, 1.0),
+            Vec3::new(0.0, 0.0, 3.0),
+        ] {
+            let ray = Ray::new(Point3::new(0.0, 2.0, 0.0), direction);
+            let t = 5.0;
+            // Méthode de Simpson sur [0, t], ds = |direction| dt
+            let n = 1000;
+            let h = t / n as f64;
+            let mut sum = density(ray.at(0.0)) + density(ray.at(t));
+            for k in 1..n {
+                let weight = if k % 2 == 1 { 4.0 } else { 2.0 };
+                sum += weight * density(ray.at(k as f64 * h));
+            }
+            let numeric = sum * h / 3.0 * direction.length();
+            assert_close(fog.optical_depth(&ray, t), numeric, 1e-9);
+        }
+
+        // Vers le haut jusqu'à l'infini : densité au départ / falloff ; vers le bas : opaque
+        let up = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let start = density(up.origin());
+        assert_close(fog.optical_depth(&up, common::INFINITY), start / fog.falloff, 1e-12);
+        let down = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(fog.optical_depth(&down, common::INFINITY), common::INFINITY);
+    }
+
+    #[test]
+    fn surface_hits_are_not_isotropic() {
+        // Volume très dense en z ∈ [-1, 1] et boule opaque en z = -3, dans les deux ordres
+        for medium_first in [true, false] {
+            let sphere = json!({ "type": "sphere", "center": [0.0, 0.0, 0.0], "radius": 1.0 });
+            let medium = Box::new(volume(sphere, 1e6));
+            let ball = Box::new(Sphere::new(
+                Point3::new(0.0, 0.0, -3.0),
+                0.5,
+                Material::default(),
+            ));
+            let mut world = HittableList::new();
+            if medium_first {
+                world.add(medium);
+                world.add(ball);
+            } else {
+                world.add(ball);
+                world.add(medium);
+            }
+
+            let trace = |origin: Point3, direction: Vec3| {
+                let mut rec = HitRecord::new();
+                let ray = Ray::new(origin, direction);
+                assert!(world.hit(&ray, 0.001, common::INFINITY, &mut rec));
+                world.shade(&mut rec);
+                rec
+            };
+            // La boule est devant le volume
+            let rec = trace(Point3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+            assert_close(rec.t, 6.5, 1e-9);
+            assert!(!rec.isotropic);
+            // Le volume est devant la boule
+            let rec = trace(Point3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+            assert_close(rec.t, 9.0, 1e-3);
+            assert!(rec.isotropic);
+        }
+    }
+}
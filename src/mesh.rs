@@ -0,0 +1,454 @@
+use std::{path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::Cache,
+    error::Error,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+// Nombre maximal de triangles dans une feuille de la hiérarchie de boîtes
+const LEAF_SIZE: usize = 4;
+
+// Maillage de triangles indexés. Les normales et les coordonnées de texture par sommet
+// sont optionnelles : sans elles, la normale est celle de la face et (u, v) les
+// coordonnées barycentriques du triangle.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Mesh {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub positions: Vec<Point3>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normals: Vec<Vec3>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uvs: Vec<[f64; 2]>,
+    pub triangles: Vec<[usize; 3]>, // Indices dans `positions`
+    #[serde(flatten)]
+    pub material: Material,
+    // Hiérarchie de boîtes englobantes, construite au chargement de la scène
+    #[serde(skip)]
+    pub bvh: Cache<Option<Arc<Bvh>>>,
+}
+
+// Hiérarchie de boîtes englobantes (BVH) sur les triangles du maillage.
+// Les nœuds sont rangés en profondeur : l'enfant gauche suit son parent.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<usize>, // Triangles réordonnés, chaque feuille en couvre une plage
+}
+
+struct BvhNode {
+    min: Point3,
+    max: Point3,
+    start: usize,
+    count: usize, // 0 pour un nœud interne
+    right: usize, // Enfant droit d'un nœud interne
+}
+
+// Triangle en cours de répartition : indice, centre et boîte englobante
+struct Item {
+    triangle: usize,
+    centroid: Point3,
+    min: Point3,
+    max: Point3,
+}
+
+impl Mesh {
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        self.material.load(base_dir)?;
+        self.build();
+        Ok(())
+    }
+
+    // Construit la BVH ; les triangles dont un indice sort de `positions` sont ignorés
+    pub fn build(&mut self) {
+        let mut items: Vec<Item> = self
+            .triangles
+            .iter()
+            .enumerate()
+            .filter(|(_, triangle)| triangle.iter().all(|&k| k < self.positions.len()))
+            .map(|(i, triangle)| {
+                let [a, b, c] = triangle.map(|k| self.positions[k]);
+                Item {
+                    triangle: i,
+                    centroid: (a + b + c) / 3.0,
+                    min: component_min(component_min(a, b), c),
+                    max: component_max(component_max(a, b), c),
+                }
+            })
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            triangles: Vec::with_capacity(items.len()),
+        };
+        if !items.is_empty() {
+            bvh.split(&mut items);
+        }
+        *self.bvh = Some(Arc::new(bvh));
+    }
+
+    fn vertex(&self, triangle: usize) -> [Point3; 3] {
+        self.triangles[triangle].map(|k| self.positions[k])
+    }
+
+    fn fill_record(
+        &self,
+        r: &Ray,
+        triangle: usize,
+        t: f64,
+        beta: f64,
+        gamma: f64,
+        rec: &mut HitRecord,
+    ) {
+        let indices = self.triangles[triangle];
+        let [a, b, c] = self.vertex(triangle);
+        let alpha = 1.0 - beta - gamma;
+
+        rec.t = t;
+        rec.p = r.at(t);
+
+        // La face touchée vient de la normale géométrique ; la normale lissée est
+        // retournée du même côté pour éviter les ombrages inversés aux silhouettes
+        let geometric = vec3::unit_vector(vec3::cross(b - a, c - a));
+        rec.front_face = vec3::dot(r.direction(), geometric) < 0.0;
+        let facing = if rec.front_face {
+            geometric
+        } else {
+            -geometric
+        };
+        rec.normal = facing;
+        if self.normals.len() == self.positions.len() {
+            let [na, nb, nc] = indices.map(|k| self.normals[k]);
+            let n = alpha * na + beta * nb + gamma * nc;
+            if n.length_squared() > 0.0 {
+                let n = vec3::unit_vector(n);
+                rec.normal = if vec3::dot(n, facing) < 0.0 { -n } else { n };
+            }
+        }
+
+        // Sans coordonnées de texture, (u, v) = (beta, gamma)
+        let [ta, tb, tc] = if self.uvs.len() == self.positions.len() {
+            indices.map(|k| self.uvs[k])
+        } else {
+            [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]
+        };
+        rec.u = alpha * ta[0] + beta * tb[0] + gamma * tc[0];
+        rec.v = alpha * ta[1] + beta * tb[1] + gamma * tc[1];
+
+        // dp/du et dp/dv à partir des arêtes et des écarts de (u, v)
+        let (e1, e2) = (b - a, c - a);
+        let (du1, dv1) = (tb[0] - ta[0], tb[1] - ta[1]);
+        let (du2, dv2) = (tc[0] - ta[0], tc[1] - ta[1]);
+        let det = du1 * dv2 - dv1 * du2;
+        if det.abs() > 1e-12 {
+            rec.dpdu = (dv2 * e1 - dv1 * e2) / det;
+            rec.dpdv = (du1 * e2 - du2 * e1) / det;
+        } else {
+            rec.dpdu = e1;
+            rec.dpdv = e2;
+        }
+    }
+}
+
+impl Bvh {
+    fn split(&mut self, items: &mut [Item]) {
+        let (min, max) = items
+            .iter()
+            .fold((items[0].min, items[0].max), |(min, max), item| {
+                (component_min(min, item.min), component_max(max, item.max))
+            });
+        let node = self.nodes.len();
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            start: self.triangles.len(),
+            count: 0,
+            right: 0,
+        });
+
+        // Découpe à la médiane des centres, selon l'axe où ils sont le plus étalés
+        let (low, high) =
+            items
+                .iter()
+                .fold((items[0].centroid, items[0].centroid), |(l, h), item| {
+                    (
+                        component_min(l, item.centroid),
+                        component_max(h, item.centroid),
+                    )
+                });
+        let extent = high - low;
+        let axis = (0..3)
+            .max_by(|&i, &j| extent[i].total_cmp(&extent[j]))
+            .unwrap_or(0);
+
+        if items.len() <= LEAF_SIZE || extent[axis] <= 0.0 {
+            self.triangles
+                .extend(items.iter().map(|item| item.triangle));
+            self.nodes[node].count = items.len();
+            return;
+        }
+
+        items.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        let (left, right) = items.split_at_mut(items.len() / 2);
+        self.split(left);
+        self.nodes[node].right = self.nodes.len();
+        self.split(right);
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let Some(bvh) = &*self.bvh else {
+            return false;
+        };
+        if bvh.nodes.is_empty() {
+            return false;
+        }
+
+        let mut closest = t_max;
+        let mut found = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &bvh.nodes[index];
+            if slab_interval(r, node.min, node.max, t_min, closest).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.right);
+                stack.push(index + 1);
+                continue;
+            }
+            for &triangle in &bvh.triangles[node.start..node.start + node.count] {
+                let [a, b, c] = self.vertex(triangle);
+                if let Some((t, beta, gamma)) = intersect_triangle(r, a, b, c) {
+                    if t > t_min && t < closest {
+                        closest = t;
+                        found = Some((triangle, beta, gamma));
+                    }
+                }
+            }
+        }
+
+        let Some((triangle, beta, gamma)) = found else {
+            return false;
+        };
+        self.fill_record(r, triangle, closest, beta, gamma, rec);
+        true
+    }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+fn component_min(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()))
+}
+
+fn component_max(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()))
+}
+
+// Intervalle du rayon dans une boîte alignée sur les axes, borné par [t_min, t_max]
+pub fn slab_interval(
+    r: &Ray,
+    min: Point3,
+    max: Point3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64)> {
+    let origin = [r.origin().x(), r.origin().y(), r.origin().z()];
+    let dir = [r.direction().x(), r.direction().y(), r.direction().z()];
+    let min = [min.x(), min.y(), min.z()];
+    let max = [max.x(), max.y(), max.z()];
+
+    let (mut t0, mut t1) = (t_min, t_max);
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let inv = 1.0 / dir[axis];
+        let mut near = (min[axis] - origin[axis]) * inv;
+        let mut far = (max[axis] - origin[axis]) * inv;
+        if near > far {
+            std::mem::swap(&mut near, &mut far);
+        }
+        t0 = t0.max(near);
+        t1 = t1.min(far);
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
+
+// Möller–Trumbore, sans élimination des faces arrière : (t, beta, gamma)
+pub fn intersect_triangle(r: &Ray, a: Point3, b: Point3, c: Point3) -> Option<(f64, f64, f64)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let pvec = vec3::cross(r.direction(), edge2);
+    let det = vec3::dot(edge1, pvec);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = r.origin() - a;
+    let beta = vec3::dot(tvec, pvec) * inv_det;
+    if !(0.0..=1.0).contains(&beta) {
+        return None;
+    }
+    let qvec = vec3::cross(tvec, edge1);
+    let gamma = vec3::dot(r.direction(), qvec) * inv_det;
+    if gamma < 0.0 || beta + gamma > 1.0 {
+        return None;
+    }
+    Some((vec3::dot(edge2, qvec) * inv_det, beta, gamma))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const T_MIN: f64 = 0.001;
+    const T_MAX: f64 = 100.0;
+
+    fn mesh(positions: Vec<Point3>, triangles: Vec<[usize; 3]>) -> Mesh {
+        let mut mesh = Mesh {
+            name: None,
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            triangles,
+            material: Material::default(),
+            bvh: Cache::default(),
+        };
+        mesh.build();
+        mesh
+    }
+
+    fn hit(mesh: &Mesh, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        mesh.hit(&Ray::new(origin, direction), T_MIN, T_MAX, &mut rec)
+            .then_some(rec)
+    }
+
+    // Référence : tous les triangles, sans la BVH
+    fn brute_force(mesh: &Mesh, r: &Ray) -> Option<f64> {
+        (0..mesh.triangles.len())
+            .filter_map(|i| {
+                let [a, b, c] = mesh.vertex(i);
+                intersect_triangle(r, a, b, c).map(|(t, _, _)| t)
+            })
+            .filter(|t| *t > T_MIN && *t < T_MAX)
+            .min_by(f64::total_cmp)
+    }
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-9, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn interpolates_normals_and_uvs() {
+        let mut quad = mesh(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(2.0, 2.0, 0.0),
+                Point3::new(0.0, 2.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+        quad.normals = vec![Vec3::new(0.0, 0.0, 1.0); 4];
+        quad.normals[2] = vec3::unit_vector(Vec3::new(1.0, 0.0, 1.0));
+        quad.uvs = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+        let rec = hit(&quad, Point3::new(1.5, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.25).abs() < 1e-12);
+        assert!(rec.normal.x() > 0.0 && rec.normal.z() > 0.0);
+        assert!((rec.normal.length() - 1.0).abs() < 1e-12);
+        assert_vec_eq(rec.dpdu, Vec3::new(2.0, 0.0, 0.0));
+        assert_vec_eq(rec.dpdv, Vec3::new(0.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn back_face_gets_the_opposite_normal() {
+        let triangle = mesh(
+            vec![
+                Point3::new(-1.0, -1.0, 0.0),
+                Point3::new(1.0, -1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2]],
+        );
+        let rec = hit(
+            &triangle,
+            Point3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        )
+        .unwrap();
+        assert!(!rec.front_face);
+        assert_vec_eq(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn triangles_with_invalid_indices_are_skipped() {
+        let triangle = mesh(
+            vec![
+                Point3::new(-1.0, -1.0, 0.0),
+                Point3::new(1.0, -1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 7], [0, 1, 2]],
+        );
+        assert!(hit(
+            &triangle,
+            Point3::new(0.0, 0.0, 2.0),
+            Vec3::new(0.0, 0.0, -1.0)
+        )
+        .is_some());
+    }
+
+    fn coordinate() -> impl Strategy<Value = f64> {
+        -5.0..5.0f64
+    }
+
+    fn point() -> impl Strategy<Value = Point3> {
+        (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| Point3::new(x, y, z))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(256))]
+
+        #[test]
+        fn bvh_finds_the_nearest_triangle(
+            corners in prop::collection::vec((point(), point(), point()), 1..60),
+            origin in point(),
+            target in point(),
+        ) {
+            let direction = target - origin;
+            prop_assume!(direction.length() > 1e-3);
+
+            let positions: Vec<Point3> = corners.iter().flat_map(|(a, b, c)| [*a, *b, *c]).collect();
+            let triangles = (0..corners.len()).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect();
+            let soup = mesh(positions, triangles);
+
+            let ray = Ray::new(origin, direction);
+            let result = hit(&soup, origin, direction).map(|rec| rec.t);
+            prop_assert_eq!(result, brute_force(&soup, &ray));
+        }
+    }
+}
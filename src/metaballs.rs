@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+// Précision (en t) de la recherche de racine
+const ROOT_TOLERANCE: f64 = 1e-9;
+// Pente maximale du noyau de Wyvill multipliée par le rayon : 96 / (25 sqrt(5)), arrondie
+// au-dessus, atteinte à la distance R / sqrt(5) du centre
+const KERNEL_SLOPE: f64 = 1.7174;
+
+// Sphère d'influence d'une métaballe : sa contribution est nulle au-delà du rayon
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Ball {
+    pub center: Point3,
+    pub radius: f64,
+}
+
+impl Ball {
+    // Noyau de Wyvill (1 - r²/R²)³ : vaut 1 au centre et 0 au bord, avec une dérivée nulle
+    fn field(&self, p: Point3) -> f64 {
+        let s = (p - self.center).length_squared() / (self.radius * self.radius);
+        if s >= 1.0 {
+            return 0.0;
+        }
+        let k = 1.0 - s;
+        k * k * k
+    }
+
+    fn gradient(&self, p: Point3) -> Vec3 {
+        let d = p - self.center;
+        let r2 = self.radius * self.radius;
+        let s = d.length_squared() / r2;
+        if s >= 1.0 {
+            return Vec3::default();
+        }
+        let k = 1.0 - s;
+        (-6.0 * k * k / r2) * d
+    }
+
+    // Intervalle [t0, t1] du rayon dans la sphère d'influence
+    fn interval(&self, r: &Ray) -> Option<(f64, f64)> {
+        let oc = r.origin() - self.center;
+        let a = r.direction().length_squared();
+        let half_b = vec3::dot(oc, r.direction());
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let sqrt_d = discriminant.sqrt();
+        Some(((-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a))
+    }
+}
+
+// Surface implicite où la somme des champs des métaballes vaut `threshold`
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Metaballs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub balls: Vec<Ball>,
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    #[serde(flatten)]
+    pub material: Material,
+}
+
+fn default_threshold() -> f64 {
+    0.5
+}
+
+// Portion du rayon couverte par une ou plusieurs sphères d'influence qui se chevauchent
+struct Span {
+    t0: f64,
+    t1: f64,
+    balls: Vec<usize>,
+}
+
+impl Metaballs {
+    // Champ moins le seuil : positif à l'intérieur de la surface
+    fn value(&self, balls: &[usize], p: Point3) -> f64 {
+        balls.iter().map(|&i| self.balls[i].field(p)).sum::<f64>() - self.threshold
+    }
+
+    // Union des sphères d'influence le long du rayon, bornée à [t_min, t_max]
+    fn spans(&self, r: &Ray, t_min: f64, t_max: f64) -> Vec<Span> {
+        let mut intervals: Vec<(f64, f64, usize)> = self
+            .balls
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ball)| {
+                let (t0, t1) = ball.interval(r)?;
+                let (t0, t1) = (t0.max(t_min), t1.min(t_max));
+                (t0 < t1).then_some((t0, t1, i))
+            })
+            .collect();
+        intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut spans: Vec<Span> = Vec::new();
+        for (t0, t1, i) in intervals {
+            match spans.last_mut() {
+                Some(span) if t0 <= span.t1 => {
+                    span.t1 = span.t1.max(t1);
+                    span.balls.push(i);
+                }
+                _ => spans.push(Span {
+                    t0,
+                    t1,
+                    balls: vec![i],
+                }),
+            }
+        }
+        spans
+    }
+
+    // Première traversée du seuil dans un intervalle. La pente du champ le long du rayon
+    // est bornée (constante de Lipschitz) : un morceau n'est découpé que si la borne y
+    // permet une traversée, ce qui trouve aussi les rayons rasants.
+    fn find_root(&self, r: &Ray, span: &Span) -> Option<f64> {
+        let slope: f64 = span
+            .balls
+            .iter()
+            .map(|&i| KERNEL_SLOPE / self.balls[i].radius)
+            .sum::<f64>()
+            * r.direction().length();
+
+        let value = |t: f64| self.value(&span.balls, r.at(t));
+        let start = value(span.t0);
+        // Côté du seuil au départ : dehors en général, dedans pour un rayon qui part de la surface
+        let side = if start > 0.0 { 1.0 } else { -1.0 };
+        let search = Search {
+            value: &value,
+            side,
+            slope,
+        };
+        search.first_crossing((span.t0, start), (span.t1, value(span.t1)))
+    }
+
+    fn gradient(&self, p: Point3) -> Vec3 {
+        self.balls
+            .iter()
+            .fold(Vec3::default(), |acc, ball| acc + ball.gradient(p))
+    }
+}
+
+// Recherche de la première valeur de t où `value` change de côté (`side`) du seuil
+struct Search<'a, F: Fn(f64) -> f64> {
+    value: &'a F,
+    side: f64,
+    slope: f64,
+}
+
+impl<F: Fn(f64) -> f64> Search<'_, F> {
+    // `a` est du côté de départ ; chaque point est (t, valeur)
+    fn first_crossing(&self, (a, va): (f64, f64), (b, vb): (f64, f64)) -> Option<f64> {
+        let crossed = self.side * vb <= 0.0;
+        if b - a < ROOT_TOLERANCE {
+            return crossed.then_some(b);
+        }
+        // Plus petite distance au seuil possible entre a et b
+        let lowest = 0.5 * self.side * (va + vb) - 0.5 * self.slope * (b - a);
+        if !crossed && lowest > 0.0 {
+            return None;
+        }
+
+        let m = 0.5 * (a + b);
+        let vm = (self.value)(m);
+        if self.side * vm <= 0.0 {
+            return self.first_crossing((a, va), (m, vm));
+        }
+        self.first_crossing((a, va), (m, vm))
+            .or_else(|| self.first_crossing((m, vm), (b, vb)))
+    }
+}
+
+impl Hittable for Metaballs {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let Some(t) = self
+            .spans(r, t_min, t_max)
+            .iter()
+            .find_map(|span| self.find_root(r, span))
+        else {
+            return false;
+        };
+
+        rec.t = t;
+        rec.p = r.at(t);
+        // Le champ décroît vers l'extérieur : la normale est l'opposé du gradient
+        let gradient = self.gradient(rec.p);
+        let outward_normal = if gradient.length_squared() > 0.0 {
+            -vec3::unit_vector(gradient)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec.set_arbitrary_tangent_frame(outward_normal);
+        true
+    }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common, sphere::Sphere};
+    use serde_json::json;
+
+    fn hit(object: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        object
+            .hit(&Ray::new(origin, direction), 0.001, common::INFINITY, &mut rec)
+            .then_some(rec)
+    }
+
+    // Une seule métaballe : surface sphérique de rayon R sqrt(1 - seuil^(1/3))
+    fn single(center: Point3, radius: f64, threshold: f64) -> (Metaballs, f64) {
+        let metaballs: Metaballs = serde_json::from_value(json!({
+            "balls": [{ "center": center, "radius": radius }],
+            "threshold": threshold,
+            "color": 1.0
+        }))
+        .unwrap();
+        (metaballs, radius * (1.0 - threshold.cbrt()).sqrt())
+    }
+
+    #[test]
+    fn single_ball_matches_the_analytic_sphere() {
+        let center = Point3::new(0.5, -0.25, 1.0);
+        let (metaballs, iso_radius) = single(center, 2.0, 0.3);
+        let sphere = Sphere::new(center, iso_radius, Material::default());
+
+        let mut hits = 0;
+        for i in 0..=20 {
+            for j in 0..=20 {
+                let offset = Vec3::new(i as f64 * 0.15 - 1.5, j as f64 * 0.15 - 1.5, 0.0);
+                for (origin, direction) in [
+                    (center + offset - Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0)),
+                    (center + offset - Vec3::new(3.0, 0.0, 4.0), Vec3::new(0.6, 0.0, 0.8)),
+                    (center + 0.3 * offset, offset + Vec3::new(0.0, 0.0, 0.3)),
+                ] {
+                    let closest = vec3::cross(center - origin, vec3::unit_vector(direction));
+                    if (closest.length() - iso_radius).abs() < 1e-6 {
+                        continue;
+                    }
+
+                    let expected = hit(&sphere, origin, direction);
+                    let found = hit(&metaballs, origin, direction);
+                    assert_eq!(found.is_some(), expected.is_some(), "{} {}", origin, direction);
+                    let (Some(found), Some(expected)) = (found, expected) else {
+                        continue;
+                    };
+                    hits += 1;
+                    assert!((found.t - expected.t).abs() < 1e-6, "{} != {}", found.t, expected.t);
+                    assert_eq!(found.front_face, expected.front_face);
+                    assert!((found.normal - expected.normal).length() < 1e-6);
+                }
+            }
+        }
+        assert!(hits > 500, "{}", hits);
+    }
+
+    #[test]
+    fn grazing_rays_are_found() {
+        let (metaballs, iso_radius) = single(Point3::default(), 1.0, 0.5);
+        // Corde de quelques millièmes, bien plus courte qu'un pas d'échantillonnage régulier
+        for (gap, chord) in [(1e-5, true), (-1e-5, false)] {
+            let height = iso_radius - gap;
+            let origin = Point3::new(-5.0, height, 0.0);
+            let found = hit(&metaballs, origin, Vec3::new(1.0, 0.0, 0.0));
+            assert_eq!(found.is_some(), chord, "{}", height);
+            if let Some(found) = found {
+                let expected = 5.0 - (iso_radius * iso_radius - height * height).sqrt();
+                assert!((found.t - expected).abs() < 1e-6, "{} != {}", found.t, expected);
+            }
+        }
+    }
+}
@@ -1,133 +1,677 @@
 use crate::{
-    camera::Camera,
-    color::{self, Color},
-    common,
-    hittable::{HitRecord, Hittable},
-    hittable_list::HittableList,
-    light::Light,
-    param::Scene,
-    ray::Ray,
-    vec3::{self, Point3, Vec3},
+    aov::Aov,
+    color,
+    error::Error,
+    framebuffer::Framebuffer,
+    matte::{self, Matte},
+    renderer::Frame,
 };
-use std::fs::OpenOptions;
-use std::io::Write;
-
-const ASPECT_RATIO: f64 = 4.0 / 3.0;
-const IMAGE_WIDTH: i32 = 800;
-const IMAGE_HEIGHT: i32 = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as i32;
-const SAMPLES_PER_PIXEL: i32 = 100;
-
-#[derive(Debug)]
-pub struct Output {
-    pub format: String,
-    pub resolution: (u32, u32),
-    pub max_color_value: u32,
-    pub pixel_color: Vec<(u32, u32, u32)>,
-}
-
-impl Output {
-    pub fn new() -> Self {
-        Output {
-            format: "P3".to_string(),
-            resolution: (800, 600),
-            max_color_value: 255,
-            pixel_color: Vec::new(),
+use exr::prelude::{
+    AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, Layer,
+    LayerAttributes, SmallVec, Text, WritableImage,
+};
+use serde_json::{json, Map, Value};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Recadrage puis redimensionnement appliqués à ce qui est écrit : l'image, ses passes
+// et ses mattes. Sans l'un ni l'autre, l'image est écrite telle quelle.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Framing {
+    pub crop: Option<(usize, usize, usize, usize)>, // x, y, largeur, hauteur dans l'image rendue
+    pub size: Option<(usize, usize)>,               // Largeur et hauteur du fichier
+}
+
+impl Framing {
+    pub fn image(&self, framebuffer: &Framebuffer) -> Framebuffer {
+        let mut image = framebuffer.clone();
+        if let Some((x, y, width, height)) = self.crop {
+            image = image.crop(x, y, width, height);
         }
+        if let Some((width, height)) = self.size {
+            image = image.resize(width, height);
+        }
+        image
     }
 
-    pub fn init_pixel_color(&mut self) {
-        for _ in 0..self.resolution.0 * self.resolution.1 {
-            self.pixel_color.push((150, 5, 15));
+    pub fn frame(&self, frame: &Frame) -> Frame {
+        let mut framed = frame.clone();
+        if let Some((x, y, width, height)) = self.crop {
+            framed = framed.crop(x, y, width, height);
+        }
+        if let Some((width, height)) = self.size {
+            framed = framed.resize(width, height);
         }
+        framed
     }
+}
 
-    pub fn edit_image(&mut self) {
-        self.pixel_color.clear();
+// Écrit l'image dans le format donné par l'extension du fichier, après l'avoir recadrée
+// et redimensionnée selon `framing`. Avec `alpha`, les formats qui le permettent (PNG,
+// EXR) gardent la couverture comme canal alpha ; l'image doit alors avoir été rendue sur
+// un fond transparent, sa couleur étant prémultipliée.
+pub fn write(
+    framebuffer: &Framebuffer,
+    path: &Path,
+    alpha: bool,
+    framing: &Framing,
+) -> Result<(), Error> {
+    let image = framing.image(framebuffer);
+    check_size(&image, path)?;
+    write_image(&image, path, alpha)
+}
+
+// Un recadrage hors de l'image ne laisse rien à écrire
+fn check_size(framebuffer: &Framebuffer, path: &Path) -> Result<(), Error> {
+    if framebuffer.width() == 0 || framebuffer.height() == 0 {
+        return Err(Error::image(path, "the crop is outside of the image"));
+    }
+    Ok(())
+}
 
-        let scene = Scene::from_file("scene.json");
+fn write_image(framebuffer: &Framebuffer, path: &Path, alpha: bool) -> Result<(), Error> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
 
-        // World
+    match extension.as_deref() {
+        Some("ppm") => write_ppm(framebuffer, path),
+        Some("png") => write_png(framebuffer, path, alpha),
+        Some("exr") => write_exr(framebuffer, path, alpha),
+        _ => Err(Error::image(
+            path,
+            "unsupported output format, expected .ppm, .png or .exr",
+        )),
+    }
+}
+
+// Écrit l'image, ses passes auxiliaires et ses mattes. En EXR, les passes sont des
+// canaux supplémentaires du même fichier (depth.Z, normal.X, ...) et les mattes des
+// calques Cryptomatte (CryptoObject00.R, ...), sauf avec `separate`. Sinon chaque passe
+// va dans son propre fichier, <nom>_<passe>.<ext> : valeurs brutes en EXR, version
+// affichable en PPM et PNG. En PPM et PNG, chaque matte donne un masque par identifiant
+// et un manifeste JSON. Le recadrage et le redimensionnement s'appliquent à tous les fichiers.
+pub fn write_frame(
+    frame: &Frame,
+    path: &Path,
+    alpha: bool,
+    separate: bool,
+    framing: &Framing,
+) -> Result<(), Error> {
+    let frame = &framing.frame(frame);
+    check_size(&frame.beauty, path)?;
+    let exr = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("exr"));
+    if exr && !separate && !(frame.aovs.is_empty() && frame.mattes.is_empty()) {
+        let mut channels = beauty_channels(&frame.beauty, alpha);
+        for (aov, pass) in &frame.aovs {
+            channels.extend(aov_channels(*aov, pass, &format!("{}.", aov.name())));
+        }
+        let mut attributes = LayerAttributes::default();
+        for matte in &frame.mattes {
+            channels.extend(cryptomatte_channels(matte));
+            attributes.other.extend(cryptomatte_attributes(matte));
+        }
+        let size = (frame.beauty.width(), frame.beauty.height());
+        return write_exr_channels(size, channels, attributes, path);
+    }
+
+    write_image(&frame.beauty, path, alpha)?;
+    for (aov, pass) in &frame.aovs {
+        let path = aov_path(path, *aov);
+        if exr {
+            let channels = aov_channels(*aov, pass, "");
+            let size = (pass.width(), pass.height());
+            write_exr_channels(size, channels, LayerAttributes::default(), &path)?;
+        } else {
+            write_image(&aov.preview(pass), &path, false)?;
+        }
+    }
+    for matte in &frame.mattes {
+        if exr {
+            let path = sibling(path, &format!("matte_{}", matte.kind().name()), Some("exr"));
+            let attributes = LayerAttributes {
+                other: cryptomatte_attributes(matte).collect(),
+                ..LayerAttributes::default()
+            };
+            let size = (matte.width(), matte.height());
+            write_exr_channels(size, cryptomatte_channels(matte), attributes, &path)?;
+        } else {
+            write_masks(matte, path)?;
+        }
+    }
+    Ok(())
+}
+
+// output.exr -> output_depth.exr
+pub fn aov_path(path: &Path, aov: Aov) -> PathBuf {
+    let extension = path.extension().map(|e| e.to_string_lossy());
+    sibling(path, aov.name(), extension.as_deref())
+}
 
-        let mut world = HittableList::new();
-        world.set_scene(scene.clone());
+// <nom>_<suffixe>.<extension>, à côté du fichier
+fn sibling(path: &Path, suffix: &str, extension: Option<&str>) -> PathBuf {
+    let mut name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    name.push('_');
+    name.push_str(suffix);
+    if let Some(extension) = extension {
+        name.push('.');
+        name.push_str(extension);
+    }
+    path.with_file_name(name)
+}
 
-        // Light source
-        let light = Light::new(
-            scene.light.position,
-            scene.light.color,
-            scene.light.intensity,
+// Un masque de couverture par identifiant, <nom>_matte_<sorte>_<n>.<ext>, et le
+// manifeste <nom>_matte_<sorte>.json : pour chaque nom, son hachage Cryptomatte et son masque
+fn write_masks(matte: &Matte, path: &Path) -> Result<(), Error> {
+    let extension = path.extension().map(|e| e.to_string_lossy());
+    let prefix = format!("matte_{}", matte.kind().name());
+    let mut manifest = Map::new();
+    for (id, name) in matte.names().iter().enumerate() {
+        let mask_path = sibling(path, &format!("{}_{}", prefix, id), extension.as_deref());
+        write_image(&matte.mask(id), &mask_path, false)?;
+        let file = mask_path.file_name().map(|f| f.to_string_lossy().into_owned());
+        manifest.insert(
+            name.clone(),
+            json!({ "hash": format!("{:08x}", matte::hash(name)), "mask": file }),
         );
+    }
+
+    let manifest_path = sibling(path, &prefix, Some("json"));
+    let text = serde_json::to_string_pretty(&Value::Object(manifest))
+        .map_err(|e| Error::format(&manifest_path, e.to_string()))?;
+    replace(&manifest_path, |temporary| {
+        fs::write(temporary, text + "\n").map_err(|e| Error::io(&manifest_path, e))
+    })
+}
+
+// Écrit dans un fichier temporaire voisin (.<nom>.tmp) puis le renomme en `path` : pendant un
+// rendu progressif, une visionneuse qui relit l'image ne voit jamais un fichier à moitié écrit.
+// `write` reçoit le chemin temporaire ; ses erreurs doivent nommer `path`.
+fn replace(path: &Path, write: impl FnOnce(&Path) -> Result<(), Error>) -> Result<(), Error> {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{}.tmp", name));
+    if let Err(error) = write(&temporary) {
+        let _ = fs::remove_file(&temporary);
+        return Err(error);
+    }
+    fs::rename(&temporary, path).map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        Error::io(path, e)
+    })
+}
+
+// PPM texte (P3), 8 bits par composante, sans alpha
+pub fn write_ppm(framebuffer: &Framebuffer, path: &Path) -> Result<(), Error> {
+    let mut code = String::new();
+
+    code.push_str("P3\n");
+    code.push_str(&format!(
+        "{} {}\n",
+        framebuffer.width(),
+        framebuffer.height()
+    ));
+    code.push_str("255\n");
+    for [r, g, b, _] in framebuffer.pixels() {
+        let [r, g, b] = [r, g, b].map(|c| color::to_byte(c as f64));
+        code.push_str(&format!("{} {} {}\n", r, g, b));
+    }
+
+    replace(path, |temporary| {
+        let mut file = File::create(temporary).map_err(|e| Error::io(path, e))?;
+        write!(file, "{}", code).map_err(|e| Error::io(path, e))
+    })
+}
 
-        // Camera
-        let cam = Camera::new(
-            scene.camera.origin,
-            scene.camera.look_at,
-            Vec3::new(0.0, 1.0, 0.0),
-            90.0,
-            ASPECT_RATIO,
+// PNG 8 bits, RVB ou RVBA. Le PNG stocke une couleur non prémultipliée.
+pub fn write_png(framebuffer: &Framebuffer, path: &Path, alpha: bool) -> Result<(), Error> {
+    let mut data = Vec::with_capacity(framebuffer.width() * framebuffer.height() * 4);
+    for [r, g, b, a] in framebuffer.pixels() {
+        let straight = if alpha && a > 0.0 { a } else { 1.0 };
+        data.extend([r, g, b].map(|c| color::to_byte((c / straight) as f64)));
+        if alpha {
+            data.push(color::to_byte(a as f64));
+        }
+    }
+
+    replace(path, |temporary| {
+        let file = File::create(temporary).map_err(|e| Error::io(path, e))?;
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(file),
+            framebuffer.width() as u32,
+            framebuffer.height() as u32,
         );
+        encoder.set_color(if alpha {
+            png::ColorType::Rgba
+        } else {
+            png::ColorType::Rgb
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(|e| Error::image(path, e.to_string()))
+    })
+}
+
+// OpenEXR en flottants 32 bits, couleur linéaire non bornée, prémultipliée avec alpha
+pub fn write_exr(framebuffer: &Framebuffer, path: &Path, alpha: bool) -> Result<(), Error> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    replace(path, |temporary| {
+        let written = if alpha {
+            exr::prelude::write_rgba_file(temporary, width, height, |x, y| {
+                let [r, g, b, a] = framebuffer.pixel(x, y);
+                (r, g, b, a)
+            })
+        } else {
+            exr::prelude::write_rgb_file(temporary, width, height, |x, y| {
+                let [r, g, b, _] = framebuffer.pixel(x, y);
+                (r, g, b)
+            })
+        };
+        written.map_err(|e| Error::image(path, e.to_string()))
+    })
+}
+
+// Une composante de chaque pixel, ligne du haut en premier
+fn component(framebuffer: &Framebuffer, c: usize) -> Vec<f32> {
+    framebuffer.pixels().map(|pixel| pixel[c]).collect()
+}
+
+fn beauty_channels(framebuffer: &Framebuffer, alpha: bool) -> Vec<AnyChannel<FlatSamples>> {
+    let names: &[&str] = if alpha { &["R", "G", "B", "A"] } else { &["R", "G", "B"] };
+    names
+        .iter()
+        .enumerate()
+        .map(|(c, name)| AnyChannel::new(*name, FlatSamples::F32(component(framebuffer, c))))
+        .collect()
+}
+
+fn aov_channels(aov: Aov, pass: &Framebuffer, prefix: &str) -> Vec<AnyChannel<FlatSamples>> {
+    aov.channels()
+        .iter()
+        .enumerate()
+        .map(|(c, name)| {
+            let name = format!("{}{}", prefix, name);
+            AnyChannel::new(name.as_str(), FlatSamples::F32(component(pass, c)))
+        })
+        .collect()
+}
 
-        for j in (0..IMAGE_HEIGHT).rev() {
-            eprint!("\rScanlines remaining: {} ", j);
-            for i in 0..IMAGE_WIDTH {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..SAMPLES_PER_PIXEL {
-                    let u = (i as f64 + common::random_double()) / (IMAGE_WIDTH - 1) as f64;
-                    let v = (j as f64 + common::random_double()) / (IMAGE_HEIGHT - 1) as f64;
-                    let r = cam.get_ray(u, v);
-                    pixel_color += ray_color(&r, &world, &light);
-                }
-                color::write_color(self, pixel_color, SAMPLES_PER_PIXEL);
+// Calque Cryptomatte : chaque groupe de canaux RGBA porte deux rangs (hachage de
+// l'identifiant, couverture), les identifiants les plus couvrants en premier
+fn cryptomatte_channels(matte: &Matte) -> Vec<AnyChannel<FlatSamples>> {
+    // Nombre pair de rangs, au moins un groupe
+    let ranks = matte.depth().max(1).div_ceil(2) * 2;
+    let hashes: Vec<f32> = matte
+        .names()
+        .iter()
+        .map(|name| f32::from_bits(matte::hash(name)))
+        .collect();
+
+    let pixels = matte.width() * matte.height();
+    let mut values = vec![vec![0.0f32; pixels]; ranks * 2];
+    for y in 0..matte.height() {
+        for x in 0..matte.width() {
+            let pixel = y * matte.width() + x;
+            for (rank, (id, coverage)) in matte.coverage(x, y).into_iter().enumerate() {
+                values[rank * 2][pixel] = hashes[id];
+                values[rank * 2 + 1][pixel] = coverage;
             }
         }
-        eprint!("\nDone.\n");
     }
 
-    pub fn generate_ppm_file(&self) {
-        let mut code = String::new();
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(c, values)| {
+            let name = format!("{}{:02}.{}", matte.kind().layer(), c / 4, ["R", "G", "B", "A"][c % 4]);
+            AnyChannel::new(name.as_str(), FlatSamples::F32(values))
+        })
+        .collect()
+}
+
+// Métadonnées Cryptomatte du calque, dans l'en-tête du fichier
+fn cryptomatte_attributes(matte: &Matte) -> impl Iterator<Item = (Text, AttributeValue)> {
+    let layer = matte.kind().layer();
+    let key = matte::layer_key(layer);
+    [
+        ("name", layer.to_string()),
+        ("hash", "MurmurHash3_32".to_string()),
+        ("conversion", "uint32_to_float32".to_string()),
+        ("manifest", matte.manifest()),
+    ]
+    .into_iter()
+    .map(move |(field, value)| {
+        // Le manifeste est en UTF-8, comme l'attendent les lecteurs Cryptomatte
+        let value = Text::from_bytes_unchecked(SmallVec::from_vec(value.into_bytes()));
+        let name = Text::from(format!("cryptomatte/{}/{}", key, field).as_str());
+        (name, AttributeValue::Text(value))
+    })
+}
+
+// EXR d'une seule partie avec des canaux quelconques, compressé sans perte
+fn write_exr_channels(
+    size: (usize, usize),
+    channels: Vec<AnyChannel<FlatSamples>>,
+    attributes: LayerAttributes,
+    path: &Path,
+) -> Result<(), Error> {
+    let layer = Layer::new(
+        size,
+        attributes,
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+    replace(path, |temporary| {
+        Image::from_layer(layer)
+            .write()
+            .to_file(temporary)
+            .map_err(|e| Error::image(path, e.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, image::Image, matte::MatteKind};
+
+    #[test]
+    fn ppm_is_read_back() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.add_sample(0, 0, Color::new(1.0, 0.0, 0.5), 1.0);
+        framebuffer.add_sample(2, 1, Color::new(0.25, 2.0, -1.0), 1.0);
+
+        let path = std::env::temp_dir().join(format!("rt-output-{}.ppm", std::process::id()));
+        write(&framebuffer, &path, false, &Framing::default()).unwrap();
+        let image = Image::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let image = image.unwrap();
+
+        assert_eq!((image.width, image.height), (3, 2));
+        let bytes = |c: Color| [c.x(), c.y(), c.z()].map(|v| (v * 255.0).round() as u8);
+        assert_eq!(bytes(image.pixel(0, 0)), [255, 0, 128]);
+        assert_eq!(bytes(image.pixel(2, 1)), [64, 255, 0]);
+        assert_eq!(bytes(image.pixel(1, 0)), [0, 0, 0]);
+    }
+
+    #[test]
+    fn files_are_replaced_whole() {
+        let dir = std::env::temp_dir().join(format!("rt-output-replace-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("render.ppm");
+        let entries = || {
+            let mut names: Vec<_> = fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            names
+        };
+
+        write(&Framebuffer::new(1, 1), &path, false, &Framing::default()).unwrap();
+        write(&Framebuffer::new(2, 1), &path, false, &Framing::default()).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("P3\n2 1\n"), "{}", text);
+        assert_eq!(entries(), ["render.ppm"]);
 
-        code.push_str(&format!("{}\n", self.format));
-        code.push_str(&format!("{} {}\n", IMAGE_WIDTH, IMAGE_HEIGHT));
-        code.push_str(&format!("{}\n", self.max_color_value));
-        //println!("Pixel_color: {:?}", self.pixel_color);
-        for (r, g, b) in self.pixel_color.iter() {
-            code.push_str(&format!("{} {} {}\n", r, g, b));
+        // Une écriture qui échoue laisse l'image précédente intacte, sans fichier temporaire
+        let failed = replace(&path, |temporary| {
+            fs::write(temporary, "P3\n").unwrap();
+            Err(Error::image(&path, "interrupted"))
+        });
+        assert!(failed.is_err());
+        let (kept, left) = (fs::read_to_string(&path).unwrap(), entries());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(kept, text);
+        assert_eq!(left, ["render.ppm"]);
+    }
+
+    fn half_covered() -> Framebuffer {
+        // Pixel de gauche couvert à moitié par du rouge, sur un fond transparent
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.add_sample(0, 0, Color::new(1.0, 0.0, 0.0), 1.0);
+        framebuffer.add_sample(0, 0, Color::new(0.0, 0.0, 0.0), 0.0);
+        framebuffer.add_sample(1, 0, Color::new(0.0, 2.0, 0.0), 1.0);
+        framebuffer
+    }
+
+    #[test]
+    fn png_keeps_alpha_with_straight_colors() {
+        let path = std::env::temp_dir().join(format!("rt-output-{}.png", std::process::id()));
+        write(&half_covered(), &path, true, &Framing::default()).unwrap();
+        let decoder = png::Decoder::new(std::io::BufReader::new(File::open(&path).unwrap()));
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(&data[..8], &[255, 0, 0, 128, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn exr_keeps_float_values_and_alpha() {
+        let path = std::env::temp_dir().join(format!("rt-output-{}.exr", std::process::id()));
+        write(&half_covered(), &path, true, &Framing::default()).unwrap();
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            &path,
+            |resolution, _| vec![[0.0f32; 4]; resolution.width() * resolution.height()],
+            |pixels, position, (r, g, b, a): (f32, f32, f32, f32)| {
+                pixels[position.y() * 2 + position.x()] = [r, g, b, a];
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+        let pixels = image.unwrap().layer_data.channel_data.pixels;
+
+        // Couleur prémultipliée et non bornée
+        assert_eq!(pixels, vec![[0.5, 0.0, 0.0, 0.5], [0.0, 2.0, 0.0, 1.0]]);
+    }
+
+    fn depth_and_ids() -> Frame {
+        let mut depth = Framebuffer::new(2, 1);
+        depth.set(0, 0, [2.5, 2.5, 2.5, 1.0]);
+        depth.set(1, 0, [f32::INFINITY, f32::INFINITY, f32::INFINITY, 0.0]);
+        let mut ids = Framebuffer::new(2, 1);
+        ids.set(0, 0, [3.0, 3.0, 3.0, 1.0]);
+        Frame {
+            beauty: half_covered(),
+            aovs: vec![(Aov::Depth, depth), (Aov::ObjectId, ids)],
+            mattes: Vec::new(),
         }
+    }
 
-        code.trim_end().to_string();
+    // Pixel de gauche : 3 échantillons sur la balle, 1 sur le sol ; pixel de droite : le fond
+    fn ball_and_floor() -> Frame {
+        let mut matte = Matte::new(MatteKind::Object, vec!["ball".into(), "floor".into()], 2, 1);
+        for id in [Some(0), Some(1), Some(0), Some(0)] {
+            matte.add_sample(0, 0, id);
+        }
+        matte.add_sample(1, 0, None);
+        Frame {
+            beauty: half_covered(),
+            aovs: Vec::new(),
+            mattes: vec![matte],
+        }
+    }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true) // Réinitialise le fichier à chaque exécution
-            .open("output.ppm")
-            .expect("Erreur lors de l'ouverture du fichier");
+    // Canaux d'un EXR, par nom
+    fn read_channels(path: &Path) -> Vec<(String, Vec<f32>)> {
+        let image = exr::prelude::read_first_flat_layer_from_file(path).unwrap();
+        image
+            .layer_data
+            .channel_data
+            .list
+            .iter()
+            .map(|channel| {
+                let values = channel.sample_data.values_as_f32().collect();
+                (channel.name.to_string(), values)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aovs_are_layers_of_one_exr() {
+        let path = std::env::temp_dir().join(format!("rt-layers-{}.exr", std::process::id()));
+        write_frame(&depth_and_ids(), &path, true, false, &Framing::default()).unwrap();
+        let channels = read_channels(&path);
+        std::fs::remove_file(&path).unwrap();
 
-        write!(file, "{}", code).expect("Writing to file error");
+        let names: Vec<_> = channels.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["A", "B", "G", "R", "depth.Z", "object_id.id"]);
+        assert_eq!(channels[3].1, [0.5, 0.0]);
+        assert_eq!(channels[4].1, [2.5, f32::INFINITY]);
+        assert_eq!(channels[5].1, [3.0, 0.0]);
     }
-}
 
-fn ray_color(r: &Ray, world: &dyn Hittable, light: &Light) -> Color {
-    let mut rec = HitRecord::new();
-    if world.hit(r, 0.001, common::INFINITY, &mut rec) {
-        let lighting = compute_lighting(&rec.p, &rec.normal, world, light);
-        return rec.color * lighting;
+    #[test]
+    fn aovs_can_be_separate_files() {
+        let dir = std::env::temp_dir().join(format!("rt-aovs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_frame(
+            &depth_and_ids(),
+            &dir.join("frame.exr"),
+            false,
+            true,
+            &Framing::default(),
+        )
+        .unwrap();
+        write_frame(
+            &depth_and_ids(),
+            &dir.join("frame.png"),
+            false,
+            true,
+            &Framing::default(),
+        )
+        .unwrap();
+
+        let depth = read_channels(&dir.join("frame_depth.exr"));
+        assert_eq!(depth, [("Z".to_string(), vec![2.5, f32::INFINITY])]);
+        assert_eq!(read_channels(&dir.join("frame.exr")).len(), 3);
+        for name in ["frame.png", "frame_depth.png", "frame_object_id.png"] {
+            assert!(dir.join(name).is_file(), "{} is missing", name);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-    Color::new(0.5, 0.7, 1.0) * light.color * light.intensity
-}
 
-fn compute_lighting(point: &Point3, normal: &Vec3, world: &dyn Hittable, light: &Light) -> Color {
-    let light_dir = vec3::unit_vector(light.position - *point); // Direction vers la lumière
-    let shadow_ray = Ray::new(*point, light_dir); // Rayon vers la lumière
+    #[test]
+    fn mattes_are_cryptomatte_layers() {
+        let path = std::env::temp_dir().join(format!("rt-crypto-{}.exr", std::process::id()));
+        write_frame(&ball_and_floor(), &path, false, false, &Framing::default()).unwrap();
+        let image = exr::prelude::read_first_flat_layer_from_file(&path);
+        let channels = read_channels(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let names: Vec<_> = channels.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["B", "CryptoObject00.A", "CryptoObject00.B", "CryptoObject00.G", "CryptoObject00.R", "G", "R"]
+        );
+        let ball = f32::from_bits(matte::hash("ball"));
+        let floor = f32::from_bits(matte::hash("floor"));
+        assert_eq!(channels[4].1[0].to_bits(), ball.to_bits());
+        assert_eq!(channels[3].1, [0.75, 0.0]);
+        assert_eq!(channels[2].1[0].to_bits(), floor.to_bits());
+        assert_eq!(channels[1].1, [0.25, 0.0]);
+
+        // Métadonnées sous la clé du calque
+        let attributes = image.unwrap().layer_data.attributes.other;
+        let text = |field: &str| {
+            let name = format!("cryptomatte/{}/{}", matte::layer_key("CryptoObject"), field);
+            match &attributes[&Text::from(name.as_str())] {
+                AttributeValue::Text(text) => text.to_string(),
+                other => panic!("{}: {:?}", field, other),
+            }
+        };
+        assert_eq!(text("name"), "CryptoObject");
+        assert_eq!(text("hash"), "MurmurHash3_32");
+        assert_eq!(text("conversion"), "uint32_to_float32");
+        let manifest: Value = serde_json::from_str(&text("manifest")).unwrap();
+        assert_eq!(manifest["ball"], format!("{:08x}", matte::hash("ball")));
+    }
+
+    #[test]
+    fn mattes_are_masks_with_a_manifest() {
+        let dir = std::env::temp_dir().join(format!("rt-masks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_frame(
+            &ball_and_floor(),
+            &dir.join("frame.png"),
+            false,
+            false,
+            &Framing::default(),
+        )
+        .unwrap();
+        let manifest = std::fs::read_to_string(dir.join("frame_matte_object.json"));
+        let ball = Image::load(&dir.join("frame_matte_object_0.png"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let manifest: Value = serde_json::from_str(&manifest.unwrap()).unwrap();
+        assert_eq!(
+            manifest["floor"],
+            json!({ "hash": format!("{:08x}", matte::hash("floor")), "mask": "frame_matte_object_1.png" })
+        );
+        let ball = ball.unwrap();
+        assert_eq!((ball.pixel(0, 0).x() * 255.0).round(), 192.0);
+        assert_eq!(ball.pixel(1, 0).x(), 0.0);
+    }
 
-    // Vérifier s'il y a un obstacle entre le point et la lumière
-    let mut temp_rec = HitRecord::new();
-    if world.hit(&shadow_ray, 0.001, common::INFINITY, &mut temp_rec) {
-        return Color::new(0.4, 0.4, 0.4); // Ombre complète (pas de lumière)
+    #[test]
+    fn framing_applies_to_every_layer() {
+        let path = std::env::temp_dir().join(format!("rt-framing-{}.exr", std::process::id()));
+        let crop = Framing {
+            crop: Some((1, 0, 5, 5)),
+            size: None,
+        };
+        write_frame(&depth_and_ids(), &path, true, false, &crop).unwrap();
+        let cropped = read_channels(&path);
+
+        let shrink = Framing {
+            crop: None,
+            size: Some((1, 1)),
+        };
+        write_frame(&ball_and_floor(), &path, false, false, &shrink).unwrap();
+        let shrunk = read_channels(&path);
+
+        let outside = Framing {
+            crop: Some((2, 0, 1, 1)),
+            size: None,
+        };
+        let error = write(&half_covered(), &path, false, &outside).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        // Pixel de droite seul, image et passes
+        assert_eq!(cropped[2].1, [2.0]);
+        assert_eq!(cropped[4].1, [f32::INFINITY]);
+        assert_eq!(cropped[5].1, [0.0]);
+        // Moyenne des deux pixels : la balle couvre 0,375 du pixel, le sol 0,125
+        assert_eq!(shrunk[6].1, [0.25]);
+        assert_eq!(shrunk[3].1, [0.375]);
+        assert_eq!(shrunk[1].1, [0.125]);
+        assert_eq!(
+            error.to_string(),
+            format!("{}: the crop is outside of the image", path.display())
+        );
     }
 
-    // Calcul de l'éclairage diffus
-    let diff = vec3::dot(*normal, light_dir).max(0.4);
-    //println!("diff: {}", diff);
-    diff * light.color * light.intensity
+    #[test]
+    fn unknown_extensions_are_rejected() {
+        let error = write(
+            &Framebuffer::new(1, 1),
+            Path::new("output.gif"),
+            false,
+            &Framing::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "output.gif: unsupported output format, expected .ppm, .png or .exr"
+        );
+    }
 }
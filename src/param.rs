@@ -1,24 +1,376 @@
-use std::fs;
+use std::{fs, path::Path};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{
-    camera::Camera, cube::Cube, cylinder::Cylinder, light::Light, plane::Plane, sphere::Sphere,
+    aov::Aov,
+    background::Background,
+    camera::{Camera, NamedCamera},
+    compose,
+    cube::Cube,
+    cylinder::Cylinder,
+    error::Error,
+    format::{self, SceneFormat},
+    gltf_import,
+    heightfield::Heightfield,
+    light::Light,
+    matte::MatteKind,
+    mesh::Mesh,
+    medium::{HeightFog, Volume},
+    metaballs::Metaballs,
+    plane::Plane,
+    sdf::Sdf,
+    sphere::Sphere,
 };
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Scene {
-    pub camera: Camera,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<Camera>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cameras: Vec<NamedCamera>,
     pub light: Light,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<Background>,
+    #[serde(default)]
     pub spheres: Vec<Sphere>,
+    #[serde(default)]
     pub planes: Vec<Plane>,
+    #[serde(default)]
     pub cubes: Vec<Cube>,
+    #[serde(default)]
     pub cylinders: Vec<Cylinder>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sdfs: Vec<Sdf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heightfields: Vec<Heightfield>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meshes: Vec<Mesh>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metaballs: Vec<Metaballs>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<Volume>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fog: Option<HeightFog>,
+    // Passes auxiliaires écrites à côté de l'image
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aovs: Vec<Aov>,
+    // Mattes d'identifiants (par objet, par matériau)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mattes: Vec<MatteKind>,
+    // Avertissements de la lecture (import glTF), affichés par la ligne de commande
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+// Trie les clés de chaque objet du document, quel que soit l'ordre gardé par `Map`
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
 }
 
 impl Scene {
-    pub fn from_file(file_path: &str) -> Self {
-        let json_data = fs::read_to_string(file_path).expect("Failed to read JSON file");
-        serde_json::from_str(&json_data).expect("Failed to parse JSON")
+    // Lit, valide puis charge les ressources de la scène
+    pub fn from_file(file_path: &str) -> Result<Self, Error> {
+        let path = Path::new(file_path);
+        let mut scene = Scene::read(path)?;
+
+        let problems = scene.validate();
+        if !problems.is_empty() {
+            return Err(Error::Invalid {
+                path: path.to_path_buf(),
+                problems,
+            });
+        }
+
+        // Les images des textures sont relatives au fichier de la scène
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        scene.load_assets(base_dir)?;
+        Ok(scene)
+    }
+
+    // Lit la scène (JSON, YAML ou TOML selon l'extension), avec ses inclusions et
+    // ses définitions nommées, sans la valider ni charger les images
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        if gltf_import::is_gltf(path) {
+            let import = gltf_import::import_scene(path)?;
+            return Scene::from_document(path, import.document, import.warnings);
+        }
+
+        let format = SceneFormat::from_path(path)?;
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let document: Value = format.parse(path, &text)?;
+        if !compose::uses_composition(&document) {
+            // Le texte n'est relu qu'en cas d'erreur, pour la situer par sa ligne et sa colonne
+            return Scene::deserialize(document).or_else(|_| format.parse(path, &text));
+        }
+
+        let (document, warnings) = compose::compose(path, document)?;
+        Scene::from_document(path, document, warnings)
+    }
+
+    // Écrit la scène dans le format donné par l'extension. La sortie est canonique :
+    // les clés sont triées à chaque niveau, les valeurs par défaut restent explicites
+    // et les nombres sont écrits au plus court, si bien qu'une scène relue puis
+    // réécrite donne exactement le même fichier.
+    pub fn to_file(&self, path: &Path) -> Result<(), Error> {
+        let document = serde_json::to_value(self).map_err(|e| Error::format(path, e.to_string()))?;
+        format::write(path, &sort_keys(document))
+    }
+
+    // Le document fusionné ou importé ne correspond plus au texte : l'erreur est située par son chemin
+    fn from_document(path: &Path, document: Value, warnings: Vec<String>) -> Result<Self, Error> {
+        let scene: Scene =
+            serde_path_to_error::deserialize(document).map_err(|e| Error::Parse {
+                path: path.to_path_buf(),
+                line: 0,
+                column: 0,
+                message: format!("{}: {}", e.path(), e.inner()),
+            })?;
+        Ok(Scene { warnings, ..scene })
+    }
+
+    // Charge les images référencées par la scène (textures, carte d'environnement, terrains)
+    pub fn load_assets(&mut self, base_dir: &Path) -> Result<(), Error> {
+        if let Some(background) = &mut self.background {
+            background.load(base_dir)?;
+        }
+        for sphere in &mut self.spheres {
+            sphere.material.load(base_dir)?;
+        }
+        for plane in &mut self.planes {
+            plane.material.load(base_dir)?;
+        }
+        for cube in &mut self.cubes {
+            cube.material.load(base_dir)?;
+        }
+        for cylinder in &mut self.cylinders {
+            cylinder.material.load(base_dir)?;
+        }
+        for sdf in &mut self.sdfs {
+            sdf.material.load(base_dir)?;
+        }
+        for metaballs in &mut self.metaballs {
+            metaballs.material.load(base_dir)?;
+        }
+        for heightfield in &mut self.heightfields {
+            heightfield.load(base_dir)?;
+        }
+        for mesh in &mut self.meshes {
+            mesh.load(base_dir)?;
+        }
+        for volume in &mut self.volumes {
+            volume.load(base_dir)?;
+        }
+        Ok(())
+    }
+
+    // Toutes les caméras à rendre : la caméra principale (sans nom) puis la liste `cameras`
+    pub fn views(&self) -> Vec<(Option<String>, Camera)> {
+        let mut views = Vec::new();
+        if let Some(camera) = self.camera {
+            views.push((None, camera));
+        }
+        for named in &self.cameras {
+            views.push((Some(named.name.clone()), named.camera));
+        }
+        views
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    const BASE: &str = r##"{
+        "camera": { "origin": [0.0, 1.0, -4.0], "look_at": [0.0, 0.0, 0.0], "fov": 60.0 },
+        "light": { "position": [2.0, 6.0, -4.0], "color": [1.0, 1.0, 1.0], "intensity": 1.0 }
+    }"##;
+
+    // Une entrée par type d'objet, avec des champs optionnels et des textures variés
+    const OBJECTS: &[(&str, &str)] = &[
+        ("spheres", r##"{ "name": "marble ball", "center": [0.0, 1.0, 0.0], "radius": 0.5,
+            "color": { "type": "marble", "low": "#202020", "high": "#f0f0f0", "scale": 3.0, "seed": 7 } }"##),
+        ("planes", r##"{ "origine": [-3.0, -1.0, -2.0], "width": 10, "height": 10,
+            "color": { "type": "uv_checker", "even": "#ffffff", "odd": "#000000", "width": 8, "height": 8 } }"##),
+        ("cubes", r##"{ "min": [-1.0, 0.0, -1.0], "max": [1.0, 0.1, 1.0], "color": 0.3, "material_name": "slab",
+            "normal_map": { "type": "image", "file": "bricks_normal.png" } }"##),
+        ("cylinders", r##"{ "base": [2.0, 0.0, 0.0], "height": 1.5, "radius": 0.25, "color": [0.1, 0.2, 0.3],
+            "bump_map": { "type": "fbm", "low": 0.0, "high": 1.0, "scale": 20.0 }, "bump_scale": 0.02 }"##),
+        ("sdfs", r##"{ "root": { "type": "smooth_union", "k": 0.3, "children": [
+                { "type": "sphere", "center": [0.5, 0.4, 1.5], "radius": 0.4 },
+                { "type": "twist", "amount": 1.5, "child": { "type": "box", "half_size": [0.3, 0.3, 0.3] } }
+            ] },
+            "color": [0.9, 0.4, 0.2], "bounds": { "center": [0.8, 0.4, 1.5], "radius": 1.2 } }"##),
+        ("heightfields", r##"{ "origin": [2.5, 0.0, 2.0], "width": 1.0, "depth": 1.0,
+            "heights": [[0.0, 0.5, 0.0], [0.5, 1.0, 0.5]], "color": [0.9, 0.9, 0.9] }"##),
+        ("meshes", r##"{ "positions": [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            "normals": [[0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+            "uvs": [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]], "triangles": [[0, 1, 2]], "color": [0.7, 0.1, 0.1] }"##),
+        ("metaballs", r##"{ "balls": [{ "center": [1.0, 0.6, -1.0], "radius": 0.7 }, { "center": [1.5, 0.7, -0.8], "radius": 0.6 }],
+            "threshold": 0.3, "color": [0.9, 0.3, 0.6] }"##),
+        ("volumes", r##"{ "boundary": { "type": "cylinder", "base": [1.0, 0.0, 1.0], "height": 2.0, "radius": 0.8 },
+            "density": 3.0, "albedo": [0.9, 0.9, 0.9] }"##),
+        ("volumes", r##"{ "name": "smoke", "boundary": { "type": "sdf",
+            "root": { "type": "torus", "major_radius": 1.0, "minor_radius": 0.3 } },
+            "density": 1.5, "albedo": 0.8 }"##),
+        ("cameras", r##"{ "name": "vr", "origin": [3.0, 2.2, -4.0], "look_at": [2.0, 0.0, 0.0],
+            "stereo": { "interocular_distance": 0.065, "layout": "separate" } }"##),
+    ];
+
+    // Les autres blocs de la scène
+    const SETTINGS: &[(&str, &str)] = &[
+        ("background", r##"{ "type": "sky", "sun_direction": [1.0, 0.4, -0.5], "turbidity": 3.0 }"##),
+        ("fog", r##"{ "density": 0.15, "falloff": 1.0, "base_height": -1.0, "color": [0.7, 0.75, 0.8] }"##),
+        ("aovs", r##"["depth", "object_id", "shadow"]"##),
+        ("mattes", r##"["object", "material"]"##),
+    ];
+
+    fn scene_with(key: &str, value: &str, in_list: bool) -> Scene {
+        let mut document: Value = serde_json::from_str(BASE).unwrap();
+        let value: Value = serde_json::from_str(value).unwrap();
+        document[key] = if in_list { Value::Array(vec![value]) } else { value };
+        serde_json::from_value(document).unwrap_or_else(|e| panic!("{}: {}", key, e))
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rt-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Charge → écrit → recharge, dans chaque format, et vérifie que la scène est identique
+    // et que la réécriture donne le même fichier
+    fn assert_round_trip(dir: &Path, name: &str, scene: &Scene) {
+        for extension in ["json", "yaml", "toml"] {
+            let path = dir.join(format!("{}.{}", name, extension));
+            scene.to_file(&path).unwrap();
+            let text = fs::read_to_string(&path).unwrap();
+
+            let reloaded = Scene::read(&path)
+                .unwrap_or_else(|e| panic!("{} ({}): {}\n{}", name, extension, e, text));
+            assert!(reloaded == *scene, "{} ({})", name, extension);
+
+            reloaded.to_file(&path).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), text, "{} ({})", name, extension);
+        }
+    }
+
+    #[test]
+    fn every_object_type_round_trips() {
+        let dir = temp_dir("round-trip");
+        for (key, value) in OBJECTS {
+            assert_round_trip(&dir, key, &scene_with(key, value, true));
+        }
+        for (key, value) in SETTINGS {
+            assert_round_trip(&dir, key, &scene_with(key, value, false));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn loaded_assets_are_not_compared() {
+        // Terrain et maillage : leur grille et leur BVH sont construits au chargement
+        for key in ["heightfields", "meshes"] {
+            let value = OBJECTS.iter().find(|(k, _)| *k == key).unwrap().1;
+            let scene = scene_with(key, value, true);
+            let mut loaded = scene.clone();
+            loaded.load_assets(Path::new(".")).unwrap();
+            assert!(loaded == scene, "{}", key);
+        }
+    }
+
+    #[test]
+    fn objects_are_named_in_the_order_of_the_world() {
+        let mut document: Value = serde_json::from_str(BASE).unwrap();
+        document["materials"] = json!({ "stone": { "color": 0.5 } });
+        document["cubes"] = json!([
+            { "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 1.0], "color": 1.0 },
+            { "name": "pedestal", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 1.0], "material": "stone" }
+        ]);
+        document["spheres"] = json!([{ "name": "ball", "center": [0.0, 0.0, 0.0], "radius": 1.0, "color": 1.0 }]);
+        document["volumes"] = json!([
+            { "boundary": { "type": "sphere", "center": [0.0, 0.0, 0.0], "radius": 1.0 }, "density": 1.0, "albedo": 1.0 }
+        ]);
+        let dir = temp_dir("names");
+        let path = dir.join("scene.json");
+        fs::write(&path, document.to_string()).unwrap();
+        let scene = Scene::read(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        let scene = scene.unwrap();
+
+        let labels = HittableList::new().set_scene(scene);
+        let names: Vec<_> = labels.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["ball", "cubes[0]", "pedestal", "volumes[0]"]);
+        let materials: Vec<_> = labels.iter().map(|l| l.material.as_deref()).collect();
+        assert_eq!(materials, [None, None, Some("stone"), None]);
+    }
+
+    #[test]
+    fn output_is_canonical() {
+        let dir = temp_dir("canonical");
+        let path = dir.join("scene.json");
+
+        // Deux écritures différentes de la même scène donnent le même fichier
+        let shuffled = r##"{
+            "spheres": [{ "radius": 0.5, "color": "#ff0000", "center": { "x": 0.0, "y": 1.0, "z": 0.0 } }],
+            "light": { "intensity": 1.0, "color": 1.0, "position": [2.0, 6.0, -4.0] },
+            "camera": { "look_at": [0.0, 0.0, 0.0], "origin": [0.0, 1.0, -4.0] }
+        }"##;
+        let ordered = r##"{
+            "camera": { "origin": [0.0, 1.0, -4.0], "look_at": [0.0, 0.0, 0.0], "fov": 90.0 },
+            "light": { "position": [2.0, 6.0, -4.0], "color": [1.0, 1.0, 1.0], "intensity": 1.0 },
+            "spheres": [{ "center": [0.0, 1.0, 0.0], "radius": 0.5, "color": [1.0, 0.0, 0.0] }]
+        }"##;
+        let write = |text: &str| {
+            let scene: Scene = serde_json::from_str(text).unwrap();
+            scene.to_file(&path).unwrap();
+            fs::read_to_string(&path).unwrap()
+        };
+        let text = write(shuffled);
+        assert_eq!(text, write(ordered));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Indenté, clés triées, valeurs par défaut explicites
+        assert!(text.starts_with("{\n  \"camera\": {\n"), "{}", text);
+        assert!(text.find("\"look_at\"").unwrap() < text.find("\"origin\"").unwrap());
+        assert!(text.contains("\"fov\": 90.0"), "{}", text);
+        assert!(text.ends_with("}\n"));
+    }
+
+    #[test]
+    fn plain_scene_errors_keep_their_position() {
+        let dir = temp_dir("read");
+        let path = dir.join("scene.yaml");
+        let text = "camera:\n  origin: [0, 1, -4]\n  look_at: [0, 0, 0]\n\
+                    light:\n  position: [2, 6, -4]\n  color: 1\n  intensity: one\n";
+        fs::write(&path, text.replace("one", "1")).unwrap();
+        let scene = Scene::read(&path).map(|scene| scene.light.intensity);
+        fs::write(&path, text).unwrap();
+        let error = Scene::read(&path).err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(scene.unwrap(), 1.0);
+        match error {
+            Some(Error::Parse { line, .. }) => assert_eq!(line, 7),
+            Some(other) => panic!("unexpected error: {}", other),
+            None => panic!("the scene should not be read"),
+        }
     }
 }
@@ -0,0 +1,181 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::vec3::{self, Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+// Bruit de Perlin à gradients aléatoires, déterministe pour une graine donnée
+#[derive(Clone)]
+pub struct Perlin {
+    ranvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let ranvec = (0..POINT_COUNT)
+            .map(|_| {
+                // Direction uniforme sur la sphère (rejet hors de la boule unité)
+                loop {
+                    let v = Vec3::new(
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                    );
+                    let len2 = v.length_squared();
+                    if len2 > 1e-6 && len2 <= 1.0 {
+                        break vec3::unit_vector(v);
+                    }
+                }
+            })
+            .collect();
+
+        Perlin {
+            ranvec,
+            perm_x: Self::generate_perm(&mut rng),
+            perm_y: Self::generate_perm(&mut rng),
+            perm_z: Self::generate_perm(&mut rng),
+        }
+    }
+
+    // Bruit dans [-1, 1] environ, continu et lisse
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.ranvec[self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize]];
+                }
+            }
+        }
+
+        Self::perlin_interp(&c, u, v, w)
+    }
+
+    // Somme d'octaves en valeur absolue : motifs « turbulents » dans [0, ~1]
+    pub fn turb(&self, p: &Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum.abs()
+    }
+
+    // Mouvement brownien fractionnaire : somme signée d'octaves
+    pub fn fbm(&self, p: &Point3, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut accum = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+
+        for _ in 0..octaves {
+            accum += amplitude * self.noise(&(*p * frequency));
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+
+        accum
+    }
+
+    fn generate_perm(rng: &mut StdRng) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = rng.gen_range(0..=i);
+            p.swap(i, target);
+        }
+        p
+    }
+
+    // Interpolation trilinéaire des gradients avec lissage d'Hermite
+    fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accum = 0.0;
+        for (i, plane) in c.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, corner) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight_v = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * vec3::dot(*corner, weight_v);
+                }
+            }
+        }
+
+        accum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Points répartis sur plusieurs cellules, négatives comprises
+    fn points() -> impl Iterator<Item = Point3> {
+        (0..2000).map(|i| {
+            let i = i as f64;
+            Point3::new(i * 0.37 - 300.0, i * 0.71 - 40.0, i * 1.13 + 7.0)
+        })
+    }
+
+    #[test]
+    fn same_seed_same_noise() {
+        let (a, b, other) = (Perlin::new(7), Perlin::new(7), Perlin::new(8));
+        let mut differs = false;
+        for p in points() {
+            assert_eq!(a.noise(&p), b.noise(&p));
+            assert_eq!(a.fbm(&p, 5, 2.0, 0.5), b.fbm(&p, 5, 2.0, 0.5));
+            differs |= a.noise(&p) != other.noise(&p);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        // Gradients unitaires : |bruit| <= sqrt(3) / 2
+        let bound = 3f64.sqrt() / 2.0;
+        let perlin = Perlin::new(0);
+        let (mut least, mut most) = (f64::INFINITY, -f64::INFINITY);
+        for p in points() {
+            let value = perlin.noise(&p);
+            assert!(value.abs() <= bound, "{}: {}", p, value);
+            least = least.min(value);
+            most = most.max(value);
+            assert!((0.0..=2.0 * bound).contains(&perlin.turb(&p, 7)));
+        }
+        assert!(least < -0.3 && most > 0.3, "[{}, {}]", least, most);
+    }
+
+    #[test]
+    fn lattice_points_are_zero() {
+        let perlin = Perlin::new(3);
+        for (x, y, z) in [(0, 0, 0), (1, 2, 3), (-5, 7, -11), (255, 256, -256)] {
+            let p = Point3::new(x as f64, y as f64, z as f64);
+            assert_eq!(perlin.noise(&p), 0.0);
+            assert_eq!(perlin.fbm(&p, 4, 2.0, 0.5), 0.0);
+        }
+    }
+}
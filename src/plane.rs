@@ -1,26 +1,30 @@
 use crate::{
-    color::Color,
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    material::Material,
     vec3::{self, Point3, Vec3},
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Plane {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub origine: Point3,
     pub width: i32,
     pub height: i32,
-    pub color: Color,
+    #[serde(flatten)]
+    pub material: Material,
 }
 
 impl Plane {
-    pub fn new(origine: Point3, width: i32, height: i32, color: Color) -> Self {
+    pub fn new(origine: Point3, width: i32, height: i32, material: Material) -> Self {
         Plane {
+            name: None,
             origine,
             width,
             height,
-            color,
+            material,
         }
     }
 }
@@ -62,7 +66,41 @@ impl Hittable for Plane {
         rec.t = t;
         rec.p = p_hit;
         rec.set_face_normal(ray, normal);
-        rec.color = self.color;
+        rec.u = local_hit.x() / self.width as f64;
+        rec.v = local_hit.z() / self.height as f64;
+        rec.dpdu = Vec3::new(self.width as f64, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, 0.0, self.height as f64);
         true
     }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uv_spans_the_plane() {
+        let plane = Plane::new(Point3::new(1.0, 0.0, 1.0), 4, 2, Material::default());
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let cases = [
+            (Point3::new(1.0, 0.0, 1.0), (0.0, 0.0)),
+            (Point3::new(2.0, 0.0, 1.5), (0.25, 0.25)),
+            (Point3::new(5.0, 0.0, 3.0), (1.0, 1.0)),
+        ];
+        for (point, (u, v)) in cases {
+            let mut rec = HitRecord::new();
+            let ray = Ray::new(point + Vec3::new(0.0, 2.0, 0.0), down);
+            assert!(plane.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+            assert_eq!((rec.u, rec.v), (u, v));
+            assert_eq!(rec.normal.y(), 1.0);
+        }
+
+        let mut rec = HitRecord::new();
+        let outside = Ray::new(Point3::new(5.5, 2.0, 2.0), down);
+        assert!(!plane.hit(&outside, 0.001, f64::INFINITY, &mut rec));
+    }
 }
@@ -0,0 +1,1221 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    aov::Aov,
+    background::Background,
+    camera::Camera,
+    color::Color,
+    common,
+    error::Error,
+    framebuffer::Framebuffer,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    light::Light,
+    matte::{self, Matte, MatteKind},
+    medium::HeightFog,
+    param::Scene,
+    ray::Ray,
+    sampler::{Sampler, SamplerKind},
+    vec3::{self, Vec3},
+};
+
+pub const DEFAULT_WIDTH: usize = 800;
+pub const DEFAULT_HEIGHT: usize = 600;
+pub const DEFAULT_SAMPLES_PER_PIXEL: u32 = 100;
+pub const DEFAULT_ADAPTIVE_THRESHOLD: f64 = 0.01;
+pub const DEFAULT_MIN_SAMPLES: u32 = 16;
+pub const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+// Éclairage minimal des zones à l'ombre quand la scène n'a pas de fond éclairant
+const AMBIENT: f64 = 0.4;
+
+// Suivi de l'avancement : (lignes terminées, nombre total de lignes), ou
+// (passes terminées, nombre de passes visé) en rendu progressif
+type Progress = Box<dyn Fn(usize, usize)>;
+
+// Réglages du rendu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: u32,
+    // Le fond est laissé noir et transparent (alpha 0), pour la composition
+    pub transparent_background: bool,
+    // Sans réglage adaptatif, chaque pixel reçoit `samples_per_pixel` échantillons
+    pub adaptive: Option<Adaptive>,
+    // Répartition des échantillons dans le pixel et des tirages de l'éclairage
+    pub sampler: SamplerKind,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            samples_per_pixel: DEFAULT_SAMPLES_PER_PIXEL,
+            transparent_background: false,
+            adaptive: None,
+            sampler: SamplerKind::default(),
+        }
+    }
+}
+
+// Échantillonnage adaptatif : un pixel n'est plus échantillonné dès que l'erreur type de
+// sa luminance moyenne passe sous `threshold` fois cette luminance (au moins 0,1, pour ne
+// pas s'acharner sur les pixels sombres). Chaque pixel reçoit entre `min_samples` et
+// `samples_per_pixel` échantillons.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adaptive {
+    pub threshold: f64,
+    pub min_samples: u32,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Adaptive {
+            threshold: DEFAULT_ADAPTIVE_THRESHOLD,
+            min_samples: DEFAULT_MIN_SAMPLES,
+        }
+    }
+}
+
+impl Adaptive {
+    // Le pixel a-t-il assez d'échantillons ?
+    pub fn converged(&self, framebuffer: &Framebuffer, x: usize, y: usize) -> bool {
+        framebuffer.samples(x, y) >= self.min_samples
+            && framebuffer.error(x, y) <= self.threshold * framebuffer.luminance(x, y).max(0.1)
+    }
+}
+
+// Rendu progressif : des passes d'un échantillon par pixel sur toute l'image, jusqu'à
+// `samples_per_pixel` passes ou à la limite de temps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progressive {
+    pub time_limit: Option<Duration>,
+    // Fréquence des mises à jour de l'image en cours
+    pub update: Update,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Update {
+    Passes(u32),        // Toutes les N passes
+    Interval(Duration), // Au plus une fois par intervalle
+}
+
+// Sans limite de temps, une mise à jour toutes les deux secondes
+impl Default for Progressive {
+    fn default() -> Self {
+        Progressive {
+            time_limit: None,
+            update: Update::Interval(Duration::from_secs(2)),
+        }
+    }
+}
+
+// Image rendue, passes auxiliaires et mattes demandées, dans l'ordre de la demande
+#[derive(Clone)]
+pub struct Frame {
+    pub beauty: Framebuffer,
+    pub aovs: Vec<(Aov, Framebuffer)>,
+    pub mattes: Vec<Matte>,
+}
+
+impl Frame {
+    // Deux rendus de même hauteur côte à côte, passes et mattes comprises
+    pub fn side_by_side(left: &Frame, right: &Frame) -> Frame {
+        Frame {
+            beauty: Framebuffer::side_by_side(&left.beauty, &right.beauty),
+            aovs: left
+                .aovs
+                .iter()
+                .zip(&right.aovs)
+                .map(|((aov, left), (_, right))| (*aov, Framebuffer::side_by_side(left, right)))
+                .collect(),
+            mattes: left
+                .mattes
+                .iter()
+                .zip(&right.mattes)
+                .map(|(left, right)| Matte::side_by_side(left, right))
+                .collect(),
+        }
+    }
+
+    // Ajoute une passe de même taille rendue à part. Les passes filtrées et les mattes
+    // cumulent leurs échantillons ; les autres gardent leur premier échantillon, sauf le
+    // nombre d'échantillons qui prend celui de la passe.
+    pub fn accumulate(&mut self, pass: &Frame) {
+        self.beauty.accumulate(&pass.beauty);
+        for ((aov, image), (_, pass)) in self.aovs.iter_mut().zip(&pass.aovs) {
+            if aov.filtered() {
+                image.accumulate(pass);
+                continue;
+            }
+            for y in 0..image.height() {
+                for x in 0..image.width() {
+                    let keep = image.samples(x, y) > 0 && *aov != Aov::Samples;
+                    if pass.samples(x, y) > 0 && !keep {
+                        image.set(x, y, pass.pixel(x, y));
+                    }
+                }
+            }
+        }
+        for (matte, pass) in self.mattes.iter_mut().zip(&pass.mattes) {
+            matte.accumulate(pass);
+        }
+    }
+
+    // Rectangle du rendu commençant en (x, y), limité aux bords de l'image
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Frame {
+        Frame {
+            beauty: self.beauty.crop(x, y, width, height),
+            aovs: self
+                .aovs
+                .iter()
+                .map(|(aov, pass)| (*aov, pass.crop(x, y, width, height)))
+                .collect(),
+            mattes: self
+                .mattes
+                .iter()
+                .map(|matte| matte.crop(x, y, width, height))
+                .collect(),
+        }
+    }
+
+    // Rendu rééchantillonné ; les passes non filtrées reprennent le pixel le plus proche
+    pub fn resize(&self, width: usize, height: usize) -> Frame {
+        Frame {
+            beauty: self.beauty.resize(width, height),
+            aovs: self
+                .aovs
+                .iter()
+                .map(|(aov, pass)| match aov.filtered() {
+                    true => (*aov, pass.resize(width, height)),
+                    false => (*aov, pass.resize_nearest(width, height)),
+                })
+                .collect(),
+            mattes: self
+                .mattes
+                .iter()
+                .map(|matte| matte.resize(width, height))
+                .collect(),
+        }
+    }
+}
+
+// Moteur de rendu prêt à l'emploi : un monde, une lumière, une caméra et des réglages.
+// Il se construit avec `Renderer::builder()`, à partir d'une scène ou d'objets créés par programme.
+pub struct Renderer {
+    world: HittableList,
+    light: Light,
+    camera: Camera,
+    background: Option<Background>,
+    fog: Option<HeightFog>,
+    settings: RenderSettings,
+    aovs: Vec<Aov>,
+    mattes: Vec<MatteKind>,
+    object_names: Vec<String>,
+    material_names: Vec<Option<String>>,
+    progress: Option<Progress>,
+}
+
+#[derive(Default)]
+pub struct RendererBuilder {
+    world: Option<HittableList>,
+    light: Option<Light>,
+    camera: Option<Camera>,
+    background: Option<Background>,
+    fog: Option<HeightFog>,
+    settings: RenderSettings,
+    aovs: Vec<Aov>,
+    mattes: Vec<MatteKind>,
+    object_names: Vec<String>,
+    material_names: Vec<Option<String>>,
+    progress: Option<Progress>,
+}
+
+impl RendererBuilder {
+    // Reprend les objets et leurs noms, la lumière, le fond, le brouillard, les passes et
+    // les mattes de la scène, ainsi que sa première caméra si aucune n'est donnée. Les images de la scène doivent déjà
+    // être chargées, ce que fait `Scene::from_file`.
+    pub fn scene(mut self, scene: &Scene) -> Self {
+        let mut world = HittableList::new();
+        let labels = world.set_scene(scene.clone());
+        self.world = Some(world);
+        self.light = Some(scene.light);
+        self.background = scene.background.clone();
+        self.fog = scene.fog;
+        self = self.aovs(&scene.aovs).mattes(&scene.mattes);
+        self.object_names = labels.iter().map(|l| l.name.clone()).collect();
+        self.material_names = labels.into_iter().map(|l| l.material).collect();
+        if self.camera.is_none() {
+            self.camera = scene.views().first().map(|(_, camera)| *camera);
+        }
+        self
+    }
+
+    pub fn world(mut self, world: HittableList) -> Self {
+        self.world = Some(world);
+        self
+    }
+
+    pub fn light(mut self, light: Light) -> Self {
+        self.light = Some(light);
+        self
+    }
+
+    // Seuls `origin`, `look_at` et `fov` sont utilisés ; le cadrage suit les proportions de l'image
+    pub fn camera(mut self, camera: Camera) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn fog(mut self, fog: HeightFog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    pub fn settings(mut self, settings: RenderSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn resolution(mut self, width: usize, height: usize) -> Self {
+        self.settings.width = width;
+        self.settings.height = height;
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
+        self.settings.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn transparent_background(mut self, transparent: bool) -> Self {
+        self.settings.transparent_background = transparent;
+        self
+    }
+
+    // Échantillonnage adaptatif, `samples_per_pixel` devenant le maximum par pixel
+    pub fn adaptive(mut self, adaptive: Adaptive) -> Self {
+        self.settings.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn sampler(mut self, sampler: SamplerKind) -> Self {
+        self.settings.sampler = sampler;
+        self
+    }
+
+    // Ajoute des passes auxiliaires, rendues par `Renderer::render_frame`
+    pub fn aovs(mut self, aovs: &[Aov]) -> Self {
+        for &aov in aovs {
+            if !self.aovs.contains(&aov) {
+                self.aovs.push(aov);
+            }
+        }
+        self
+    }
+
+    // Ajoute des mattes d'identifiants, rendues par `Renderer::render_frame`
+    pub fn mattes(mut self, mattes: &[MatteKind]) -> Self {
+        for &kind in mattes {
+            if !self.mattes.contains(&kind) {
+                self.mattes.push(kind);
+            }
+        }
+        self
+    }
+
+    // Nom de chaque objet du monde, dans l'ordre d'ajout ; par défaut `object<indice>`
+    pub fn object_names(mut self, names: Vec<String>) -> Self {
+        self.object_names = names;
+        self
+    }
+
+    // Matériau nommé de chaque objet du monde, dans l'ordre d'ajout
+    pub fn material_names(mut self, names: Vec<Option<String>>) -> Self {
+        self.material_names = names;
+        self
+    }
+
+    // Appelée après chaque ligne avec (lignes terminées, nombre total de lignes), ou après
+    // chaque passe du rendu progressif avec (passes terminées, nombre de passes visé)
+    pub fn progress(mut self, progress: impl Fn(usize, usize) + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    pub fn build(self) -> Result<Renderer, Error> {
+        let settings = self.settings;
+        if settings.width < 2 || settings.height < 2 {
+            return Err(Error::renderer(format!(
+                "resolution must be at least 2x2 (got {}x{})",
+                settings.width, settings.height
+            )));
+        }
+        if settings.samples_per_pixel == 0 {
+            return Err(Error::renderer("samples_per_pixel must be positive"));
+        }
+        if let Some(adaptive) = settings.adaptive {
+            // La variance demande au moins deux échantillons
+            if adaptive.min_samples < 2 {
+                return Err(Error::renderer("adaptive min_samples must be at least 2"));
+            }
+            if !(adaptive.threshold > 0.0 && adaptive.threshold.is_finite()) {
+                return Err(Error::renderer(format!(
+                    "adaptive threshold must be positive (got {})",
+                    adaptive.threshold
+                )));
+            }
+        }
+
+        // Une carte non chargée ne peut être ni affichée ni échantillonnée
+        if let Some(Background::Hdr(hdr)) = &self.background {
+            if hdr.map.is_none() {
+                return Err(Error::renderer(format!(
+                    "HDR environment \"{}\" is not loaded",
+                    hdr.file
+                )));
+            }
+        }
+
+        Ok(Renderer {
+            world: self.world.unwrap_or_default(),
+            light: self
+                .light
+                .ok_or_else(|| Error::renderer("no light given"))?,
+            camera: self
+                .camera
+                .ok_or_else(|| Error::renderer("no camera given"))?,
+            background: self.background,
+            fog: self.fog,
+            settings,
+            aovs: self.aovs,
+            mattes: self.mattes,
+            object_names: self.object_names,
+            material_names: self.material_names,
+            progress: self.progress,
+        })
+    }
+}
+
+impl Renderer {
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder::default()
+    }
+
+    pub fn settings(&self) -> RenderSettings {
+        self.settings
+    }
+
+    pub fn aovs(&self) -> &[Aov] {
+        &self.aovs
+    }
+
+    pub fn mattes(&self) -> &[MatteKind] {
+        &self.mattes
+    }
+
+    // Libellé de chaque objet du monde pour une sorte de matte
+    fn labels(&self, kind: MatteKind) -> Vec<Option<String>> {
+        (0..self.world.len())
+            .map(|i| match kind {
+                MatteKind::Object => Some(
+                    self.object_names
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| format!("object{}", i)),
+                ),
+                MatteKind::Material => self.material_names.get(i).cloned().flatten(),
+            })
+            .collect()
+    }
+
+    pub fn render(&self) -> Framebuffer {
+        self.render_from(&self.camera)
+    }
+
+    // Rendu depuis un autre point de vue, par exemple une autre caméra de la scène ou un œil
+    // d'un rig stéréo, sans reconstruire le monde
+    pub fn render_from(&self, camera: &Camera) -> Framebuffer {
+        self.render_frame(camera).beauty
+    }
+
+    // Rendu de l'image, des passes auxiliaires et des mattes depuis le point de vue donné
+    pub fn render_frame(&self, camera: &Camera) -> Frame {
+        let RenderSettings {
+            width,
+            height,
+            samples_per_pixel,
+            ..
+        } = self.settings;
+        let view = self.view(camera);
+        let mut sampler = self.sampler();
+        let mut frame = self.frame();
+        for row in 0..height {
+            for i in 0..width {
+                for _ in 0..samples_per_pixel {
+                    if self.converged(&frame, i, row) {
+                        break;
+                    }
+                    let sample = frame.beauty.samples(i, row);
+                    self.add_sample(&view, sampler.as_mut(), &mut frame, i, row, sample);
+                }
+            }
+            if let Some(progress) = &self.progress {
+                progress(row + 1, height);
+            }
+        }
+        frame
+    }
+
+    // Image vide aux dimensions du rendu, avec les passes et les mattes demandées
+    pub fn frame(&self) -> Frame {
+        let RenderSettings { width, height, .. } = self.settings;
+        Frame {
+            beauty: Framebuffer::new(width, height),
+            aovs: self
+                .aovs
+                .iter()
+                .map(|&aov| (aov, Framebuffer::new(width, height)))
+                .collect(),
+            mattes: self
+                .mattes
+                .iter()
+                .map(|&kind| Matte::new(kind, matte::ids(&self.labels(kind)).0, width, height))
+                .collect(),
+        }
+    }
+
+    // Ajoute à `frame` un échantillon par pixel, rendu depuis `camera`, sauf aux pixels
+    // que l'échantillonnage adaptatif juge terminés. Renvoie le nombre de pixels échantillonnés.
+    pub fn render_pass(&self, camera: &Camera, frame: &mut Frame) -> usize {
+        let (pass, sampled) = self.pass(&self.view(camera), self.sampler().as_mut(), frame);
+        frame.accumulate(&pass);
+        sampled
+    }
+
+    // Passe rendue à part, à ajouter à `frame` avec `Frame::accumulate` : chaque pixel
+    // que `frame` n'a pas terminé y reçoit l'échantillon qui suit ceux de `frame`
+    fn pass(&self, view: &View, sampler: &mut dyn Sampler, frame: &Frame) -> (Frame, usize) {
+        let mut pass = self.frame();
+        let mut sampled = 0;
+        for row in 0..self.settings.height {
+            for i in 0..self.settings.width {
+                if !self.converged(frame, i, row) {
+                    let sample = frame.beauty.samples(i, row);
+                    self.add_sample(view, sampler, &mut pass, i, row, sample);
+                    sampled += 1;
+                }
+            }
+        }
+        (pass, sampled)
+    }
+
+    // Les tirages ne dépendent que du pixel et du numéro de l'échantillon : un nouvel
+    // échantillonneur reprend la suite de chaque pixel là où elle en est
+    fn sampler(&self) -> Box<dyn Sampler> {
+        let settings = self.settings;
+        settings.sampler.sampler(settings.samples_per_pixel, 0)
+    }
+
+    // Le pixel a-t-il reçu assez d'échantillons pour l'échantillonnage adaptatif ?
+    fn converged(&self, frame: &Frame, i: usize, row: usize) -> bool {
+        self.settings
+            .adaptive
+            .is_some_and(|adaptive| adaptive.converged(&frame.beauty, i, row))
+    }
+
+    // Rendu progressif depuis une ou plusieurs caméras (les deux yeux d'un rig stéréo),
+    // affinées ensemble passe après passe. `update` reçoit les images en cours au rythme
+    // demandé, puis une dernière fois quand le rendu s'arrête : après `samples_per_pixel`
+    // passes, quand l'échantillonnage adaptatif a terminé tous les pixels, à la limite de
+    // temps, ou dès que `stop` passe à vrai.
+    pub fn render_progressive(
+        &self,
+        cameras: &[Camera],
+        progressive: &Progressive,
+        stop: &AtomicBool,
+        mut update: impl FnMut(&[Frame]) -> Result<(), Error>,
+    ) -> Result<Vec<Frame>, Error> {
+        let start = Instant::now();
+        let mut last_update = start;
+        let target = self.settings.samples_per_pixel;
+        let views: Vec<_> = cameras.iter().map(|camera| self.view(camera)).collect();
+        let mut sampler = self.sampler();
+        let mut frames: Vec<_> = cameras.iter().map(|_| self.frame()).collect();
+
+        for passes in 1..=target {
+            let mut sampled = 0;
+            for (view, frame) in views.iter().zip(&mut frames) {
+                let (pass, n) = self.pass(view, sampler.as_mut(), frame);
+                frame.accumulate(&pass);
+                sampled += n;
+            }
+            if let Some(progress) = &self.progress {
+                progress(passes as usize, target as usize);
+            }
+
+            let out_of_time = progressive
+                .time_limit
+                .is_some_and(|limit| start.elapsed() >= limit);
+            let done = passes == target || sampled == 0;
+            if done || out_of_time || stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let due = match progressive.update {
+                Update::Passes(n) => passes % n.max(1) == 0,
+                Update::Interval(interval) => last_update.elapsed() >= interval,
+            };
+            if due {
+                update(&frames)?;
+                last_update = Instant::now();
+            }
+        }
+        update(&frames)?;
+        Ok(frames)
+    }
+
+    // Caméra au format de l'image et identifiants des objets pour chaque matte
+    fn view(&self, camera: &Camera) -> View {
+        let aspect_ratio = self.settings.width as f64 / self.settings.height as f64;
+        View {
+            camera: Camera::new(camera.origin, camera.look_at, VUP, camera.fov, aspect_ratio),
+            forward: vec3::unit_vector(camera.look_at - camera.origin),
+            ids: self
+                .mattes
+                .iter()
+                .map(|&kind| matte::ids(&self.labels(kind)).1)
+                .collect(),
+        }
+    }
+
+    // Trace l'échantillon numéro `sample` du pixel (i, row) et l'ajoute à l'image, aux
+    // passes et aux mattes
+    fn add_sample(
+        &self,
+        view: &View,
+        sampler: &mut dyn Sampler,
+        frame: &mut Frame,
+        i: usize,
+        row: usize,
+        sample: u32,
+    ) {
+        let RenderSettings {
+            width,
+            height,
+            transparent_background,
+            ..
+        } = self.settings;
+        let background = self.background.as_ref();
+        let fog = self.fog.as_ref();
+
+        // Échantillon suivant du pixel ; ses deux premières dimensions le placent dans le pixel
+        sampler.start_pixel_sample(i, row, sample);
+        let (du, dv) = sampler.get_2d();
+        // La première ligne de l'image est en haut de l'écran (v = 1)
+        let j = height - 1 - row;
+        let u = (i as f64 + du) / (width - 1) as f64;
+        let v = (j as f64 + dv) / (height - 1) as f64;
+        let r = view.camera.get_ray(u, v);
+        let shading = trace(&r, &self.world, &self.light, background, fog, sampler);
+
+        // Alpha : couverture de l'échantillon, 1 s'il touche un objet
+        let (color, alpha) = match &shading {
+            Some(shading) => (shading.color, 1.0),
+            None if transparent_background => (Color::new(0.0, 0.0, 0.0), 0.0),
+            None => (miss_color(&r, &self.light, background, fog), 0.0),
+        };
+        frame.beauty.add_sample(i, row, color, alpha);
+
+        let samples = (sample + 1) as f32;
+        for (aov, pass) in &mut frame.aovs {
+            // Nombre d'échantillons reçus jusqu'ici par le pixel
+            if *aov == Aov::Samples {
+                pass.set(i, row, [samples, samples, samples, 1.0]);
+                continue;
+            }
+            let value = aov_value(*aov, &r, view.forward, shading.as_ref());
+            if aov.filtered() {
+                pass.add_sample(i, row, value, alpha);
+            } else if pass.samples(i, row) == 0 {
+                let [x, y, z] = [value.x(), value.y(), value.z()].map(|c| c as f32);
+                pass.set(i, row, [x, y, z, alpha as f32]);
+            }
+        }
+        for (matte, ids) in frame.mattes.iter_mut().zip(&view.ids) {
+            let id = shading.as_ref().and_then(|s| ids[s.rec.object]);
+            matte.add_sample(i, row, id);
+        }
+    }
+}
+
+// Point de vue prêt à tracer
+struct View {
+    camera: Camera,
+    forward: Vec3,                // Axe de visée, pour la profondeur
+    ids: Vec<Vec<Option<usize>>>, // Identifiant de chaque objet du monde, pour chaque matte
+}
+
+// Ce que voit un rayon qui touche un objet
+struct Shading {
+    rec: HitRecord,
+    direct: Color,   // Lumière reçue de la lumière ponctuelle
+    indirect: Color, // Lumière ambiante ou reçue du fond
+    shadowed: bool,
+    color: Color, // Couleur finale, brouillard compris
+}
+
+// Trace le rayon ; None s'il part vers le fond. Les tirages viennent de `sampler`.
+fn trace(
+    r: &Ray,
+    world: &dyn Hittable,
+    light: &Light,
+    background: Option<&Background>,
+    fog: Option<&HeightFog>,
+    sampler: &mut dyn Sampler,
+) -> Option<Shading> {
+    let mut rec = HitRecord::new();
+    if !world.hit(r, 0.001, common::INFINITY, &mut rec) {
+        return None;
+    }
+    world.shade(&mut rec);
+
+    // Le fond remplace l'éclairage ambiant forfaitaire
+    let ambient = if background.is_some() { 0.0 } else { AMBIENT };
+    let (direct, shadowed) = direct_lighting(&rec, world, light, ambient);
+    let mut indirect = if shadowed {
+        Color::new(ambient, ambient, ambient)
+    } else {
+        Color::new(0.0, 0.0, 0.0)
+    };
+    if let Some(background) = background {
+        indirect += environment_lighting(&rec, world, background, sampler.get_2d());
+    }
+
+    let color = rec.color * (direct + indirect);
+    let color = match fog {
+        Some(fog) => fog.apply(r, rec.t, color),
+        None => color,
+    };
+    Some(Shading {
+        rec,
+        direct,
+        indirect,
+        shadowed,
+        color,
+    })
+}
+
+// Valeur d'un échantillon pour une passe auxiliaire ; le fond est à 0, à l'infini pour la profondeur.
+// La profondeur est la distance le long de l'axe de visée `forward`, pas le long du rayon.
+fn aov_value(aov: Aov, r: &Ray, forward: Vec3, shading: Option<&Shading>) -> Color {
+    let Some(shading) = shading else {
+        let background = if aov == Aov::Depth {
+            common::INFINITY
+        } else {
+            0.0
+        };
+        return Color::new(background, background, background);
+    };
+    let rec = &shading.rec;
+    let scalar = |value: f64| Color::new(value, value, value);
+    match aov {
+        Aov::Depth => scalar(rec.t * vec3::dot(r.direction(), forward)),
+        Aov::Normal => rec.normal,
+        Aov::Albedo => rec.color,
+        Aov::ObjectId => scalar((rec.object + 1) as f64),
+        Aov::Uv => Color::new(rec.u, rec.v, 0.0),
+        Aov::Direct => rec.color * shading.direct,
+        Aov::Indirect => rec.color * shading.indirect,
+        Aov::Shadow => scalar(if shading.shadowed { 1.0 } else { 0.0 }),
+        Aov::Samples => unreachable!("the sample count is not traced"),
+    }
+}
+
+// Couleur du fond dans la direction du rayon
+fn miss_color(
+    r: &Ray,
+    light: &Light,
+    background: Option<&Background>,
+    fog: Option<&HeightFog>,
+) -> Color {
+    let color = match background {
+        Some(background) => background.radiance(r.direction()),
+        None => Color::new(0.5, 0.7, 1.0) * light.color * light.intensity,
+    };
+    match fog {
+        Some(fog) => fog.apply(r, common::INFINITY, color),
+        None => color,
+    }
+}
+
+// Estimation à un échantillon de la lumière diffuse reçue du fond
+fn environment_lighting(
+    rec: &HitRecord,
+    world: &dyn Hittable,
+    background: &Background,
+    u: (f64, f64),
+) -> Color {
+    // Volume : fonction de phase isotrope 1 / 4pi, surface : lambertien cos / pi
+    let (direction, pdf, weight) = if rec.isotropic {
+        let (direction, pdf) = background.sample_sphere(u);
+        (direction, pdf, 1.0 / (4.0 * common::PI))
+    } else {
+        let (direction, pdf) = background.sample(rec.normal, u);
+        let cos_theta = vec3::dot(rec.normal, direction);
+        (direction, pdf, cos_theta / common::PI)
+    };
+    if weight <= 0.0 || pdf <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    let mut temp_rec = HitRecord::new();
+    if world.hit(
+        &Ray::new(rec.p, direction),
+        0.001,
+        common::INFINITY,
+        &mut temp_rec,
+    ) {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    background.radiance(direction) * (weight / pdf)
+}
+
+// Lumière reçue de la lumière ponctuelle, et si le point est à l'ombre
+fn direct_lighting(
+    rec: &HitRecord,
+    world: &dyn Hittable,
+    light: &Light,
+    ambient: f64,
+) -> (Color, bool) {
+    let (point, normal) = (&rec.p, &rec.normal);
+    let light_dir = vec3::unit_vector(light.position - *point); // Direction vers la lumière
+    let shadow_ray = Ray::new(*point, light_dir); // Rayon vers la lumière
+
+    // Vérifier s'il y a un obstacle entre le point et la lumière
+    let mut temp_rec = HitRecord::new();
+    if world.hit(&shadow_ray, 0.001, common::INFINITY, &mut temp_rec) {
+        return (Color::new(0.0, 0.0, 0.0), true); // Ombre complète (pas de lumière)
+    }
+
+    // Calcul de l'éclairage diffus (sans orientation dans un volume)
+    let diff = if rec.isotropic {
+        1.0
+    } else {
+        vec3::dot(*normal, light_dir).max(ambient)
+    };
+    (diff * light.color * light.intensity, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        background::HdrEnvironment, cube::Cube, material::Material, sphere::Sphere,
+        texture::TextureConfig, vec3::Point3,
+    };
+
+    fn red_ball() -> RendererBuilder {
+        let mut world = HittableList::new();
+        let material = Material::from(TextureConfig::Solid(Color::new(1.0, 0.0, 0.0)));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            material,
+        )));
+
+        Renderer::builder()
+            .world(world)
+            .light(Light::new(
+                Point3::new(0.0, 0.0, 3.0),
+                Color::new(1.0, 1.0, 1.0),
+                1.0,
+            ))
+            .camera(Camera::new(
+                Point3::new(0.0, 0.0, 3.0),
+                Point3::new(0.0, 0.0, 0.0),
+                VUP,
+                60.0,
+                1.0,
+            ))
+            .resolution(9, 7)
+            .samples_per_pixel(4)
+    }
+
+    #[test]
+    fn renders_a_programmatic_world() {
+        let framebuffer = red_ball().build().unwrap().render();
+        assert_eq!((framebuffer.width(), framebuffer.height()), (9, 7));
+        assert_eq!(framebuffer.samples(8, 6), 4);
+
+        // La sphère rouge au centre, le ciel par défaut dans les coins
+        let center = framebuffer.color(4, 3);
+        assert!(center.x() > 0.5 && center.y() < 1e-9 && center.z() < 1e-9);
+        let corner = framebuffer.color(0, 0);
+        assert!(corner.z() > corner.x());
+    }
+
+    #[test]
+    fn depth_is_measured_along_the_view_axis() {
+        // Un mur face à la caméra, à 4 unités : même profondeur partout, même dans les coins
+        let mut world = HittableList::new();
+        world.add(Box::new(Cube::new(
+            Point3::new(-10.0, -10.0, -11.0),
+            Point3::new(10.0, 10.0, -1.0),
+            Material::default(),
+        )));
+        let renderer = red_ball().world(world).aovs(&[Aov::Depth]).build().unwrap();
+        let frame = renderer.render_frame(&renderer.camera);
+        let depth = &frame.aovs[0].1;
+        for (x, y) in [(4, 3), (0, 0), (8, 6), (0, 3)] {
+            assert!(
+                (depth.pixel(x, y)[0] - 4.0).abs() < 1e-5,
+                "{:?}",
+                depth.pixel(x, y)
+            );
+        }
+    }
+
+    #[test]
+    fn alpha_is_the_coverage_of_the_pixel() {
+        let opaque = red_ball().samples_per_pixel(64).build().unwrap().render();
+        let transparent = red_ball()
+            .samples_per_pixel(64)
+            .transparent_background(true)
+            .build()
+            .unwrap()
+            .render();
+
+        for framebuffer in [&opaque, &transparent] {
+            assert_eq!(framebuffer.alpha(4, 3), 1.0);
+            assert_eq!(framebuffer.alpha(0, 0), 0.0);
+            // Les pixels de la silhouette sont en partie couverts
+            let partial = framebuffer
+                .pixels()
+                .filter(|pixel| pixel[3] > 0.0 && pixel[3] < 1.0)
+                .count();
+            assert!(partial > 0);
+        }
+
+        // Le fond opaque garde sa couleur, le fond transparent est noir
+        assert!(opaque.color(0, 0).z() > 0.5);
+        assert_eq!(transparent.pixel(0, 0), [0.0; 4]);
+    }
+
+    #[test]
+    fn aovs_are_rendered_with_the_image() {
+        let renderer = red_ball()
+            .aovs(&Aov::ALL)
+            .aovs(&[Aov::Depth])
+            .build()
+            .unwrap();
+        assert_eq!(renderer.aovs(), Aov::ALL);
+        let frame = renderer.render_frame(&renderer.camera);
+        let pass = |aov: Aov| &frame.aovs.iter().find(|(a, _)| *a == aov).unwrap().1;
+
+        // Au centre : la sphère à 2 unités, face à la caméra et à la lumière. La profondeur
+        // vient d'un seul échantillon, n'importe où dans le pixel.
+        let depth = pass(Aov::Depth).pixel(4, 3)[0];
+        assert!((2.0..2.25).contains(&depth), "depth {}", depth);
+        assert!(pass(Aov::Normal).color(4, 3).z() > 0.5);
+        assert_eq!(pass(Aov::Albedo).pixel(4, 3), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pass(Aov::ObjectId).pixel(4, 3)[0], 1.0);
+        assert_eq!(pass(Aov::Shadow).pixel(4, 3)[0], 0.0);
+        assert!(pass(Aov::Direct).color(4, 3).x() > 0.5);
+        assert_eq!(pass(Aov::Indirect).pixel(4, 3), [0.0, 0.0, 0.0, 1.0]);
+
+        // Dans un coin : le fond
+        assert_eq!(pass(Aov::Depth).pixel(0, 0)[0], f32::INFINITY);
+        assert_eq!(pass(Aov::ObjectId).pixel(0, 0), [0.0; 4]);
+        assert_eq!(pass(Aov::Albedo).pixel(0, 0), [0.0; 4]);
+
+        // L'image est la somme des deux contributions
+        let beauty = frame.beauty.color(4, 3);
+        let sum = pass(Aov::Direct).color(4, 3) + pass(Aov::Indirect).color(4, 3);
+        assert!((beauty - sum).length() < 1e-6);
+    }
+
+    #[test]
+    fn mattes_count_the_samples_of_each_object() {
+        let renderer = red_ball()
+            .samples_per_pixel(16)
+            .mattes(&[MatteKind::Object, MatteKind::Material])
+            .build()
+            .unwrap();
+        let frame = renderer.render_frame(&renderer.camera);
+        let [objects, materials] = [&frame.mattes[0], &frame.mattes[1]];
+
+        // Sans noms donnés, l'objet prend son indice ; sans matériau nommé, pas d'identifiant
+        assert_eq!(objects.names(), ["object0"]);
+        assert!(materials.names().is_empty());
+        assert_eq!(objects.coverage(4, 3), [(0, 1.0)]);
+        assert_eq!(objects.coverage(0, 0), []);
+
+        // La couverture de la silhouette suit l'alpha de l'image
+        for y in 0..7 {
+            for x in 0..9 {
+                let coverage: f32 = objects.coverage(x, y).iter().map(|(_, c)| c).sum();
+                assert!((coverage as f64 - frame.beauty.alpha(x, y)).abs() < 1e-6);
+            }
+        }
+
+        let named = red_ball()
+            .object_names(vec!["ball".into()])
+            .material_names(vec![Some("red".into())])
+            .mattes(&[MatteKind::Material, MatteKind::Object])
+            .build()
+            .unwrap();
+        let frame = named.render_frame(&named.camera);
+        assert_eq!(frame.mattes[0].names(), ["red"]);
+        assert_eq!(frame.mattes[1].names(), ["ball"]);
+    }
+
+    #[test]
+    fn nested_lists_shade_the_object_hit() {
+        let ball = |center: Point3, color: Color| -> Box<dyn Hittable> {
+            let material = Material::from(TextureConfig::Solid(color));
+            Box::new(Sphere::new(center, 1.0, material))
+        };
+        // Boule rouge au centre, deuxième objet d'une liste qui est le premier du monde
+        let mut inner = HittableList::new();
+        inner.add(ball(
+            Point3::new(0.0, 0.0, -50.0),
+            Color::new(0.0, 1.0, 0.0),
+        ));
+        inner.add(ball(Point3::new(0.0, 0.0, 0.0), Color::new(1.0, 0.0, 0.0)));
+        let mut world = HittableList::new();
+        world.add(Box::new(inner));
+        world.add(ball(
+            Point3::new(0.0, 0.0, -60.0),
+            Color::new(0.0, 0.0, 1.0),
+        ));
+
+        let frame = red_ball()
+            .world(world)
+            .aovs(&[Aov::ObjectId])
+            .build()
+            .unwrap()
+            .render_frame(&Camera::new(
+                Point3::new(0.0, 0.0, 3.0),
+                Point3::new(0.0, 0.0, 0.0),
+                VUP,
+                60.0,
+                1.0,
+            ));
+        let color = frame.beauty.color(4, 3);
+        assert!(
+            color.x() > 0.0 && color.y() == 0.0 && color.z() == 0.0,
+            "{:?}",
+            color
+        );
+        // Les passes et les mattes désignent l'objet du monde
+        assert_eq!(frame.aovs[0].1.pixel(4, 3)[0], 1.0);
+    }
+
+    #[test]
+    fn passes_add_one_sample_per_pixel() {
+        let renderer = red_ball().aovs(&[Aov::Depth]).build().unwrap();
+        let mut frame = renderer.frame();
+        renderer.render_pass(&renderer.camera, &mut frame);
+        renderer.render_pass(&renderer.camera, &mut frame);
+        assert_eq!(frame.beauty.samples(0, 0), 2);
+        assert_eq!(frame.beauty.alpha(4, 3), 1.0);
+        // La profondeur garde le premier échantillon
+        assert_eq!(frame.aovs[0].1.samples(4, 3), 1);
+    }
+
+    #[test]
+    fn accumulated_passes_match_a_full_render() {
+        let renderer = red_ball()
+            .aovs(&[Aov::Normal, Aov::Depth, Aov::Samples])
+            .mattes(&[MatteKind::Object])
+            .build()
+            .unwrap();
+        let full = renderer.render_frame(&renderer.camera);
+        let mut frame = renderer.frame();
+        for _ in 0..4 {
+            renderer.render_pass(&renderer.camera, &mut frame);
+        }
+        assert!(frame.beauty == full.beauty);
+        assert!(frame.aovs == full.aovs);
+        assert!(frame.mattes == full.mattes);
+    }
+
+    #[test]
+    fn progressive_rendering_stops_at_the_first_limit() {
+        let renderer = red_ball().samples_per_pixel(6).build().unwrap();
+        let cameras = [renderer.camera; 2];
+        let run = |progressive: Progressive, stop: bool| {
+            let mut updates = Vec::new();
+            let frames = renderer
+                .render_progressive(&cameras, &progressive, &AtomicBool::new(stop), |frames| {
+                    updates.push(frames[1].beauty.samples(0, 0));
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(frames.len(), 2);
+            assert_eq!(updates.last(), Some(&frames[0].beauty.samples(0, 0)));
+            updates
+        };
+
+        // Toutes les 2 passes, puis l'image finale
+        let every_two = Progressive {
+            time_limit: None,
+            update: Update::Passes(2),
+        };
+        assert_eq!(run(every_two, false), [2, 4, 6]);
+
+        // Arrêt demandé ou temps écoulé : l'image en cours est rendue
+        assert_eq!(run(every_two, true), [1]);
+        let no_time = Progressive {
+            time_limit: Some(Duration::ZERO),
+            ..every_two
+        };
+        assert_eq!(run(no_time, false), [1]);
+    }
+
+    #[test]
+    fn adaptive_sampling_spends_samples_on_noisy_pixels() {
+        let adaptive = Adaptive {
+            threshold: 0.01,
+            min_samples: 4,
+        };
+        let renderer = red_ball()
+            .samples_per_pixel(64)
+            .adaptive(adaptive)
+            .aovs(&[Aov::Samples])
+            .build()
+            .unwrap();
+        let frame = renderer.render_frame(&renderer.camera);
+        let samples = &frame.aovs[0].1;
+
+        // Le ciel uni s'arrête au minimum, la silhouette va jusqu'au maximum
+        assert_eq!(frame.beauty.samples(0, 0), 4);
+        let edges = (0..7)
+            .flat_map(|y| (0..9).map(move |x| (x, y)))
+            .filter(|&(x, y)| frame.beauty.samples(x, y) == 64)
+            .count();
+        assert!(edges > 0);
+        for y in 0..7 {
+            for x in 0..9 {
+                let count = frame.beauty.samples(x, y) as f32;
+                assert_eq!(samples.pixel(x, y), [count, count, count, 1.0]);
+                assert!(count == 64.0 || adaptive.converged(&frame.beauty, x, y));
+            }
+        }
+
+        // En progressif, le rendu s'arrête une passe après que tous les pixels sont
+        // terminés : ici tout le ciel au bout de 4 passes
+        let sky = Camera::new(
+            Point3::new(0.0, 0.0, 3.0),
+            Point3::new(0.0, 5.0, 3.0),
+            VUP,
+            30.0,
+            1.0,
+        );
+        let every_pass = Progressive {
+            time_limit: None,
+            update: Update::Passes(1),
+        };
+        let mut updates = Vec::new();
+        renderer
+            .render_progressive(&[sky], &every_pass, &AtomicBool::new(false), |frames| {
+                updates.push(frames[0].beauty.samples(0, 0));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(updates, [1, 2, 3, 4, 4]);
+    }
+
+    #[test]
+    fn samplers_lower_the_noise_of_the_silhouette() {
+        // Sans fond, seule la position dans le pixel est tirée : l'alpha des pixels de la
+        // silhouette mesure l'erreur, comparée à un rendu de référence très échantillonné
+        let alpha = |sampler: SamplerKind, samples: u32| {
+            let framebuffer = red_ball()
+                .sampler(sampler)
+                .samples_per_pixel(samples)
+                .build()
+                .unwrap()
+                .render();
+            framebuffer
+                .pixels()
+                .map(|pixel| pixel[3])
+                .collect::<Vec<_>>()
+        };
+        let reference = alpha(SamplerKind::Sobol, 4096);
+        let error = |sampler: SamplerKind| {
+            let squares: f32 = alpha(sampler, 16)
+                .iter()
+                .zip(&reference)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum();
+            squares.sqrt()
+        };
+        let independent = error(SamplerKind::Independent);
+        for sampler in &SamplerKind::ALL[1..] {
+            let error = error(*sampler);
+            assert!(
+                error < 0.7 * independent,
+                "{}: {} against {}",
+                sampler.name(),
+                error,
+                independent
+            );
+        }
+    }
+
+    #[test]
+    fn missing_camera_or_invalid_settings_are_errors() {
+        let error = |builder: RendererBuilder| builder.build().err().map(|e| e.to_string());
+        assert_eq!(
+            error(Renderer::builder().light(Light::new(
+                Point3::new(0.0, 1.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+                1.0
+            ))),
+            Some("renderer: no camera given".to_string())
+        );
+        assert_eq!(
+            error(red_ball().samples_per_pixel(0)),
+            Some("renderer: samples_per_pixel must be positive".to_string())
+        );
+        assert_eq!(
+            error(red_ball().resolution(1, 100)),
+            Some("renderer: resolution must be at least 2x2 (got 1x100)".to_string())
+        );
+        let adaptive = |threshold, min_samples| {
+            error(red_ball().adaptive(Adaptive {
+                threshold,
+                min_samples,
+            }))
+        };
+        assert_eq!(
+            adaptive(0.01, 1),
+            Some("renderer: adaptive min_samples must be at least 2".to_string())
+        );
+        assert_eq!(
+            adaptive(0.0, 16),
+            Some("renderer: adaptive threshold must be positive (got 0)".to_string())
+        );
+        let environment = Background::Hdr(HdrEnvironment {
+            file: "sky.hdr".to_string(),
+            intensity: 1.0,
+            rotation: 0.0,
+            map: Default::default(),
+        });
+        assert_eq!(
+            error(red_ball().background(environment)),
+            Some("renderer: HDR environment \"sky.hdr\" is not loaded".to_string())
+        );
+    }
+}
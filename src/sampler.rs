@@ -0,0 +1,638 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+// Fournit les nombres aléatoires d'un échantillon de pixel, dimension après dimension :
+// la position dans le pixel, puis chaque tirage de l'intégrateur (lumière, objectif,
+// BSDF...). Les échantillons d'un même pixel sont répartis ensemble dans chaque
+// dimension au lieu d'être tirés indépendamment, ce qui réduit le bruit à nombre égal.
+// Les valeurs ne dépendent que du pixel, du numéro de l'échantillon et de la dimension :
+// un rendu progressif ou adaptatif reprend la suite là où le pixel en est.
+pub trait Sampler {
+    // Commence l'échantillon numéro `index` du pixel (x, y), à la première dimension
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u32);
+
+    // Dimension suivante, dans [0, 1)
+    fn get_1d(&mut self) -> f64;
+
+    // Deux dimensions suivantes, réparties ensemble dans [0, 1)²
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+// Sorte d'échantillonneur
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerKind {
+    // Tirages indépendants, la référence
+    Independent,
+    // Grille de strates, un tirage au hasard dans chacune
+    Stratified,
+    // Suite de Halton décalée au hasard pour chaque pixel
+    Halton,
+    // Suite de Sobol brouillée par permutations d'Owen
+    #[default]
+    Sobol,
+    // Suite de Sobol brouillée, décalée par un masque de bruit bleu
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub const ALL: [SamplerKind; 5] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+        SamplerKind::BlueNoise,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+            SamplerKind::BlueNoise => "blue_noise",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SamplerKind> {
+        SamplerKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+
+    // Échantillonneur pour `samples_per_pixel` échantillons par pixel ; `seed` change
+    // les tirages sans changer leur répartition
+    pub fn sampler(self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(Independent::new(seed)),
+            SamplerKind::Stratified => Box::new(Stratified::new(samples_per_pixel, seed)),
+            SamplerKind::Halton => Box::new(Halton::new(seed)),
+            SamplerKind::Sobol => Box::new(Sobol::new(seed)),
+            SamplerKind::BlueNoise => Box::new(BlueNoise::new(seed)),
+        }
+    }
+}
+
+// Échantillon en cours : pixel, numéro et prochaine dimension
+#[derive(Clone, Copy, Debug, Default)]
+struct PixelSample {
+    seed: u64,
+    x: u64,
+    y: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl PixelSample {
+    fn new(seed: u64) -> Self {
+        PixelSample {
+            seed,
+            ..PixelSample::default()
+        }
+    }
+
+    fn start(&mut self, x: usize, y: usize, index: u32) {
+        *self = PixelSample {
+            x: x as u64,
+            y: y as u64,
+            index,
+            dimension: 0,
+            ..*self
+        };
+    }
+
+    // Clé des `count` dimensions suivantes, la même pour tous les échantillons du pixel
+    fn next(&mut self, count: u64) -> u64 {
+        let key = hash(&[self.seed, self.x, self.y, self.dimension]);
+        self.dimension += count;
+        key
+    }
+
+    // Clé des `count` dimensions suivantes, la même pour tous les pixels
+    fn next_shared(&mut self, count: u64) -> u64 {
+        let key = hash(&[self.seed, self.dimension]);
+        self.dimension += count;
+        key
+    }
+}
+
+pub struct Independent {
+    sample: PixelSample,
+}
+
+impl Independent {
+    pub fn new(seed: u64) -> Self {
+        Independent {
+            sample: PixelSample::new(seed),
+        }
+    }
+}
+
+impl Sampler for Independent {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u32) {
+        self.sample.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let key = self.sample.next(1);
+        unit(hash(&[key, self.sample.index as u64]))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+// Une strate par échantillon : des intervalles égaux en 1D, une grille d'environ
+// sqrt(n) x sqrt(n) cases en 2D. L'ordre des strates est mélangé pour chaque pixel et
+// chaque dimension ; au-delà de `samples_per_pixel` échantillons, elles sont reparcourues.
+pub struct Stratified {
+    samples_per_pixel: u32,
+    sample: PixelSample,
+}
+
+impl Stratified {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        Stratified {
+            samples_per_pixel: samples_per_pixel.max(1),
+            sample: PixelSample::new(seed),
+        }
+    }
+
+    // Strate de l'échantillon parmi `strata`, et deux nombres pour s'y placer
+    fn stratum(&mut self, strata: u32) -> (u32, u64) {
+        let key = self.sample.next(1);
+        let index = self.sample.index;
+        let stratum = permute(index % strata, strata, key as u32);
+        (stratum, hash(&[key, index as u64]))
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u32) {
+        self.sample.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let n = self.samples_per_pixel;
+        let (stratum, jitter) = self.stratum(n);
+        (stratum as f64 + unit(jitter)) / n as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let columns = (self.samples_per_pixel as f64).sqrt() as u32;
+        let rows = self.samples_per_pixel / columns;
+        let (cell, jitter) = self.stratum(columns * rows);
+        let (x, y) = (cell % columns, cell / columns);
+        (
+            (x as f64 + unit(jitter)) / columns as f64,
+            (y as f64 + unit(mix(jitter))) / rows as f64,
+        )
+    }
+}
+
+// Bases des dimensions de la suite de Halton ; les suivantes sont tirées au hasard
+const PRIMES: [u32; 16] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+
+// Suite de Halton, décalée (modulo 1) d'une valeur propre à chaque pixel et à chaque
+// dimension pour que les pixels voisins ne reçoivent pas les mêmes points
+pub struct Halton {
+    sample: PixelSample,
+}
+
+impl Halton {
+    pub fn new(seed: u64) -> Self {
+        Halton {
+            sample: PixelSample::new(seed),
+        }
+    }
+}
+
+impl Sampler for Halton {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u32) {
+        self.sample.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.sample.dimension as usize;
+        let key = self.sample.next(1);
+        let index = self.sample.index;
+        match PRIMES.get(dimension) {
+            Some(&base) => (radical_inverse(base, index) + unit(key)).fract(),
+            None => unit(hash(&[key, index as u64])),
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+// Nombre dont les chiffres en base `base` sont ceux de `index`, lus à l'envers après la virgule
+fn radical_inverse(base: u32, mut index: u32) -> f64 {
+    let (mut value, mut scale) = (0.0, 1.0);
+    while index > 0 {
+        scale /= base as f64;
+        value += (index % base) as f64 * scale;
+        index /= base;
+    }
+    value
+}
+
+// Les deux premières dimensions de la suite de Sobol, reprises pour chaque paire de
+// dimensions demandée. Chaque paire a son propre brouillage d'Owen des valeurs et son
+// propre mélange des numéros d'échantillons (Burley, 2020), ce qui décorrèle les paires
+// tout en gardant leurs bonnes propriétés : les 2^k premiers points d'un pixel ont
+// exactement un point dans chaque rectangle dyadique d'aire 2^-k.
+pub struct Sobol {
+    sample: PixelSample,
+}
+
+impl Sobol {
+    pub fn new(seed: u64) -> Self {
+        Sobol {
+            sample: PixelSample::new(seed),
+        }
+    }
+}
+
+impl Sampler for Sobol {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u32) {
+        self.sample.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let key = self.sample.next(1);
+        scrambled_sobol(self.sample.index, key).0
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let key = self.sample.next(2);
+        scrambled_sobol(self.sample.index, key)
+    }
+}
+
+// Point `index` des deux premières dimensions de Sobol, numéro et valeurs brouillés d'après `key`
+fn scrambled_sobol(index: u32, key: u64) -> (f64, f64) {
+    let index = owen_scramble(index, key as u32);
+    let seeds = mix(key);
+    (
+        unit32(owen_scramble(sobol(index, 0), seeds as u32)),
+        unit32(owen_scramble(sobol(index, 1), (seeds >> 32) as u32)),
+    )
+}
+
+// Matrices génératrices des deux premières dimensions de Sobol, une colonne par bit du
+// numéro : la suite de van der Corput, puis le polynôme primitif x + 1
+const SOBOL_MATRICES: [[u32; 32]; 2] = sobol_matrices();
+
+const fn sobol_matrices() -> [[u32; 32]; 2] {
+    let mut matrices = [[0; 32]; 2];
+    let mut bit = 0;
+    while bit < 32 {
+        matrices[0][bit] = 1 << (31 - bit);
+        matrices[1][bit] = if bit == 0 {
+            1 << 31
+        } else {
+            matrices[1][bit - 1] ^ (matrices[1][bit - 1] >> 1)
+        };
+        bit += 1;
+    }
+    matrices
+}
+
+// Point `index` de la dimension `dimension` de la suite de Sobol, en virgule fixe sur 32 bits
+fn sobol(index: u32, dimension: usize) -> u32 {
+    (0..32)
+        .filter(|bit| index >> bit & 1 == 1)
+        .fold(0, |value, bit| value ^ SOBOL_MATRICES[dimension][bit])
+}
+
+// Brouillage d'Owen : chaque chiffre binaire est inversé ou non selon les chiffres qui le
+// précèdent. Les bits sont retournés pour que la permutation de Laine et Karras, où chaque
+// bit ne dépend que des bits de poids plus faible, parcoure les chiffres dans ce sens.
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut x = value.reverse_bits();
+    x ^= x.wrapping_mul(0x3d20_adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x0552_6c56);
+    x ^= x.wrapping_mul(0x53a2_2864);
+    x.reverse_bits()
+}
+
+// Côté du masque de bruit bleu, qui se répète sur l'image
+pub const BLUE_NOISE_SIZE: usize = 64;
+
+// Les mêmes points de Sobol brouillés dans tous les pixels, décalés (modulo 1) d'une
+// valeur lue dans un masque de bruit bleu (Georgiev et Fajardo, 2016). Chaque pixel
+// garde la bonne répartition de Sobol, et l'erreur de pixels voisins est décorrélée :
+// le bruit restant, sans basses fréquences, paraît plus fin à l'œil. Chaque dimension
+// lit le masque à une position différente.
+pub struct BlueNoise {
+    sample: PixelSample,
+}
+
+impl BlueNoise {
+    pub fn new(seed: u64) -> Self {
+        BlueNoise {
+            sample: PixelSample::new(seed),
+        }
+    }
+
+    // Valeur du masque sous le pixel, le masque étant décalé d'après `key`
+    fn mask(&self, key: u64) -> f64 {
+        let size = BLUE_NOISE_SIZE as u64;
+        let x = (self.sample.x + key % size) % size;
+        let y = (self.sample.y + (key >> 32) % size) % size;
+        blue_noise()[(y * size + x) as usize]
+    }
+}
+
+impl Sampler for BlueNoise {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u32) {
+        self.sample.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let key = self.sample.next_shared(1);
+        (scrambled_sobol(self.sample.index, key).0 + self.mask(key)).fract()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let key = self.sample.next_shared(2);
+        let (u, v) = scrambled_sobol(self.sample.index, key);
+        (
+            (u + self.mask(key)).fract(),
+            (v + self.mask(mix(key))).fract(),
+        )
+    }
+}
+
+// Masque de bruit bleu : une valeur dans [0, 1) par pixel, chacune une seule fois, les
+// valeurs proches étant éloignées les unes des autres. Calculé au premier usage.
+pub fn blue_noise() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(|| void_and_cluster(BLUE_NOISE_SIZE, 0))
+}
+
+// Méthode « void and cluster » d'Ulichney (1993) sur une image torique. Un motif de
+// points réparti au mieux est classé en retirant ses points les plus serrés, puis
+// complété en remplissant les plus grands vides : l'ordre de remplissage donne le masque.
+fn void_and_cluster(size: usize, seed: u64) -> Vec<f64> {
+    const SIGMA: f64 = 1.5;
+    let n = size * size;
+
+    // Influence d'un point sur un autre selon leur écart, l'image se refermant sur elle-même
+    let kernel: Vec<f64> = (0..n)
+        .map(|i| {
+            let wrap = |d: usize| d.min(size - d) as f64;
+            let (dx, dy) = (wrap(i % size), wrap(i / size));
+            (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp()
+        })
+        .collect();
+    let offset = |p: usize, q: usize| {
+        let dx = (p % size + size - q % size) % size;
+        let dy = (p / size + size - q / size) % size;
+        dy * size + dx
+    };
+
+    struct Pattern {
+        points: Vec<bool>,
+        energy: Vec<f64>, // Densité des points autour de chaque pixel
+    }
+    let flip = |pattern: &mut Pattern, q: usize| {
+        let sign = if pattern.points[q] { -1.0 } else { 1.0 };
+        pattern.points[q] = !pattern.points[q];
+        for (p, energy) in pattern.energy.iter_mut().enumerate() {
+            *energy += sign * kernel[offset(p, q)];
+        }
+    };
+    // Point le plus serré, ou plus grand vide
+    let tightest = |pattern: &Pattern| {
+        (0..n)
+            .filter(|&p| pattern.points[p])
+            .max_by(|&a, &b| pattern.energy[a].total_cmp(&pattern.energy[b]))
+    };
+    let emptiest = |pattern: &Pattern| {
+        (0..n)
+            .filter(|&p| !pattern.points[p])
+            .min_by(|&a, &b| pattern.energy[a].total_cmp(&pattern.energy[b]))
+    };
+
+    // Un dixième des pixels au hasard, puis déplacés du plus serré vers le plus grand
+    // vide jusqu'à ce que le motif ne bouge plus
+    let mut initial = Pattern {
+        points: vec![false; n],
+        energy: vec![0.0; n],
+    };
+    let mut placed = 0;
+    let mut draw = seed;
+    while placed < n / 10 {
+        draw += 1;
+        let p = (hash(&[seed, draw]) % n as u64) as usize;
+        if !initial.points[p] {
+            flip(&mut initial, p);
+            placed += 1;
+        }
+    }
+    for _ in 0..n {
+        let cluster = tightest(&initial).expect("points were placed");
+        flip(&mut initial, cluster);
+        let void = emptiest(&initial).expect("a void remains");
+        flip(&mut initial, void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; n];
+    let mut pattern = Pattern {
+        points: initial.points.clone(),
+        energy: initial.energy.clone(),
+    };
+    for rank in (0..placed).rev() {
+        let cluster = tightest(&pattern).expect("points remain");
+        flip(&mut pattern, cluster);
+        ranks[cluster] = rank;
+    }
+    let mut pattern = initial;
+    for rank in placed..n {
+        let void = emptiest(&pattern).expect("pixels remain");
+        flip(&mut pattern, void);
+        ranks[void] = rank;
+    }
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f64 + 0.5) / n as f64)
+        .collect()
+}
+
+// Permutation pseudo-aléatoire de [0, n) choisie par `seed` (Kensler, 2013) : le numéro
+// est mélangé sur la plus petite puissance de deux qui contient n, jusqu'à tomber dans [0, n)
+fn permute(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return (i + seed) % n;
+        }
+    }
+}
+
+// Finaliseur de SplitMix64 : mélange les bits d'un entier
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Entier pseudo-aléatoire tiré d'une suite d'entiers
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0, |h, &v| mix(h ^ v.wrapping_add(0x9e37_79b9_7f4a_7c15)))
+}
+
+// Nombre dans [0, 1) tiré des 53 bits de poids fort
+fn unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn unit32(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(kind: SamplerKind, samples: u32, x: usize, y: usize) -> Vec<(f64, f64)> {
+        let mut sampler = kind.sampler(samples, 7);
+        (0..samples)
+            .map(|index| {
+                sampler.start_pixel_sample(x, y, index);
+                sampler.get_1d();
+                sampler.get_2d()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for kind in SamplerKind::ALL {
+            assert_eq!(SamplerKind::from_name(kind.name()), Some(kind));
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::json!(kind.name())
+            );
+        }
+        assert_eq!(SamplerKind::from_name("random"), None);
+    }
+
+    #[test]
+    fn permutations_are_bijections() {
+        for n in [1, 2, 5, 16, 100] {
+            let mut seen: Vec<_> = (0..n).map(|i| permute(i, n, 0x1234_5678)).collect();
+            seen.sort();
+            assert_eq!(seen, (0..n).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn sobol_prefixes_are_nets() {
+        assert_eq!(sobol(2, 1), 3 << 30);
+
+        // 16 points : un seul dans chaque rectangle dyadique d'aire 1/16, de 1x16 à 16x1
+        let points = points(SamplerKind::Sobol, 16, 3, 5);
+        for k in 0..=4 {
+            let (columns, rows) = (1 << k, 1 << (4 - k));
+            let mut cells: Vec<_> = points
+                .iter()
+                .map(|(u, v)| (u * columns as f64) as usize + columns * (v * rows as f64) as usize)
+                .collect();
+            cells.sort();
+            assert_eq!(cells, (0..16).collect::<Vec<_>>(), "{}x{}", columns, rows);
+        }
+    }
+
+    #[test]
+    fn stratified_samples_fill_every_cell() {
+        let mut cells: Vec<_> = points(SamplerKind::Stratified, 16, 1, 2)
+            .iter()
+            .map(|(u, v)| (u * 4.0) as usize + 4 * (v * 4.0) as usize)
+            .collect();
+        cells.sort();
+        assert_eq!(cells, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn blue_noise_spreads_close_values_apart() {
+        let mask = blue_noise();
+        let mut sorted = mask.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+        assert!(sorted
+            .iter()
+            .enumerate()
+            .all(|(i, &v)| v == (i as f64 + 0.5) / n as f64));
+
+        // Des voisins indépendants différeraient de 1/3 en moyenne
+        let difference = (0..n)
+            .map(|i| (mask[i] - mask[(i + 1) % n]).abs())
+            .sum::<f64>()
+            / n as f64;
+        assert!(difference > 0.4, "mean difference {}", difference);
+    }
+
+    #[test]
+    fn well_distributed_samples_have_less_error() {
+        // Part d'un pixel couverte par un disque : l'erreur moyenne sur 256 pixels à
+        // 16 échantillons, comparée à celle des tirages indépendants
+        let area = std::f64::consts::PI * 0.5 / 4.0;
+        let error = |kind: SamplerKind| {
+            let squares: f64 = (0..256)
+                .map(|pixel| {
+                    let points = points(kind, 16, pixel % 16, pixel / 16);
+                    let inside = points.iter().filter(|(u, v)| u * u + v * v < 0.5).count();
+                    (inside as f64 / 16.0 - area).powi(2)
+                })
+                .sum();
+            (squares / 256.0).sqrt()
+        };
+        let independent = error(SamplerKind::Independent);
+        for kind in &SamplerKind::ALL[1..] {
+            let error = error(*kind);
+            assert!(
+                error < 0.6 * independent,
+                "{}: {} against {}",
+                kind.name(),
+                error,
+                independent
+            );
+        }
+    }
+}
@@ -0,0 +1,538 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{self, Point3, Vec3},
+};
+
+// Arbre de fonctions de distance signée : primitives, opérateurs et transformations.
+// Les distances sont négatives à l'intérieur des formes.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SdfNode {
+    Sphere {
+        #[serde(default)]
+        center: Point3,
+        radius: f64,
+    },
+    Box {
+        #[serde(default)]
+        center: Point3,
+        half_size: Vec3,
+    },
+    RoundBox {
+        #[serde(default)]
+        center: Point3,
+        half_size: Vec3,
+        radius: f64, // Rayon des arrêtes arrondies
+    },
+    // Tore dans le plan XZ
+    Torus {
+        #[serde(default)]
+        center: Point3,
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    Capsule {
+        a: Point3,
+        b: Point3,
+        radius: f64,
+    },
+    // Fractale de Mandelbulb (estimateur de distance)
+    Mandelbulb {
+        #[serde(default)]
+        center: Point3,
+        #[serde(default = "default_one")]
+        scale: f64,
+        #[serde(default = "default_power")]
+        power: f64,
+        #[serde(default = "default_iterations")]
+        iterations: u32,
+    },
+    Union {
+        children: Vec<SdfNode>,
+    },
+    Intersection {
+        children: Vec<SdfNode>,
+    },
+    // `base` privé de `subtract`
+    Subtraction {
+        base: Box<SdfNode>,
+        subtract: Box<SdfNode>,
+    },
+    // Union lissée sur une largeur k
+    SmoothUnion {
+        children: Vec<SdfNode>,
+        k: f64,
+    },
+    SmoothSubtraction {
+        base: Box<SdfNode>,
+        subtract: Box<SdfNode>,
+        k: f64,
+    },
+    Translate {
+        offset: Vec3,
+        child: Box<SdfNode>,
+    },
+    Scale {
+        factor: f64,
+        child: Box<SdfNode>,
+    },
+    // Répétition infinie ; une période nulle désactive la répétition sur cet axe
+    Repeat {
+        period: Vec3,
+        child: Box<SdfNode>,
+    },
+    // Torsion autour de l'axe Y, en radians par unité de hauteur
+    Twist {
+        amount: f64,
+        child: Box<SdfNode>,
+    },
+}
+
+fn default_one() -> f64 {
+    1.0
+}
+
+fn default_power() -> f64 {
+    8.0
+}
+
+fn default_iterations() -> u32 {
+    10
+}
+
+impl SdfNode {
+    pub fn distance(&self, p: Point3) -> f64 {
+        match self {
+            SdfNode::Sphere { center, radius } => (p - *center).length() - radius,
+            SdfNode::Box { center, half_size } => round_box(p - *center, *half_size, 0.0),
+            SdfNode::RoundBox {
+                center,
+                half_size,
+                radius,
+            } => round_box(p - *center, *half_size, *radius),
+            SdfNode::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let q = p - *center;
+                let ring = f64::sqrt(q.x() * q.x() + q.z() * q.z()) - major_radius;
+                f64::sqrt(ring * ring + q.y() * q.y()) - minor_radius
+            }
+            SdfNode::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = common::clamp(vec3::dot(pa, ba) / ba.length_squared(), 0.0, 1.0);
+                (pa - ba * h).length() - radius
+            }
+            SdfNode::Mandelbulb {
+                center,
+                scale,
+                power,
+                iterations,
+            } => mandelbulb((p - *center) / *scale, *power, *iterations) * scale,
+            SdfNode::Union { children } => children
+                .iter()
+                .map(|c| c.distance(p))
+                .fold(common::INFINITY, f64::min),
+            SdfNode::Intersection { children } => children
+                .iter()
+                .map(|c| c.distance(p))
+                .fold(-common::INFINITY, f64::max),
+            SdfNode::Subtraction { base, subtract } => base.distance(p).max(-subtract.distance(p)),
+            SdfNode::SmoothUnion { children, k } => children
+                .iter()
+                .map(|c| c.distance(p))
+                .reduce(|a, b| smooth_min(a, b, *k))
+                .unwrap_or(common::INFINITY),
+            SdfNode::SmoothSubtraction { base, subtract, k } => {
+                let d1 = subtract.distance(p);
+                let d2 = base.distance(p);
+                let h = common::clamp(0.5 - 0.5 * (d2 + d1) / k, 0.0, 1.0);
+                mix(d2, -d1, h) + k * h * (1.0 - h)
+            }
+            SdfNode::Translate { offset, child } => child.distance(p - *offset),
+            SdfNode::Scale { factor, child } => child.distance(p / *factor) * factor,
+            SdfNode::Repeat { period, child } => {
+                let wrap = |x: f64, c: f64| if c > 0.0 { x - c * (x / c).round() } else { x };
+                child.distance(Vec3::new(
+                    wrap(p.x(), period.x()),
+                    wrap(p.y(), period.y()),
+                    wrap(p.z(), period.z()),
+                ))
+            }
+            SdfNode::Twist { amount, child } => {
+                let (s, c) = (amount * p.y()).sin_cos();
+                child.distance(Vec3::new(
+                    c * p.x() - s * p.z(),
+                    p.y(),
+                    s * p.x() + c * p.z(),
+                ))
+            }
+        }
+    }
+}
+
+fn mix(a: f64, b: f64, t: f64) -> f64 {
+    a * (1.0 - t) + b * t
+}
+
+// Minimum polynomial lissé (Inigo Quilez)
+fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = common::clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+    mix(b, a, h) - k * h * (1.0 - h)
+}
+
+fn round_box(p: Vec3, half_size: Vec3, radius: f64) -> f64 {
+    let q = Vec3::new(
+        p.x().abs() - half_size.x() + radius,
+        p.y().abs() - half_size.y() + radius,
+        p.z().abs() - half_size.z() + radius,
+    );
+    let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+    let inside = q.x().max(q.y().max(q.z())).min(0.0);
+    outside + inside - radius
+}
+
+fn mandelbulb(p: Point3, power: f64, iterations: u32) -> f64 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = 0.0;
+
+    for _ in 0..iterations {
+        r = z.length();
+        if r > 2.0 {
+            break;
+        }
+        if r == 0.0 {
+            return -1.0;
+        }
+
+        let theta = f64::acos(z.z() / r) * power;
+        let phi = f64::atan2(z.y(), z.x()) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+        let zr = r.powf(power);
+        z =
+            zr * Vec3::new(
+                theta.sin() * phi.cos(),
+                phi.sin() * theta.sin(),
+                theta.cos(),
+            ) + p;
+    }
+
+    0.5 * r.ln() * r / dr
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3,
+    pub radius: f64,
+}
+
+// Objet défini par un arbre SDF, intersecté par lancer de sphères (sphere tracing)
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Sdf {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub root: SdfNode,
+    #[serde(flatten)]
+    pub material: Material,
+    #[serde(default = "default_max_steps")]
+    pub max_steps: u32,
+    #[serde(default = "default_epsilon")]
+    pub epsilon: f64, // Distance sous laquelle la surface est considérée atteinte
+    #[serde(default = "default_max_distance")]
+    pub max_distance: f64,
+    // Fraction du pas de sécurité, < 1 pour les opérateurs qui déforment les distances
+    #[serde(default = "default_one")]
+    pub step_scale: f64,
+    // Sphère englobante optionnelle qui limite la marche
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<BoundingSphere>,
+}
+
+fn default_max_steps() -> u32 {
+    256
+}
+
+fn default_epsilon() -> f64 {
+    1e-4
+}
+
+fn default_max_distance() -> f64 {
+    100.0
+}
+
+impl Sdf {
+    // Gradient par différences finies sur un tétraèdre (4 évaluations)
+    fn normal(&self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let k = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ];
+        let gradient = k.iter().fold(Vec3::default(), |acc, k| {
+            acc + *k * self.root.distance(p + h * *k)
+        });
+        if gradient.length_squared() == 0.0 {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        vec3::unit_vector(gradient)
+    }
+
+    // Intervalle [t0, t1] du rayon à l'intérieur de la sphère englobante
+    fn bounded_interval(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let Some(bounds) = self.bounds else {
+            return Some((t_min, t_max));
+        };
+
+        let oc = r.origin() - bounds.center;
+        let a = r.direction().length_squared();
+        let half_b = vec3::dot(oc, r.direction());
+        let c = oc.length_squared() - bounds.radius * bounds.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrt_d = discriminant.sqrt();
+        let t0 = ((-half_b - sqrt_d) / a).max(t_min);
+        let t1 = ((-half_b + sqrt_d) / a).min(t_max);
+        (t0 < t1).then_some((t0, t1))
+    }
+}
+
+impl Hittable for Sdf {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let length = r.direction().length();
+        if length == 0.0 {
+            return false;
+        }
+        let t_max = t_max.min(self.max_distance / length);
+        let Some((t_start, t_end)) = self.bounded_interval(r, t_min, t_max) else {
+            return false;
+        };
+
+        // Départ à l'intérieur : on cherche la surface de sortie
+        let mut t = t_start;
+        let start_distance = self.root.distance(r.at(t));
+        let sign = if start_distance < 0.0 { -1.0 } else { 1.0 };
+        // Départ sur la surface (rayon d'ombre) : il faut d'abord s'en éloigner
+        let mut leaving_surface = start_distance.abs() < self.epsilon;
+
+        let mut hit = false;
+        for _ in 0..self.max_steps {
+            if t > t_end {
+                break;
+            }
+            let d = sign * self.root.distance(r.at(t));
+            if leaving_surface {
+                if d.abs() >= self.epsilon {
+                    leaving_surface = false;
+                } else {
+                    t += self.epsilon / length;
+                    continue;
+                }
+            }
+            if d < self.epsilon {
+                hit = true;
+                break;
+            }
+            t += d * self.step_scale / length;
+        }
+
+        if !hit || t < t_min || t > t_end {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = self.normal(rec.p);
+        rec.set_face_normal(r, outward_normal);
+        rec.set_arbitrary_tangent_frame(outward_normal);
+        true
+    }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use serde_json::json;
+
+    fn hit(object: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        object
+            .hit(
+                &Ray::new(origin, direction),
+                0.001,
+                common::INFINITY,
+                &mut rec,
+            )
+            .then_some(rec)
+    }
+
+    #[test]
+    fn sphere_matches_the_analytic_sphere() {
+        let (center, radius) = (Point3::new(0.5, -0.25, 1.0), 1.2);
+        let sdf: Sdf = serde_json::from_value(json!({
+            "root": { "type": "sphere", "center": [0.5, -0.25, 1.0], "radius": radius },
+            "color": 1.0
+        }))
+        .unwrap();
+        let sphere = Sphere::new(center, radius, Material::default());
+
+        let mut hits = 0;
+        for i in 0..=20 {
+            for j in 0..=20 {
+                // Rayons parallèles et obliques depuis l'extérieur, puis depuis l'intérieur
+                let offset = Vec3::new(i as f64 * 0.15 - 1.5, j as f64 * 0.15 - 1.5, 0.0);
+                for (origin, direction) in [
+                    (
+                        center + offset - Vec3::new(0.0, 0.0, 5.0),
+                        Vec3::new(0.0, 0.0, 1.0),
+                    ),
+                    (
+                        center + offset - Vec3::new(3.0, 0.0, 4.0),
+                        Vec3::new(0.6, 0.0, 0.8),
+                    ),
+                    (center + 0.5 * offset, offset + Vec3::new(0.0, 0.0, 0.3)),
+                ] {
+                    // Rayons rasants : le résultat dépend de la tolérance
+                    let closest = vec3::cross(center - origin, vec3::unit_vector(direction));
+                    if (closest.length() - radius).abs() < 1e-3 {
+                        continue;
+                    }
+
+                    let expected = hit(&sphere, origin, direction);
+                    let found = hit(&sdf, origin, direction);
+                    assert_eq!(
+                        found.is_some(),
+                        expected.is_some(),
+                        "{} {}",
+                        origin,
+                        direction
+                    );
+                    let (Some(found), Some(expected)) = (found, expected) else {
+                        continue;
+                    };
+                    hits += 1;
+                    assert!(
+                        (found.t - expected.t).abs() < 1e-3,
+                        "{} != {}",
+                        found.t,
+                        expected.t
+                    );
+                    assert_eq!(found.front_face, expected.front_face);
+                    assert!((found.normal - expected.normal).length() < 1e-3);
+                    // Repère tangent orthonormé autour de la normale
+                    assert!(vec3::dot(found.dpdu, found.normal).abs() < 1e-9);
+                    assert!(vec3::dot(found.dpdv, found.normal).abs() < 1e-9);
+                    assert!((found.dpdu.length() - 1.0).abs() < 1e-9);
+                }
+            }
+        }
+        assert!(hits > 500, "{}", hits);
+    }
+
+    fn node(value: serde_json::Value) -> SdfNode {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn assert_distance(node: &SdfNode, p: Point3, expected: f64) {
+        let d = node.distance(p);
+        assert!((d - expected).abs() < 1e-12, "{}: {} != {}", p, d, expected);
+    }
+
+    #[test]
+    fn smooth_union_blends_near_both_shapes() {
+        let union = node(json!({ "type": "smooth_union", "k": 0.4, "children": [
+            { "type": "sphere", "center": [-1.0, 0.0, 0.0], "radius": 1.0 },
+            { "type": "sphere", "center": [1.0, 0.0, 0.0], "radius": 1.0 }
+        ] }));
+        // À égale distance des deux sphères : min(0, 0) - k / 4
+        assert_distance(&union, Point3::new(0.0, 0.0, 0.0), -0.1);
+        // a = 0,1, b = -0,1 : h = 0,25, mix = -0,05, moins k h (1 - h) = 0,075
+        assert_distance(&union, Point3::new(0.1, 0.0, 0.0), -0.125);
+        // Loin de la seconde sphère : distance à la première seule
+        assert_distance(&union, Point3::new(-3.0, 0.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn smooth_subtraction_rounds_the_cut() {
+        let subtraction = node(json!({ "type": "smooth_subtraction", "k": 0.4,
+            "base": { "type": "sphere", "center": [0.0, 0.0, 0.0], "radius": 1.0 },
+            "subtract": { "type": "sphere", "center": [1.0, 0.0, 0.0], "radius": 1.0 }
+        }));
+        // Base -0,5, retirée 0,5 : h = 0,5, mix = -0,5, plus k / 4
+        assert_distance(&subtraction, Point3::new(-0.5, 0.0, 0.0), -0.4);
+        // Loin de la sphère retirée : distance à la base seule
+        assert_distance(&subtraction, Point3::new(-0.9, 0.0, 0.0), -0.1);
+        // Au centre de la sphère retirée : à 1 de sa surface
+        assert_distance(&subtraction, Point3::new(1.0, 0.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn repeat_wraps_each_axis_with_a_period() {
+        let repeat = node(json!({ "type": "repeat", "period": [2.0, 0.0, 2.0],
+            "child": { "type": "sphere", "radius": 0.5 }
+        }));
+        // (4,3 ; -1,9) est à (0,3 ; 0,1) de la copie centrée en (4, 0, -2)
+        assert_distance(&repeat, Point3::new(4.3, 0.0, -1.9), 0.1f64.sqrt() - 0.5);
+        // Période nulle : pas de répétition en Y
+        assert_distance(&repeat, Point3::new(0.0, 3.0, 0.0), 2.5);
+    }
+
+    #[test]
+    fn twist_turns_with_the_height() {
+        let twist = node(json!({ "type": "twist", "amount": common::PI / 2.0,
+            "child": { "type": "box", "half_size": [1.0, 2.0, 0.2] }
+        }));
+        // En y = 0 la boîte est intacte : mince en Z
+        assert_distance(&twist, Point3::new(0.9, 0.0, 0.1), -0.1);
+        assert_distance(&twist, Point3::new(0.1, 0.0, 0.9), 0.7);
+        // En y = 1 elle a tourné d'un quart de tour : mince en X
+        assert_distance(&twist, Point3::new(0.1, 1.0, 0.9), -0.1);
+        assert_distance(&twist, Point3::new(0.9, 1.0, 0.1), 0.7);
+    }
+
+    #[test]
+    fn repeated_copies_are_hit_away_from_the_origin() {
+        let sdf: Sdf = serde_json::from_value(json!({
+            "root": { "type": "repeat", "period": [3.0, 0.0, 3.0],
+                "child": { "type": "sphere", "radius": 0.5 } },
+            "color": 1.0
+        }))
+        .unwrap();
+        let copy = Sphere::new(Point3::new(6.0, 0.0, -3.0), 0.5, Material::default());
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let origin = Point3::new(6.2, 5.0, -3.0);
+        let found = hit(&sdf, origin, down).unwrap();
+        let expected = hit(&copy, origin, down).unwrap();
+        assert!(
+            (found.t - expected.t).abs() < 1e-3,
+            "{} != {}",
+            found.t,
+            expected.t
+        );
+        assert!((found.normal - expected.normal).length() < 1e-3);
+
+        // Entre deux copies
+        assert!(hit(&sdf, Point3::new(7.5, 5.0, -3.0), down).is_none());
+    }
+}
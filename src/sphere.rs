@@ -1,24 +1,37 @@
-use crate::color::Color;
+use crate::common::PI;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
-use crate::vec3::{self, Point3};
+use crate::material::Material;
+use crate::vec3::{self, Point3, Vec3};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Sphere {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub center: Point3,
     pub radius: f64,
-    pub color: Color,
+    #[serde(flatten)]
+    pub material: Material,
 }
 
 impl Sphere {
-    pub fn new(cen: Point3, r: f64, color: Color) -> Sphere {
+    pub fn new(cen: Point3, r: f64, material: Material) -> Sphere {
         Sphere {
+            name: None,
             center: cen,
             radius: r,
-            color,
+            material,
         }
     }
+
+    // Coordonnées (u, v) d'un point de la sphère unité centrée en 0
+    // u : angle autour de l'axe Y depuis X = -1, v : angle depuis Y = -1
+    fn get_sphere_uv(p: Point3) -> (f64, f64) {
+        let theta = f64::acos(-p.y());
+        let phi = f64::atan2(-p.z(), p.x()) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
@@ -47,7 +60,57 @@ impl Hittable for Sphere {
         rec.p = r.at(rec.t);
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
-        rec.color = self.color;
+        (rec.u, rec.v) = Self::get_sphere_uv(outward_normal);
+
+        // Dérivées de p(u, v) pour u = phi / 2pi et v = theta / pi
+        let n = outward_normal;
+        let rho = f64::sqrt(n.x() * n.x() + n.z() * n.z());
+        rec.dpdu = 2.0 * PI * self.radius * Vec3::new(n.z(), 0.0, -n.x());
+        rec.dpdv = if rho > 0.0 {
+            PI * self.radius * Vec3::new(-n.x() * n.y() / rho, rho, -n.y() * n.z() / rho)
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        };
+
         true
     }
+
+    fn shade(&self, rec: &mut HitRecord) {
+        self.material.shade(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (u, v) du point de la sphère unité touché en venant de `origin` vers le centre
+    fn uv(origin: Point3) -> (f64, f64) {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, Material::default());
+        let mut rec = HitRecord::new();
+        let ray = Ray::new(origin, -origin);
+        assert!(sphere.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        (rec.u, rec.v)
+    }
+
+    fn assert_uv(origin: Point3, expected: (f64, f64)) {
+        let (u, v) = uv(origin);
+        assert!(
+            (u - expected.0).abs() < 1e-12 && (v - expected.1).abs() < 1e-12,
+            "{}: ({}, {}) != {:?}",
+            origin,
+            u,
+            v,
+            expected
+        );
+    }
+
+    #[test]
+    fn uv_at_known_points() {
+        assert_uv(Point3::new(3.0, 0.0, 0.0), (0.5, 0.5));
+        assert_uv(Point3::new(0.0, 0.0, 3.0), (0.25, 0.5));
+        assert_uv(Point3::new(0.0, 0.0, -3.0), (0.75, 0.5));
+        assert_eq!(uv(Point3::new(0.0, 3.0, 0.0)).1, 1.0);
+        assert_eq!(uv(Point3::new(0.0, -3.0, 0.0)).1, 0.0);
+    }
 }
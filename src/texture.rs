@@ -0,0 +1,576 @@
+use std::{
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use serde::{
+    de::{
+        self, value::MapAccessDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{
+    color::{self, Color},
+    common::{self, Cache},
+    error::Error,
+    image::Image,
+    perlin::Perlin,
+    vec3::{Point3, Vec3Visitor},
+};
+
+// Couleur d'une surface évaluée aux coordonnées (u, v) et au point p
+pub trait Texture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+// Valeur acceptée partout où une couleur est attendue dans la scène :
+// soit une couleur simple, soit une texture décrite par son `type`
+#[derive(Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum TextureConfig {
+    Solid(Color),
+    Pattern(Pattern),
+}
+
+// Choix explicite entre couleur et texture, pour garder le message d'erreur de l'une ou de
+// l'autre au lieu de l'erreur générique d'un enum `untagged`. Une table est une couleur si sa
+// première clé est une composante (x, y, z, r, g, b ou e), une texture sinon.
+impl<'de> Deserialize<'de> for TextureConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TextureVisitor)
+    }
+}
+
+struct TextureVisitor;
+
+const COLOR: Vec3Visitor = Vec3Visitor { color: true };
+
+impl<'de> Visitor<'de> for TextureVisitor {
+    type Value = TextureConfig;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a color or a texture with a `type`")
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<TextureConfig, E> {
+        COLOR.visit_f64(value).map(TextureConfig::Solid)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<TextureConfig, E> {
+        COLOR.visit_i64(value).map(TextureConfig::Solid)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<TextureConfig, E> {
+        COLOR.visit_u64(value).map(TextureConfig::Solid)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<TextureConfig, E> {
+        COLOR.visit_str(value).map(TextureConfig::Solid)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<TextureConfig, A::Error> {
+        COLOR.visit_seq(seq).map(TextureConfig::Solid)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TextureConfig, A::Error> {
+        let first = map.next_key::<String>()?;
+        let is_color = match first.as_deref() {
+            None | Some("x" | "y" | "z" | "r" | "g" | "b" | "e") => true,
+            Some(_) => false,
+        };
+        let map = Replay { first, map };
+        if is_color {
+            COLOR.visit_map(map).map(TextureConfig::Solid)
+        } else {
+            Pattern::deserialize(MapAccessDeserializer::new(map)).map(TextureConfig::Pattern)
+        }
+    }
+}
+
+// Rend d'abord la clé déjà lue par `TextureVisitor`, puis le reste de la table
+struct Replay<A> {
+    first: Option<String>,
+    map: A,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Replay<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        match self.first.take() {
+            Some(key) => seed.deserialize(key.into_deserializer()).map(Some),
+            None => self.map.next_key_seed(seed),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(seed)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Pattern {
+    Solid(SolidColor),
+    Checker(CheckerTexture),
+    UvChecker(UvCheckerTexture),
+    Image(ImageTexture),
+    Fbm(FbmTexture),
+    Turbulence(TurbulenceTexture),
+    Marble(MarbleTexture),
+    Wood(WoodTexture),
+}
+
+impl TextureConfig {
+    // Charge les images référencées, chemins relatifs au dossier de la scène
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        match self {
+            TextureConfig::Solid(_) => Ok(()),
+            TextureConfig::Pattern(pattern) => match pattern {
+                Pattern::Solid(_) => Ok(()),
+                Pattern::Checker(checker) => {
+                    checker.even.load(base_dir)?;
+                    checker.odd.load(base_dir)
+                }
+                Pattern::UvChecker(checker) => {
+                    checker.even.load(base_dir)?;
+                    checker.odd.load(base_dir)
+                }
+                Pattern::Image(image) => image.load(base_dir),
+                Pattern::Fbm(noise) => {
+                    noise.low.load(base_dir)?;
+                    noise.high.load(base_dir)
+                }
+                Pattern::Turbulence(noise) => {
+                    noise.low.load(base_dir)?;
+                    noise.high.load(base_dir)
+                }
+                Pattern::Marble(marble) => {
+                    marble.low.load(base_dir)?;
+                    marble.high.load(base_dir)
+                }
+                Pattern::Wood(wood) => {
+                    wood.low.load(base_dir)?;
+                    wood.high.load(base_dir)
+                }
+            },
+        }
+    }
+}
+
+impl Default for TextureConfig {
+    fn default() -> Self {
+        TextureConfig::Solid(Color::new(1.0, 1.0, 1.0))
+    }
+}
+
+impl From<Color> for TextureConfig {
+    fn from(color: Color) -> Self {
+        TextureConfig::Solid(color)
+    }
+}
+
+impl Texture for TextureConfig {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        match self {
+            TextureConfig::Solid(color) => *color,
+            TextureConfig::Pattern(pattern) => match pattern {
+                Pattern::Solid(solid) => solid.value(u, v, p),
+                Pattern::Checker(checker) => checker.value(u, v, p),
+                Pattern::UvChecker(checker) => checker.value(u, v, p),
+                Pattern::Image(image) => image.value(u, v, p),
+                Pattern::Fbm(noise) => noise.value(u, v, p),
+                Pattern::Turbulence(noise) => noise.value(u, v, p),
+                Pattern::Marble(marble) => marble.value(u, v, p),
+                Pattern::Wood(wood) => wood.value(u, v, p),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SolidColor {
+    #[serde(deserialize_with = "color::deserialize")]
+    pub color: Color,
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.color
+    }
+}
+
+// Damier 3D : alterne selon la position dans l'espace, indépendamment des UV
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct CheckerTexture {
+    pub even: Box<TextureConfig>,
+    pub odd: Box<TextureConfig>,
+    pub scale: f64, // Taille d'une case
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let cell = |x: f64| (x / self.scale).floor() as i64;
+        if (cell(p.x()) + cell(p.y()) + cell(p.z())).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// Damier dans l'espace (u, v) de la surface
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct UvCheckerTexture {
+    pub even: Box<TextureConfig>,
+    pub odd: Box<TextureConfig>,
+    pub width: f64,  // Nombre de cases le long de u
+    pub height: f64, // Nombre de cases le long de v
+}
+
+impl Texture for UvCheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let i = (u * self.width).floor() as i64;
+        let j = (v * self.height).floor() as i64;
+        if (i + j).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// Texture lue depuis un fichier PPM ou PNG
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImageTexture {
+    pub file: String,
+    #[serde(skip)]
+    pub image: Cache<Option<Arc<Image>>>,
+}
+
+impl ImageTexture {
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), Error> {
+        let image = if self.file.starts_with("data:") {
+            Image::from_data_uri(&self.file)?
+        } else {
+            Image::load(&base_dir.join(&self.file))?
+        };
+        *self.image = Some(Arc::new(image));
+        Ok(())
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // Magenta si l'image n'a pas été chargée, pour repérer l'erreur au rendu
+        let Some(image) = &*self.image else {
+            return Color::new(1.0, 0.0, 1.0);
+        };
+        if image.width == 0 || image.height == 0 {
+            return Color::new(1.0, 0.0, 1.0);
+        }
+
+        // v = 0 correspond au bas de l'image
+        let u = common::clamp(u, 0.0, 1.0);
+        let v = 1.0 - common::clamp(v, 0.0, 1.0);
+        let i = ((u * image.width as f64) as usize).min(image.width - 1);
+        let j = ((v * image.height as f64) as usize).min(image.height - 1);
+        image.pixel(i, j)
+    }
+}
+
+// Générateur de bruit partagé par les textures procédurales.
+// La table de Perlin est construite au premier appel à partir de la graine.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NoiseSource {
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(skip)]
+    perlin: Cache<OnceLock<Perlin>>,
+}
+
+impl NoiseSource {
+    fn perlin(&self) -> &Perlin {
+        self.perlin.get_or_init(|| Perlin::new(self.seed))
+    }
+}
+
+fn default_octaves() -> u32 {
+    7
+}
+
+fn default_lacunarity() -> f64 {
+    2.0
+}
+
+fn default_gain() -> f64 {
+    0.5
+}
+
+fn default_strength() -> f64 {
+    10.0
+}
+
+fn default_wood_strength() -> f64 {
+    1.0
+}
+
+// Mélange des deux couleurs d'une texture procédurale, t dans [0, 1]
+fn mix(low: &TextureConfig, high: &TextureConfig, t: f64, u: f64, v: f64, p: &Point3) -> Color {
+    let t = common::clamp(t, 0.0, 1.0);
+    (1.0 - t) * low.value(u, v, p) + t * high.value(u, v, p)
+}
+
+// Somme d'octaves de bruit de Perlin (fBm)
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct FbmTexture {
+    pub low: Box<TextureConfig>,
+    pub high: Box<TextureConfig>,
+    pub scale: f64, // Fréquence de la première octave
+    #[serde(default = "default_octaves")]
+    pub octaves: u32,
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f64, // Facteur de fréquence entre deux octaves
+    #[serde(default = "default_gain")]
+    pub gain: f64, // Facteur d'amplitude entre deux octaves
+    #[serde(flatten)]
+    pub noise: NoiseSource,
+}
+
+impl Texture for FbmTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let n =
+            self.noise
+                .perlin()
+                .fbm(&(self.scale * *p), self.octaves, self.lacunarity, self.gain);
+        mix(&self.low, &self.high, 0.5 * (1.0 + n), u, v, p)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct TurbulenceTexture {
+    pub low: Box<TextureConfig>,
+    pub high: Box<TextureConfig>,
+    pub scale: f64,
+    #[serde(default = "default_octaves")]
+    pub depth: u32, // Nombre d'octaves
+    #[serde(flatten)]
+    pub noise: NoiseSource,
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let t = self.noise.perlin().turb(&(self.scale * *p), self.depth);
+        mix(&self.low, &self.high, t, u, v, p)
+    }
+}
+
+// Veines sinusoïdales le long de Z, déformées par la turbulence
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct MarbleTexture {
+    pub low: Box<TextureConfig>,
+    pub high: Box<TextureConfig>,
+    pub scale: f64, // Fréquence des veines
+    #[serde(default = "default_octaves")]
+    pub depth: u32,
+    #[serde(default = "default_strength")]
+    pub strength: f64, // Amplitude de la déformation
+    #[serde(flatten)]
+    pub noise: NoiseSource,
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let turb = self.noise.perlin().turb(p, self.depth);
+        let t = 0.5 * (1.0 + f64::sin(self.scale * p.z() + self.strength * turb));
+        mix(&self.low, &self.high, t, u, v, p)
+    }
+}
+
+// Cernes concentriques autour de l'axe Y, perturbés par la turbulence
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct WoodTexture {
+    pub low: Box<TextureConfig>,
+    pub high: Box<TextureConfig>,
+    pub scale: f64, // Nombre de cernes par unité
+    #[serde(default = "default_octaves")]
+    pub depth: u32,
+    #[serde(default = "default_wood_strength")]
+    pub strength: f64,
+    #[serde(flatten)]
+    pub noise: NoiseSource,
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let distance = f64::sqrt(p.x() * p.x() + p.z() * p.z());
+        let turb = self.noise.perlin().turb(p, self.depth);
+        let t = (distance * self.scale + self.strength * turb).fract();
+        mix(&self.low, &self.high, t, u, v, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(texture: &TextureConfig) -> [f64; 3] {
+        match texture {
+            TextureConfig::Solid(color) => [color.x(), color.y(), color.z()],
+            TextureConfig::Pattern(_) => panic!("expected a solid color"),
+        }
+    }
+
+    fn parse_error(text: &str) -> serde_json::Error {
+        serde_json::from_str::<TextureConfig>(text).err().unwrap()
+    }
+
+    fn color(texture: &impl Texture, u: f64, v: f64, p: Point3) -> [f64; 3] {
+        let c = texture.value(u, v, &p);
+        [c.x(), c.y(), c.z()]
+    }
+
+    const BLACK: [f64; 3] = [0.0, 0.0, 0.0];
+    const WHITE: [f64; 3] = [1.0, 1.0, 1.0];
+
+    #[test]
+    fn checkers_alternate() {
+        let checker = CheckerTexture {
+            even: Box::new(Color::new(1.0, 1.0, 1.0).into()),
+            odd: Box::new(Color::new(0.0, 0.0, 0.0).into()),
+            scale: 0.5,
+        };
+        let at = |x: f64, y: f64, z: f64| color(&checker, 0.0, 0.0, Point3::new(x, y, z));
+        assert_eq!(at(0.25, 0.25, 0.25), WHITE);
+        assert_eq!(at(0.75, 0.25, 0.25), BLACK);
+        assert_eq!(at(0.75, 0.75, 0.25), WHITE);
+        // Cases négatives : même alternance de part et d'autre de 0
+        assert_eq!(at(-0.25, 0.25, 0.25), BLACK);
+        assert_eq!(at(-0.75, 0.25, 0.25), WHITE);
+
+        let uv_checker = UvCheckerTexture {
+            even: Box::new(Color::new(1.0, 1.0, 1.0).into()),
+            odd: Box::new(Color::new(0.0, 0.0, 0.0).into()),
+            width: 4.0,
+            height: 2.0,
+        };
+        let at = |u: f64, v: f64| color(&uv_checker, u, v, Point3::default());
+        assert_eq!(at(0.1, 0.1), WHITE);
+        assert_eq!(at(0.3, 0.1), BLACK);
+        assert_eq!(at(0.3, 0.6), WHITE);
+        assert_eq!(at(0.99, 0.99), WHITE);
+    }
+
+    #[test]
+    fn image_lookup() {
+        // 2 x 2 : rouge, vert en haut ; bleu, blanc en bas
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+                Color::new(1.0, 1.0, 1.0),
+            ],
+        };
+        let mut texture = ImageTexture {
+            file: String::from("test.png"),
+            image: Cache::default(),
+        };
+        let p = Point3::default();
+        assert_eq!(color(&texture, 0.5, 0.5, p), [1.0, 0.0, 1.0]);
+
+        *texture.image = Some(Arc::new(image));
+        assert_eq!(color(&texture, 0.25, 0.75, p), [1.0, 0.0, 0.0]);
+        assert_eq!(color(&texture, 0.75, 0.75, p), [0.0, 1.0, 0.0]);
+        assert_eq!(color(&texture, 0.25, 0.25, p), [0.0, 0.0, 1.0]);
+        assert_eq!(color(&texture, 0.75, 0.25, p), WHITE);
+        // (u, v) hors de [0, 1] : bord de l'image
+        assert_eq!(color(&texture, 1.5, -0.5, p), WHITE);
+        assert_eq!(color(&texture, 1.0, 1.0, p), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn colors_and_patterns_are_told_apart() {
+        let cases = [
+            ("0.5", [0.5, 0.5, 0.5]),
+            (r##""#ff0000""##, [1.0, 0.0, 0.0]),
+            ("[0.1, 0.2, 0.3]", [0.1, 0.2, 0.3]),
+            (r#"{ "r": 0.1, "g": 0.2, "b": 0.3 }"#, [0.1, 0.2, 0.3]),
+            (r#"{ "e": [0.1, 0.2, 0.3] }"#, [0.1, 0.2, 0.3]),
+        ];
+        for (text, expected) in cases {
+            let texture: TextureConfig = serde_json::from_str(text).unwrap();
+            assert_eq!(solid(&texture), expected, "{}", text);
+        }
+
+        // `type` n'a pas besoin d'être la première clé
+        for text in [
+            r#"{ "type": "checker", "even": 1, "odd": 0, "scale": 2 }"#,
+            r#"{ "even": 1, "odd": 0, "scale": 2, "type": "checker" }"#,
+        ] {
+            let texture: TextureConfig = serde_json::from_str(text).unwrap();
+            match texture {
+                TextureConfig::Pattern(Pattern::Checker(checker)) => {
+                    assert_eq!(checker.scale, 2.0);
+                    assert_eq!(solid(&checker.even), [1.0, 1.0, 1.0]);
+                }
+                _ => panic!("expected a checker: {}", text),
+            }
+        }
+    }
+
+    #[test]
+    fn errors_keep_their_message_and_position() {
+        let text = "{\n  \"type\": \"checker\",\n  \"even\": 1,\n  \"odd\": 0\n}";
+        let error = parse_error(text);
+        assert!(
+            error.to_string().contains("missing field `scale`"),
+            "{}",
+            error
+        );
+        assert_eq!(error.line(), 5);
+
+        let error = parse_error(r#"{ "r": 1, "y": 0, "b": 0 }"#);
+        assert!(
+            error.to_string().contains("either x, y, z or r, g, b"),
+            "{}",
+            error
+        );
+
+        let error = parse_error(r#"{ "even": 1 }"#);
+        assert!(
+            error.to_string().contains("missing field `type`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn other_formats_are_read_directly() {
+        #[derive(Deserialize)]
+        struct Surface {
+            color: TextureConfig,
+            pattern: TextureConfig,
+        }
+        let surface: Surface = toml::from_str(
+            "color = \"#00ff00\"\npattern = { type = \"checker\", even = 1, odd = 0, scale = 1 }\n",
+        )
+        .unwrap();
+        assert_eq!(solid(&surface.color), [0.0, 1.0, 0.0]);
+        assert!(matches!(
+            surface.pattern,
+            TextureConfig::Pattern(Pattern::Checker(_))
+        ));
+
+        let error = toml::from_str::<Surface>("color = [1, 2]\npattern = 0\n")
+            .err()
+            .unwrap();
+        assert!(error.span().is_some(), "{}", error);
+    }
+}
@@ -0,0 +1,596 @@
+// Les comparaisons sont niées exprès : `!(x > 0.0)` refuse aussi NaN, contrairement à `x <= 0.0`
+#![allow(clippy::neg_cmp_op_on_partial_ord)]
+
+use std::collections::HashSet;
+
+use crate::{
+    background::Background,
+    camera::Camera,
+    color::Color,
+    cube::Cube,
+    cylinder::Cylinder,
+    error::Problem,
+    heightfield::Heightfield,
+    material::Material,
+    medium::{Boundary, VolumeShape},
+    mesh::Mesh,
+    metaballs::Metaballs,
+    param::Scene,
+    sdf::{Sdf, SdfNode},
+    sphere::Sphere,
+    texture::{Pattern, TextureConfig},
+    vec3::{self, Point3, Vec3},
+};
+
+// Vecteur « haut » des caméras, la direction de visée ne doit pas lui être parallèle
+const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+
+impl Scene {
+    // Vérifie la cohérence des valeurs de la scène et renvoie tous les problèmes trouvés
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut v = Validator::default();
+
+        if self.camera.is_none() && self.cameras.is_empty() {
+            v.report(
+                "camera",
+                "the scene must define a `camera` or a non-empty `cameras` list",
+            );
+        }
+        if let Some(camera) = &self.camera {
+            v.camera("camera", camera);
+        }
+        let mut names = HashSet::new();
+        for (i, named) in self.cameras.iter().enumerate() {
+            let location = format!("cameras[{}]", i);
+            // Le nom entre dans le nom des fichiers écrits : ni `/` ni `..`
+            let allowed = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+            if named.name.is_empty() {
+                v.report(format!("{}.name", location), "must not be empty");
+            } else if !named.name.chars().all(allowed) {
+                let message = format!(
+                    "must only contain letters, digits, `_` and `-` (got \"{}\")",
+                    named.name
+                );
+                v.report(format!("{}.name", location), message);
+            } else if !names.insert(named.name.as_str()) {
+                let message = format!("duplicate camera name \"{}\"", named.name);
+                v.report(format!("{}.name", location), message);
+            }
+            v.camera(&location, &named.camera);
+        }
+
+        v.non_negative("light.intensity", self.light.intensity);
+        v.color("light.color", self.light.color);
+
+        if let Some(background) = &self.background {
+            v.background(background);
+        }
+
+        for (i, sphere) in self.spheres.iter().enumerate() {
+            v.sphere(&format!("spheres[{}]", i), sphere);
+        }
+        for (i, plane) in self.planes.iter().enumerate() {
+            let location = format!("planes[{}]", i);
+            v.positive(&format!("{}.width", location), plane.width as f64);
+            v.positive(&format!("{}.height", location), plane.height as f64);
+            v.material(&location, &plane.material);
+        }
+        for (i, cube) in self.cubes.iter().enumerate() {
+            v.cube(&format!("cubes[{}]", i), cube);
+        }
+        for (i, cylinder) in self.cylinders.iter().enumerate() {
+            v.cylinder(&format!("cylinders[{}]", i), cylinder);
+        }
+        for (i, sdf) in self.sdfs.iter().enumerate() {
+            v.sdf(&format!("sdfs[{}]", i), sdf);
+        }
+        for (i, metaballs) in self.metaballs.iter().enumerate() {
+            v.metaballs(&format!("metaballs[{}]", i), metaballs);
+        }
+        for (i, heightfield) in self.heightfields.iter().enumerate() {
+            v.heightfield(&format!("heightfields[{}]", i), heightfield);
+        }
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            v.mesh(&format!("meshes[{}]", i), mesh);
+        }
+        for (i, volume) in self.volumes.iter().enumerate() {
+            let location = format!("volumes[{}]", i);
+            v.non_negative(&format!("{}.density", location), volume.density);
+            v.color(&format!("{}.albedo", location), volume.albedo);
+            let boundary = format!("{}.boundary", location);
+            match &volume.boundary {
+                VolumeShape::Sphere(Boundary(sphere)) => v.sphere(&boundary, sphere),
+                VolumeShape::Cube(Boundary(cube)) => v.cube(&boundary, cube),
+                VolumeShape::Cylinder(Boundary(cylinder)) => v.cylinder(&boundary, cylinder),
+                VolumeShape::Sdf(Boundary(sdf)) => v.sdf(&boundary, sdf),
+                VolumeShape::Metaballs(Boundary(metaballs)) => v.metaballs(&boundary, metaballs),
+                VolumeShape::Mesh(Boundary(mesh)) => v.mesh(&boundary, mesh),
+            }
+        }
+        if let Some(fog) = &self.fog {
+            v.non_negative("fog.density", fog.density);
+            v.color("fog.color", fog.color);
+        }
+
+        v.problems
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    problems: Vec<Problem>,
+}
+
+impl Validator {
+    fn report(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.problems.push(Problem {
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    fn positive(&mut self, location: &str, value: f64) {
+        if !(value > 0.0) {
+            self.report(location, format!("must be positive (got {})", value));
+        }
+    }
+
+    fn non_negative(&mut self, location: &str, value: f64) {
+        if !(value >= 0.0) {
+            self.report(location, format!("must not be negative (got {})", value));
+        }
+    }
+
+    fn non_zero(&mut self, location: &str, vector: Vec3) {
+        if vector.length_squared() == 0.0 {
+            self.report(location, "must not be a zero-length vector");
+        }
+    }
+
+    fn color(&mut self, location: &str, color: Color) {
+        if !(color.x() >= 0.0 && color.y() >= 0.0 && color.z() >= 0.0) {
+            self.report(
+                location,
+                format!("components must not be negative (got {})", color),
+            );
+        }
+    }
+
+    // Chaque composante de `min` doit être inférieure ou égale à celle de `max`
+    fn min_max(&mut self, location: &str, min: Point3, max: Point3) {
+        for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+            if min[axis] > max[axis] {
+                let message = format!(
+                    "min.{} ({}) is greater than max.{} ({})",
+                    name, min[axis], name, max[axis]
+                );
+                self.report(location, message);
+            }
+        }
+    }
+
+    fn camera(&mut self, location: &str, camera: &Camera) {
+        let direction = camera.look_at - camera.origin;
+        if direction.length_squared() == 0.0 {
+            self.report(location, "origin and look_at are the same point");
+        } else if vec3::cross(VUP, direction).length_squared() == 0.0 {
+            self.report(
+                location,
+                "looks straight up or down, the view direction must not be vertical",
+            );
+        }
+        if !(camera.fov > 0.0 && camera.fov < 180.0) {
+            let message = format!("must be between 0 and 180 degrees (got {})", camera.fov);
+            self.report(format!("{}.fov", location), message);
+        }
+        if let Some(stereo) = &camera.stereo {
+            let location = format!("{}.stereo.interocular_distance", location);
+            self.non_negative(&location, stereo.interocular_distance);
+        }
+    }
+
+    fn background(&mut self, background: &Background) {
+        match background {
+            Background::Solid { color } => self.color("background.color", *color),
+            Background::Gradient { bottom, top } => {
+                self.color("background.bottom", *bottom);
+                self.color("background.top", *top);
+            }
+            Background::Hdr(hdr) => self.non_negative("background.intensity", hdr.intensity),
+            Background::Sky(sky) => {
+                self.non_zero("background.sun_direction", sky.sun_direction);
+                if !(sky.turbidity >= 1.0) {
+                    let message = format!("must be at least 1 (got {})", sky.turbidity);
+                    self.report("background.turbidity", message);
+                }
+                self.non_negative("background.intensity", sky.intensity);
+                self.color("background.ground", sky.ground);
+            }
+        }
+    }
+
+    fn material(&mut self, location: &str, material: &Material) {
+        self.texture(&format!("{}.color", location), &material.color);
+        if let Some(normal_map) = &material.normal_map {
+            self.texture(&format!("{}.normal_map", location), normal_map);
+        }
+        if let Some(bump_map) = &material.bump_map {
+            self.texture(&format!("{}.bump_map", location), bump_map);
+        }
+    }
+
+    fn texture(&mut self, location: &str, texture: &TextureConfig) {
+        let TextureConfig::Pattern(pattern) = texture else {
+            return;
+        };
+        let mut pair = |low: &TextureConfig, high: &TextureConfig, names: [&str; 2]| {
+            self.texture(&format!("{}.{}", location, names[0]), low);
+            self.texture(&format!("{}.{}", location, names[1]), high);
+        };
+        match pattern {
+            Pattern::Solid(_) => {}
+            Pattern::Checker(checker) => {
+                pair(&checker.even, &checker.odd, ["even", "odd"]);
+                self.positive(&format!("{}.scale", location), checker.scale);
+            }
+            Pattern::UvChecker(checker) => {
+                pair(&checker.even, &checker.odd, ["even", "odd"]);
+                self.positive(&format!("{}.width", location), checker.width);
+                self.positive(&format!("{}.height", location), checker.height);
+            }
+            Pattern::Image(image) => {
+                if image.file.is_empty() {
+                    self.report(format!("{}.file", location), "must not be empty");
+                }
+            }
+            Pattern::Fbm(noise) => {
+                pair(&noise.low, &noise.high, ["low", "high"]);
+                self.positive(&format!("{}.scale", location), noise.scale);
+            }
+            Pattern::Turbulence(noise) => pair(&noise.low, &noise.high, ["low", "high"]),
+            Pattern::Marble(marble) => pair(&marble.low, &marble.high, ["low", "high"]),
+            Pattern::Wood(wood) => pair(&wood.low, &wood.high, ["low", "high"]),
+        }
+    }
+
+    fn sdf_node(&mut self, location: &str, node: &SdfNode) {
+        let field = |name: &str| format!("{}.{}", location, name);
+        match node {
+            SdfNode::Sphere { radius, .. } => self.positive(&field("radius"), *radius),
+            SdfNode::Box { half_size, .. } => self.half_size(&field("half_size"), *half_size),
+            SdfNode::RoundBox {
+                half_size, radius, ..
+            } => {
+                self.half_size(&field("half_size"), *half_size);
+                self.non_negative(&field("radius"), *radius);
+            }
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+                ..
+            } => {
+                self.positive(&field("major_radius"), *major_radius);
+                self.positive(&field("minor_radius"), *minor_radius);
+            }
+            SdfNode::Capsule { radius, .. } => self.positive(&field("radius"), *radius),
+            SdfNode::Mandelbulb {
+                scale, iterations, ..
+            } => {
+                self.positive(&field("scale"), *scale);
+                if *iterations == 0 {
+                    self.report(field("iterations"), "must be at least 1");
+                }
+            }
+            SdfNode::Union { children } | SdfNode::Intersection { children } => {
+                self.sdf_children(location, children);
+            }
+            SdfNode::SmoothUnion { children, k } => {
+                self.sdf_children(location, children);
+                self.non_negative(&field("k"), *k);
+            }
+            SdfNode::Subtraction { base, subtract } => {
+                self.sdf_node(&field("base"), base);
+                self.sdf_node(&field("subtract"), subtract);
+            }
+            SdfNode::SmoothSubtraction { base, subtract, k } => {
+                self.sdf_node(&field("base"), base);
+                self.sdf_node(&field("subtract"), subtract);
+                self.positive(&field("k"), *k);
+            }
+            SdfNode::Scale { factor, child } => {
+                self.positive(&field("factor"), *factor);
+                self.sdf_node(&field("child"), child);
+            }
+            SdfNode::Repeat { period, child } => {
+                if period.x() < 0.0 || period.y() < 0.0 || period.z() < 0.0 {
+                    let message = format!("components must not be negative (got {})", period);
+                    self.report(field("period"), message);
+                }
+                self.sdf_node(&field("child"), child);
+            }
+            SdfNode::Translate { child, .. } | SdfNode::Twist { child, .. } => {
+                self.sdf_node(&field("child"), child);
+            }
+        }
+    }
+
+    fn sdf_children(&mut self, location: &str, children: &[SdfNode]) {
+        if children.is_empty() {
+            self.report(format!("{}.children", location), "must not be empty");
+        }
+        for (i, child) in children.iter().enumerate() {
+            self.sdf_node(&format!("{}.children[{}]", location, i), child);
+        }
+    }
+
+    fn half_size(&mut self, location: &str, half_size: Vec3) {
+        if half_size.x() < 0.0 || half_size.y() < 0.0 || half_size.z() < 0.0 {
+            let message = format!("components must not be negative (got {})", half_size);
+            self.report(location, message);
+        }
+    }
+
+    fn sphere(&mut self, location: &str, sphere: &Sphere) {
+        self.positive(&format!("{}.radius", location), sphere.radius);
+        self.material(location, &sphere.material);
+    }
+
+    fn cube(&mut self, location: &str, cube: &Cube) {
+        self.min_max(location, cube.min, cube.max);
+        self.material(location, &cube.material);
+    }
+
+    fn cylinder(&mut self, location: &str, cylinder: &Cylinder) {
+        self.positive(&format!("{}.radius", location), cylinder.radius);
+        self.positive(&format!("{}.height", location), cylinder.height);
+        self.material(location, &cylinder.material);
+    }
+
+    fn sdf(&mut self, location: &str, sdf: &Sdf) {
+        self.sdf_node(&format!("{}.root", location), &sdf.root);
+        if sdf.max_steps == 0 {
+            self.report(format!("{}.max_steps", location), "must be at least 1");
+        }
+        self.positive(&format!("{}.epsilon", location), sdf.epsilon);
+        self.positive(&format!("{}.max_distance", location), sdf.max_distance);
+        self.positive(&format!("{}.step_scale", location), sdf.step_scale);
+        if let Some(bounds) = &sdf.bounds {
+            self.positive(&format!("{}.bounds.radius", location), bounds.radius);
+        }
+        self.material(location, &sdf.material);
+    }
+
+    fn metaballs(&mut self, location: &str, metaballs: &Metaballs) {
+        if metaballs.balls.is_empty() {
+            self.report(format!("{}.balls", location), "must not be empty");
+        }
+        for (j, ball) in metaballs.balls.iter().enumerate() {
+            self.positive(&format!("{}.balls[{}].radius", location, j), ball.radius);
+        }
+        if !(metaballs.threshold > 0.0 && metaballs.threshold < 1.0) {
+            let message = format!("must be between 0 and 1 (got {})", metaballs.threshold);
+            self.report(format!("{}.threshold", location), message);
+        }
+        self.material(location, &metaballs.material);
+    }
+
+    fn heightfield(&mut self, location: &str, heightfield: &Heightfield) {
+        self.positive(&format!("{}.width", location), heightfield.width);
+        self.positive(&format!("{}.depth", location), heightfield.depth);
+
+        match (&heightfield.file, &heightfield.heights) {
+            (None, None) => self.report(location, "needs either `file` or `heights`"),
+            (Some(_), Some(_)) => self.report(location, "`file` and `heights` cannot both be set"),
+            (Some(file), None) if file.is_empty() => {
+                self.report(format!("{}.file", location), "must not be empty");
+            }
+            (Some(_), None) => {}
+            (None, Some(heights)) => {
+                let location = format!("{}.heights", location);
+                let nx = heights.first().map_or(0, |row| row.len());
+                if heights.len() < 2 || nx < 2 {
+                    self.report(&location, "the grid needs at least 2 rows of 2 values");
+                }
+                for (j, row) in heights.iter().enumerate() {
+                    if row.len() != nx {
+                        let message = format!("has {} values, expected {}", row.len(), nx);
+                        self.report(format!("{}[{}]", location, j), message);
+                    }
+                }
+            }
+        }
+
+        self.material(location, &heightfield.material);
+    }
+
+    fn mesh(&mut self, location: &str, mesh: &Mesh) {
+        let vertices = mesh.positions.len();
+        if mesh.triangles.is_empty() {
+            self.report(format!("{}.triangles", location), "must not be empty");
+        }
+        for (j, triangle) in mesh.triangles.iter().enumerate() {
+            if let Some(k) = triangle.iter().find(|&&k| k >= vertices) {
+                let message = format!(
+                    "index {} is out of range, the mesh has {} positions",
+                    k, vertices
+                );
+                self.report(format!("{}.triangles[{}]", location, j), message);
+            }
+        }
+        for (name, len) in [("normals", mesh.normals.len()), ("uvs", mesh.uvs.len())] {
+            if len != 0 && len != vertices {
+                let message = format!(
+                    "has {} values, expected one per position ({})",
+                    len, vertices
+                );
+                self.report(format!("{}.{}", location, name), message);
+            }
+        }
+        self.material(location, &mesh.material);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    // Scène minimale et valide, complétée ou modifiée par chaque test
+    fn problems(changes: Value) -> Vec<Problem> {
+        let mut document = json!({
+            "camera": { "origin": [0.0, 1.0, -4.0], "look_at": [0.0, 0.0, 0.0], "fov": 60.0 },
+            "light": { "position": [2.0, 6.0, -4.0], "color": 1.0, "intensity": 1.0 }
+        });
+        for (key, value) in changes.as_object().unwrap() {
+            document[key] = value.clone();
+        }
+        let scene: Scene = serde_json::from_value(document).unwrap();
+        scene.validate()
+    }
+
+    fn problem(location: &str, message: &str) -> Problem {
+        Problem {
+            location: location.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn valid_scene_has_no_problems() {
+        assert_eq!(problems(json!({})), []);
+    }
+
+    #[test]
+    fn negative_radius() {
+        let spheres = json!([
+            { "center": [0.0, 0.0, 0.0], "radius": 1.0, "color": 1.0 },
+            { "center": [0.0, 0.0, 0.0], "radius": -0.5, "color": 1.0 }
+        ]);
+        assert_eq!(
+            problems(json!({ "spheres": spheres })),
+            [problem("spheres[1].radius", "must be positive (got -0.5)")]
+        );
+    }
+
+    #[test]
+    fn nan_sizes_are_refused() {
+        // JSON n'a pas de NaN : la valeur est mise après le chargement
+        let mut scene: Scene = serde_json::from_value(json!({
+            "camera": { "origin": [0.0, 1.0, -4.0], "look_at": [0.0, 0.0, 0.0] },
+            "light": { "position": [2.0, 6.0, -4.0], "color": 1.0, "intensity": 1.0 },
+            "spheres": [{ "center": [0.0, 0.0, 0.0], "radius": 1.0, "color": 1.0 }]
+        }))
+        .unwrap();
+        scene.spheres[0].radius = f64::NAN;
+        scene.light.intensity = f64::NAN;
+        assert_eq!(
+            scene.validate(),
+            [
+                problem("light.intensity", "must not be negative (got NaN)"),
+                problem("spheres[0].radius", "must be positive (got NaN)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn min_greater_than_max() {
+        let cubes = json!([{ "min": [1.0, 0.0, 2.0], "max": [0.0, 1.0, 1.0], "color": 1.0 }]);
+        assert_eq!(
+            problems(json!({ "cubes": cubes })),
+            [
+                problem("cubes[0]", "min.x (1) is greater than max.x (0)"),
+                problem("cubes[0]", "min.z (2) is greater than max.z (1)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_length_vector() {
+        let background = json!({ "type": "sky", "sun_direction": [0.0, 0.0, 0.0] });
+        assert_eq!(
+            problems(json!({ "background": background })),
+            [problem(
+                "background.sun_direction",
+                "must not be a zero-length vector"
+            )]
+        );
+    }
+
+    #[test]
+    fn origin_equals_look_at() {
+        let cameras = json!([
+            { "name": "front", "origin": [0.0, 1.0, -4.0], "look_at": [0.0, 0.0, 0.0] },
+            { "name": "stuck", "origin": [1.0, 2.0, 3.0], "look_at": [1.0, 2.0, 3.0] }
+        ]);
+        assert_eq!(
+            problems(json!({ "cameras": cameras })),
+            [problem(
+                "cameras[1]",
+                "origin and look_at are the same point"
+            )]
+        );
+    }
+
+    #[test]
+    fn camera_names_stay_in_the_output_directory() {
+        let camera = |name: &str| json!({ "name": name, "origin": [0.0, 1.0, -4.0], "look_at": [0.0, 0.0, 0.0] });
+        let cameras = json!([
+            camera("front_2-wide"),
+            camera("../escape"),
+            camera("a/b"),
+            camera("")
+        ]);
+        assert_eq!(
+            problems(json!({ "cameras": cameras })),
+            [
+                problem(
+                    "cameras[1].name",
+                    "must only contain letters, digits, `_` and `-` (got \"../escape\")"
+                ),
+                problem(
+                    "cameras[2].name",
+                    "must only contain letters, digits, `_` and `-` (got \"a/b\")"
+                ),
+                problem("cameras[3].name", "must not be empty"),
+            ]
+        );
+    }
+
+    #[test]
+    fn volume_boundaries_are_checked_like_objects() {
+        let volumes = json!([
+            { "boundary": { "type": "sphere", "center": [0.0, 0.0, 0.0], "radius": -1.0 },
+              "density": 1.0, "albedo": 1.0 },
+            { "boundary": { "type": "metaballs", "balls": [], "threshold": 2.0 },
+              "density": 1.0, "albedo": 1.0 }
+        ]);
+        assert_eq!(
+            problems(json!({ "volumes": volumes })),
+            [
+                problem("volumes[0].boundary.radius", "must be positive (got -1)"),
+                problem("volumes[1].boundary.balls", "must not be empty"),
+                problem(
+                    "volumes[1].boundary.threshold",
+                    "must be between 0 and 1 (got 2)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn texture_sizes_must_be_positive() {
+        let spheres = json!([{
+            "center": [0.0, 0.0, 0.0],
+            "radius": 1.0,
+            "color": { "type": "uv_checker", "even": 1.0, "odd": 0.0, "width": 0.0, "height": 4.0 },
+            "bump_map": { "type": "fbm", "low": 0.0, "high": 1.0, "scale": -2.0 }
+        }]);
+        assert_eq!(
+            problems(json!({ "spheres": spheres })),
+            [
+                problem("spheres[0].color.width", "must be positive (got 0)"),
+                problem("spheres[0].bump_map.scale", "must be positive (got -2)"),
+            ]
+        );
+    }
+}
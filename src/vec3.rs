@@ -1,9 +1,15 @@
 use std::{
-    fmt::{Display, Formatter, Result},
-    ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub},
+    fmt::{self, Display, Formatter, Result},
+    ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub},
 };
 
-#[derive(Copy, Clone, Default, serde::Serialize, serde::Deserialize)]
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Vec3 {
     e: [f64; 3],
 }
@@ -22,8 +28,19 @@ impl Index<usize> for Vec3 {
     }
 }
 
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        match i {
+            0 => &mut self.e[0],
+            1 => &mut self.e[1],
+            2 => &mut self.e[2],
+            _ => panic!("Index out of range"),
+        }
+    }
+}
+
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Vec3 {
         Vec3 { e: [x, y, z] }
     }
 
@@ -156,3 +173,256 @@ pub fn cross(u: Vec3, v: Vec3) -> Vec3 {
 pub fn unit_vector(v: Vec3) -> Vec3 {
     v / v.length()
 }
+
+// Écrit sous forme de tableau [x, y, z]
+impl Serialize for Vec3 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(3)?;
+        for value in &self.e {
+            tuple.serialize_element(value)?;
+        }
+        tuple.end()
+    }
+}
+
+// Formes acceptées dans les scènes : [x, y, z], { "x", "y", "z" }, { "r", "g", "b" }
+// ou l'ancien { "e": [x, y, z] }. Les couleurs passent par `color::deserialize`.
+impl<'de> Deserialize<'de> for Vec3 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(Vec3Visitor { color: false })
+    }
+}
+
+// `color` autorise en plus un nombre (gris) ou une couleur hexadécimale "#ff8800"
+pub(crate) struct Vec3Visitor {
+    pub(crate) color: bool,
+}
+
+impl<'de> Visitor<'de> for Vec3Visitor {
+    type Value = Vec3;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        if self.color {
+            write!(
+                f,
+                "a color as [r, g, b], {{r, g, b}}, a number or a \"#rrggbb\" string"
+            )
+        } else {
+            write!(f, "a vector as [x, y, z] or {{x, y, z}}")
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> std::result::Result<Vec3, E> {
+        if !self.color {
+            return Err(E::custom(format!(
+                "a single number ({}) is only accepted for colors, write vectors as [x, y, z]",
+                value
+            )));
+        }
+        Ok(Vec3::new(value, value, value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Vec3, E> {
+        self.visit_f64(value as f64)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Vec3, E> {
+        self.visit_f64(value as f64)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Vec3, E> {
+        if !self.color {
+            return Err(E::custom(format!(
+                "a string (\"{}\") is only accepted for colors, write vectors as [x, y, z]",
+                value
+            )));
+        }
+        parse_hex_color(value)
+            .ok_or_else(|| E::custom(format!("invalid color \"{}\", expected \"#rrggbb\"", value)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Vec3, A::Error> {
+        let mut e = [0.0; 3];
+        for (i, value) in e.iter_mut().enumerate() {
+            *value = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(4, &self));
+        }
+        Ok(Vec3 { e })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Vec3, A::Error> {
+        const FIELDS: &[&str] = &["x", "y", "z", "r", "g", "b", "e"];
+        let mut components: [Option<f64>; 3] = [None; 3];
+        let mut style: Option<&str> = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            let (axis, key_style) = match key.as_str() {
+                "x" => (0, "xyz"),
+                "y" => (1, "xyz"),
+                "z" => (2, "xyz"),
+                "r" => (0, "rgb"),
+                "g" => (1, "rgb"),
+                "b" => (2, "rgb"),
+                "e" => {
+                    if style.is_some() {
+                        return Err(de::Error::custom(
+                            "`e` cannot be mixed with other components",
+                        ));
+                    }
+                    let e: [f64; 3] = map.next_value()?;
+                    components = e.map(Some);
+                    style = Some("e");
+                    continue;
+                }
+                _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+            };
+
+            if style.is_some_and(|s| s != key_style) {
+                return Err(de::Error::custom(
+                    "vector components must be either x, y, z or r, g, b",
+                ));
+            }
+            style = Some(key_style);
+            if components[axis].is_some() {
+                return Err(de::Error::custom(format!("duplicate component `{}`", key)));
+            }
+            components[axis] = Some(map.next_value()?);
+        }
+
+        let names = if style == Some("rgb") {
+            ["r", "g", "b"]
+        } else {
+            ["x", "y", "z"]
+        };
+        let mut e = [0.0; 3];
+        for axis in 0..3 {
+            e[axis] = components[axis].ok_or_else(|| de::Error::missing_field(names[axis]))?;
+        }
+        Ok(Vec3 { e })
+    }
+}
+
+// "#rrggbb" ou "#rgb", composantes ramenées dans [0, 1]
+fn parse_hex_color(value: &str) -> Option<Vec3> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok().map(|c| c as f64 / 255.0);
+    let (r, g, b) = match hex.len() {
+        6 => (
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ),
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1].repeat(2));
+            (short(0)?, short(1)?, short(2)?)
+        }
+        _ => return None,
+    };
+    Some(Vec3::new(r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+
+    fn vector(text: &str) -> std::result::Result<[f64; 3], String> {
+        serde_json::from_str::<Vec3>(text)
+            .map(|v| v.e)
+            .map_err(|e| e.to_string())
+    }
+
+    fn color(text: &str) -> std::result::Result<[f64; 3], String> {
+        color::deserialize(&mut serde_json::Deserializer::from_str(text))
+            .map(|c| c.e)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn accepted_vector_forms() {
+        let cases = [
+            ("[1, 2.5, -3]", [1.0, 2.5, -3.0]),
+            (r#"{ "x": 1, "y": 2.5, "z": -3 }"#, [1.0, 2.5, -3.0]),
+            (r#"{ "z": -3, "x": 1, "y": 2.5 }"#, [1.0, 2.5, -3.0]),
+            (r#"{ "r": 1, "g": 2.5, "b": -3 }"#, [1.0, 2.5, -3.0]),
+            (r#"{ "e": [1, 2.5, -3] }"#, [1.0, 2.5, -3.0]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(vector(text), Ok(expected), "{}", text);
+            assert_eq!(color(text), Ok(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn accepted_color_only_forms() {
+        let cases = [
+            ("0.5", [0.5, 0.5, 0.5]),
+            ("1", [1.0, 1.0, 1.0]),
+            (r##""#ff8000""##, [1.0, 128.0 / 255.0, 0.0]),
+            (r##""#FF8000""##, [1.0, 128.0 / 255.0, 0.0]),
+            (r##""#f80""##, [1.0, 136.0 / 255.0, 0.0]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(color(text), Ok(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejected_forms() {
+        let cases = [
+            ("[1, 2]", "invalid length 2"),
+            ("[1, 2, 3, 4]", "invalid length 4"),
+            (r#"{ "x": 1, "y": 2 }"#, "missing field `z`"),
+            (r#"{ "r": 1, "g": 2 }"#, "missing field `b`"),
+            (r#"{ "x": 1, "g": 2, "z": 3 }"#, "either x, y, z or r, g, b"),
+            (r#"{ "x": 1, "x": 2, "z": 3 }"#, "duplicate component `x`"),
+            (r#"{ "x": 1, "e": [1, 2, 3] }"#, "`e` cannot be mixed"),
+            (r#"{ "e": [1, 2, 3], "x": 1 }"#, "either x, y, z or r, g, b"),
+            (r#"{ "w": 1 }"#, "unknown field `w`"),
+            ("true", "invalid type: boolean"),
+        ];
+        for (text, message) in cases {
+            for result in [vector(text), color(text)] {
+                let error = result.expect_err(text);
+                assert!(error.contains(message), "{}: {}", text, error);
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_colors() {
+        let cases = [
+            (r##""#ff80""##, "invalid color \"#ff80\""),
+            (r##""#gg0000""##, "invalid color \"#gg0000\""),
+            (r#""ff8000""#, "invalid color \"ff8000\""),
+            (r##""#ffé0""##, "invalid color"),
+        ];
+        for (text, message) in cases {
+            let error = color(text).expect_err(text);
+            assert!(error.contains(message), "{}: {}", text, error);
+        }
+    }
+
+    #[test]
+    fn color_forms_are_refused_for_vectors() {
+        let error = vector("2").unwrap_err();
+        assert!(
+            error.contains("a single number (2) is only accepted for colors"),
+            "{}",
+            error
+        );
+        let error = vector(r##""#ff8000""##).unwrap_err();
+        assert!(
+            error.contains("a string (\"#ff8000\") is only accepted for colors"),
+            "{}",
+            error
+        );
+    }
+}